@@ -0,0 +1,86 @@
+//! Helpers to build and simulate Anchor program instructions.
+//!
+//! Enabled with the `anchor` feature.
+
+use std::collections::HashMap;
+
+use borsh::BorshSerialize;
+use solana_sdk::account::AccountSharedData;
+use solana_sdk::hash::hashv;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::message::Message;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+
+use crate::error::SolanaClientExtError;
+use crate::state::return_struct::RawSimulationResult;
+use crate::state::rollup_channel::RollUpChannel;
+use crate::AnalysisConfig;
+
+/// Lamports held by the payer `simulate_anchor_ix` funds.
+const ANCHOR_PAYER_LAMPORTS: u64 = 1_000 * LAMPORTS_PER_SOL;
+
+/// Returns the 8-byte discriminator Anchor prefixes an instruction's data with:
+/// the first bytes of `sha256("global:<ix_name>")`.
+///
+/// `ix_name` is the instruction's snake_case name, as declared in the program.
+pub fn anchor_discriminator(ix_name: &str) -> [u8; 8] {
+    let hash = hashv(&[b"global:", ix_name.as_bytes()]);
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash.to_bytes()[..8]);
+    discriminator
+}
+
+/// Builds an Anchor instruction from its name and Borsh-serializable arguments.
+///
+/// `accounts` must be in the order the instruction's `Accounts` struct declares them.
+pub fn anchor_instruction(
+    program_id: Pubkey,
+    accounts: Vec<AccountMeta>,
+    ix_name: &str,
+    args: &impl BorshSerialize,
+) -> Result<Instruction, SolanaClientExtError> {
+    let mut data = anchor_discriminator(ix_name).to_vec();
+    args.serialize(&mut data).map_err(|err| {
+        SolanaClientExtError::TransactionBuildError(format!(
+            "failed to serialize arguments of `{}`: {}",
+            ix_name, err
+        ))
+    })?;
+    Ok(Instruction::new_with_bytes(program_id, &data, accounts))
+}
+
+impl RollUpChannel<'_> {
+    /// Simulates a single Anchor instruction, given its name and arguments.
+    ///
+    /// Builds the instruction with `anchor_instruction` and simulates it in a
+    /// transaction paid by a throwaway payer funded with 1,000 SOL, so no real
+    /// payer is needed. The payer exists for this call only. Signatures aren't
+    /// checked, so signer accounts need no keypairs.
+    pub fn simulate_anchor_ix(
+        &self,
+        program_id: Pubkey,
+        accounts: Vec<AccountMeta>,
+        ix_name: &str,
+        args: impl BorshSerialize,
+    ) -> Result<RawSimulationResult, SolanaClientExtError> {
+        let instruction = anchor_instruction(program_id, accounts, ix_name, &args)?;
+        let payer = Pubkey::new_unique();
+        let transaction = Transaction::new_unsigned(Message::new(&[instruction], Some(&payer)));
+        let overrides = HashMap::from([(
+            payer,
+            AccountSharedData::new(ANCHOR_PAYER_LAMPORTS, 0, &system_program::id()),
+        )]);
+
+        Ok(self
+            .simulate_transactions_raw_with_overrides(
+                &[transaction],
+                &AnalysisConfig::default(),
+                &overrides,
+            )
+            .pop()
+            .unwrap_or_else(RawSimulationResult::base_no_results))
+    }
+}
@@ -1,21 +1,51 @@
-use std::error::Error;
-use std::fmt::{Display, Formatter};
+use solana_client::client_error::ClientError;
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum SolanaClientExtError {
+    #[error("RPC error: {0}")]
     RpcError(String),
+    #[error("Compute Units error: {0}")]
     ComputeUnitsError(String),
+    #[error("Blockhash error: {0}")]
+    BlockhashError(String),
+    #[error("Transaction decode error: {0}")]
+    TransactionDecodeError(String),
+    #[error("Transaction build error: {0}")]
+    TransactionBuildError(String),
+    #[error("Priority fee fetch error: {0}")]
+    PriorityFeeFetchError(String),
+    #[error("Fee model error: {0}")]
+    FeeModelError(String),
+    #[error("Account data too large: {pubkey} has {data_len} bytes (limit {max_data_len} bytes)")]
+    AccountDataTooLarge {
+        pubkey: Pubkey,
+        data_len: usize,
+        max_data_len: usize,
+    },
+    #[error("Compute units out of range: consumed {actual} CUs, expected {min}..={max}")]
+    CuOutOfRange { actual: u64, min: u64, max: u64 },
+    #[error("Insufficient funds: fee payer {fee_payer} can't cover the transaction")]
+    InsufficientFunds { fee_payer: Pubkey },
+    #[error("Account not found: {pubkey}")]
+    AccountNotFound { pubkey: Pubkey },
+    #[error("Missing signer: {pubkey} must sign the transaction")]
+    MissingSigner { pubkey: Pubkey },
+    #[error("Simulation failed: {0}")]
+    SimulationFailed(String),
+    #[error("Invalid heap size: {bytes} bytes isn't a multiple of 1024 between 32 KiB and 256 KiB")]
+    InvalidHeapSize { bytes: u32 },
+    #[error("Fee cap exceeded: base fee of {base_fee} lamports is above the {max_fee} lamport cap")]
+    FeeCapExceeded { base_fee: u64, max_fee: u64 },
+    /// An error returned by the underlying `RpcClient`, kept as the error source.
+    /// Boxed to keep `Result<_, SolanaClientExtError>` small.
+    #[error("RPC client error: {0}")]
+    ClientError(#[source] Box<ClientError>),
 }
 
-impl Display for SolanaClientExtError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            SolanaClientExtError::RpcError(ref err) => write!(f, "RPC error: {}", err),
-            SolanaClientExtError::ComputeUnitsError(ref err) => {
-                write!(f, "Compute Units error: {}", err)
-            }
-        }
+impl From<ClientError> for SolanaClientExtError {
+    fn from(err: ClientError) -> Self {
+        SolanaClientExtError::ClientError(Box::new(err))
     }
 }
-
-impl Error for SolanaClientExtError {}
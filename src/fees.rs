@@ -0,0 +1,213 @@
+//! Fee calculation helpers and the pluggable priority fee model.
+
+use anyhow::Result;
+use solana_compute_budget::compute_budget_limits::{
+    DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT, MAX_COMPUTE_UNIT_LIMIT,
+};
+use solana_sdk::fee::FeeStructure;
+use solana_sdk::message::Message;
+use solana_sdk::native_token::{lamports_to_sol, LAMPORTS_PER_SOL};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+
+use crate::{parse_compute_budget, EstimatedPrioritizationFee};
+
+/// A custom priority fee model used by `RollUpChannel` when
+/// `AnalysisConfig::calculate_priority_fee` is set.
+///
+/// Replaces the built-in logic, which takes the highest recent prioritization
+/// fee reported by the RPC for the transaction's accounts.
+pub trait FeeModel: Send + Sync {
+    /// Estimates the prioritization fee for a transaction touching `accounts`
+    /// that consumes `cu` compute units.
+    fn estimate_priority_fee(
+        &self,
+        accounts: &[Pubkey],
+        cu: u64,
+    ) -> Result<EstimatedPrioritizationFee>;
+}
+
+/// Base and priority fee of a single transaction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TotalFee {
+    /// Compute units the fee was estimated for.
+    pub cu: u64,
+    /// Signature and write-lock fees, in lamports.
+    pub base_fee_lamports: u64,
+    /// Prioritization fee, in lamports.
+    pub priority_fee_lamports: u64,
+    /// Sum of the base and priority fees, in lamports.
+    pub total_lamports: u64,
+}
+
+impl TotalFee {
+    /// Returns the total fee in SOL.
+    pub fn total_fee_sol(&self) -> f64 {
+        lamports_to_sol(self.total_lamports)
+    }
+
+    /// Renders the total fee for display, e.g. `"0.000012 SOL (12000 lamports)"`.
+    pub fn format_fee(&self) -> String {
+        format_lamports(self.total_lamports)
+    }
+}
+
+/// Fees for a batch of transactions, as returned by `RollUpChannel::estimate_batch_fees`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchFeeEstimate {
+    /// Sum of every transaction's total fee, in lamports.
+    pub total_lamports: u64,
+    /// Fee of each transaction, in input order.
+    pub per_tx: Vec<TotalFee>,
+}
+
+/// Everything a transaction takes from the payer's wallet, as returned by
+/// `RpcClientExt::estimate_full_cost`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FullCost {
+    /// Signature and write-lock fees, in lamports.
+    pub base_fee: u64,
+    /// Prioritization fee, in lamports.
+    pub priority_fee: u64,
+    /// Rent-exempt minimum of the accounts the transaction creates, in lamports.
+    /// Recoverable by closing the accounts, but paid up front.
+    pub rent_for_new_accounts: u64,
+    /// Sum of the fees and rent, in lamports.
+    pub total: u64,
+}
+
+impl FullCost {
+    /// Returns the total cost in SOL.
+    pub fn total_sol(&self) -> f64 {
+        lamports_to_sol(self.total)
+    }
+
+    /// Renders the total cost for display, e.g. `"0.00203928 SOL (2039280 lamports)"`.
+    pub fn format_total(&self) -> String {
+        format_lamports(self.total)
+    }
+}
+
+/// Estimates the write-lock fee component of a transaction, in lamports.
+///
+/// Counts the writable non-signer accounts of the message and charges
+/// `fee_structure.lamports_per_write_lock` for each. Signer write locks are
+/// already covered by the signature fee.
+pub fn estimate_write_lock_fee(transaction: &Transaction, fee_structure: &FeeStructure) -> u64 {
+    message_write_lock_fee(&transaction.message, fee_structure)
+}
+
+/// Estimates the base (non-priority) fee of a message, in lamports.
+///
+/// Charges `fee_structure.lamports_per_signature` per signature plus the
+/// write-lock fee. `num_signatures` overrides the signature count, which
+/// otherwise defaults to the header's `num_required_signatures`; use it when
+/// more signatures are added after estimation (e.g. a relayer's fee payer).
+pub fn estimate_base_fee(
+    message: &Message,
+    fee_structure: &FeeStructure,
+    num_signatures: Option<u64>,
+) -> u64 {
+    let num_signatures = num_signatures.unwrap_or(message.header.num_required_signatures as u64);
+    num_signatures
+        .saturating_mul(fee_structure.lamports_per_signature)
+        .saturating_add(message_write_lock_fee(message, fee_structure))
+}
+
+/// Charges `lamports_per_write_lock` for each writable non-signer account.
+fn message_write_lock_fee(message: &Message, fee_structure: &FeeStructure) -> u64 {
+    let writable_non_signers = (0..message.account_keys.len())
+        .filter(|&i| !message.is_signer(i) && message.is_maybe_writable(i, None))
+        .count() as u64;
+    writable_non_signers.saturating_mul(fee_structure.lamports_per_write_lock)
+}
+
+/// Returns the lowest compute unit price (micro-lamports per CU) at which a
+/// transaction using `my_cu` compute units pays a higher prioritization fee
+/// than `target_total_lamports`.
+///
+/// Uses the runtime's rounding (the fee is `price * cu / 1_000_000`, rounded up),
+/// so the result is never below 1 even when `my_cu` is far larger than the
+/// target's implied CU. Saturates at `u64::MAX` when no price is high enough,
+/// e.g. when `my_cu` is 0.
+pub fn min_price_to_outbid(my_cu: u64, target_total_lamports: u64) -> u64 {
+    if my_cu == 0 {
+        return u64::MAX;
+    }
+    let price = (target_total_lamports as u128 * 1_000_000) / my_cu as u128 + 1;
+    price.min(u64::MAX as u128) as u64
+}
+
+/// Returns the prioritization fee, in lamports, a built transaction will pay.
+///
+/// Reads its `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions and
+/// applies the runtime's rounding (`limit * price / 1_000_000`, rounded up).
+/// Without a price the fee is 0; without a limit, the runtime default is used:
+/// 200,000 CUs per non-Compute Budget instruction. Like the runtime, the limit
+/// is capped at 1,400,000 either way.
+pub fn compute_priority_fee_from_tx(transaction: &Transaction) -> u64 {
+    let settings = parse_compute_budget(transaction);
+    let message = &transaction.message;
+    let unit_limit = settings
+        .unit_limit
+        .unwrap_or_else(|| {
+            let instructions = message
+                .instructions
+                .iter()
+                .filter(|ix| {
+                    message.account_keys.get(ix.program_id_index as usize)
+                        != Some(&solana_sdk::compute_budget::id())
+                })
+                .count() as u32;
+            instructions.saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT)
+        })
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+    let unit_price = settings.unit_price.unwrap_or(0);
+    let fee = (unit_limit as u128 * unit_price as u128).div_ceil(1_000_000);
+    fee.min(u64::MAX as u128) as u64
+}
+
+/// Groups fees into `buckets` equal-width buckets spanning the observed range.
+///
+/// Returns `(bucket_lower_bound, count)` pairs in ascending order. Fewer buckets
+/// are returned when the range holds fewer distinct values than requested, and
+/// an empty vec is returned for empty input or zero buckets.
+pub fn bucket_fees(fees: &[u64], buckets: usize) -> Vec<(u64, usize)> {
+    let (Some(&min), Some(&max)) = (fees.iter().min(), fees.iter().max()) else {
+        return Vec::new();
+    };
+    if buckets == 0 {
+        return Vec::new();
+    }
+
+    let span = (max - min) as u128 + 1;
+    let buckets = (buckets as u128).min(span);
+    let width = span.div_ceil(buckets);
+
+    let mut counts = vec![0usize; buckets as usize];
+    for &fee in fees {
+        counts[((fee - min) as u128 / width) as usize] += 1;
+    }
+
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| ((min as u128 + i as u128 * width) as u64, count))
+        .collect()
+}
+
+/// Renders a lamport amount for display, e.g. `"0.000012 SOL (12000 lamports)"`.
+///
+/// The SOL amount is computed with integer math, so it is exact and has no
+/// trailing zeros.
+pub fn format_lamports(lamports: u64) -> String {
+    let whole = lamports / LAMPORTS_PER_SOL;
+    let fraction = lamports % LAMPORTS_PER_SOL;
+    let sol = if fraction == 0 {
+        whole.to_string()
+    } else {
+        let fraction = format!("{:09}", fraction);
+        format!("{}.{}", whole, fraction.trim_end_matches('0'))
+    };
+    format!("{} SOL ({} lamports)", sol, lamports)
+}
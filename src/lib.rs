@@ -73,7 +73,7 @@
 /// ```no_run
 /// use solana_client::rpc_client::RpcClient;
 /// // Assuming RollUpChannel and RpcClientExt are correctly imported from your crate
-/// use solana_client_ext::{state::rollup_channel::RollUpChannel, RpcClientExt};
+/// use solana_client_ext::{RollUpChannel, RpcClientExt};
 /// use solana_sdk::{
 ///     message::Message, pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction,
 ///     transaction::Transaction,
@@ -139,7 +139,7 @@
 /// use solana_client::rpc_client::RpcClient;
 /// // Assuming TaggedAnalysisClient, AnalysisConfig, etc. are correctly imported
 /// use solana_client_ext::{
-///     AnalysisConfig, TaggedAnalysisClient, state::return_struct::AnalysisResultDetail
+///     AnalysisConfig, TaggedAnalysisClient, AnalysisResultDetail
 /// };
 /// use solana_sdk::{
 ///     message::Message, pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction,
@@ -204,22 +204,57 @@
 ///     Ok(())
 /// }
 /// ```
-use error::SolanaClientExtError;
 use solana_client::rpc_config::RpcSimulateTransactionConfig;
 use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::lamports_to_sol;
+use solana_sdk::account::ReadableAccount;
+use solana_sdk::fee::FeeStructure;
+use solana_sdk::hash::Hash;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::transaction::TransactionError;
+use solana_sdk::message::{v0, AddressLookupTableAccount, VersionedMessage};
+use solana_sdk::transaction::VersionedTransaction;
 use solana_sdk::{message::Message, pubkey::Pubkey, signers::Signers, transaction::Transaction};
 use std::collections::HashMap;
+#[cfg(feature = "anchor")]
+pub mod anchor;
 mod error;
+pub mod fees;
+pub mod rpc_compat;
 pub mod state;
 mod utils;
 use crate::state::fork_rollup_graph::ForkRollUpGraph;
+use crate::utils::describe::describe_known_instruction;
+use crate::utils::helpers::{
+    decode_compute_budget_instruction, decompile_instructions, fee_for_message_error, fits_cu_cap,
+    is_program_load_error, missing_signer, priority_fee_fetch_error, set_compute_unit_limit,
+    set_compute_unit_price, set_heap_frame, set_loaded_accounts_data_size_limit,
+    simulate_at_max_cu_limit, units_consumed_from_simulation,
+};
 use anyhow::Result;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_client::rpc_response::RpcPrioritizationFee;
-pub use state::rollup_channel::RollUpChannel;
+pub use error::SolanaClientExtError;
+pub use fees::{
+    bucket_fees, compute_priority_fee_from_tx, estimate_base_fee, estimate_write_lock_fee,
+    format_lamports, min_price_to_outbid, BatchFeeEstimate, FeeModel, FullCost, TotalFee,
+};
+pub use state::account_source::{AccountSource, FailoverAccountSource, MockAccountSource};
+pub use state::rollup_account_loader::{
+    AccountLoadHook, OversizedAccountPolicy, RollUpAccountLoader, RpcCall,
+    DEFAULT_PREFETCH_CONCURRENCY,
+};
+pub use state::rollup_channel::{RollUpChannel, RollUpChannelConfig, DEFAULT_ITER_CHUNK_SIZE};
 pub use crate::state::return_struct::{
-    AnalysisResultDetail, ComputeUnitsDetails, RawSimulationResult, SimulationAnalysisResult,
-    PrioritizationFeeDetails,
+    total_compute_units, try_total_compute_units, AccountChangeSummary, AccountDataSizeChange,
+    AnalysisResultDetail, ComputeUnitsDetails, CuBreakdown, CuComparison, CuEstimate, Diagnostic,
+    FeesOnlyDetails, InnerInstructionCu, InstructionGroup, PrioritizationFeeDetails,
+    RawSimulationResult, SimulationAnalysisResult, SimulationFailure, TransactionSummary,
+    TxMetrics,
+};
+use solana_compute_budget::compute_budget_limits::{
+    MAX_COMPUTE_UNIT_LIMIT, MAX_LOADED_ACCOUNTS_DATA_SIZE_BYTES,
 };
 
 /// Configuration for transaction simulation analyses.
@@ -229,8 +264,204 @@ pub struct AnalysisConfig {
     pub estimate_compute_units: bool,
     /// If `true`, calculate and include prioritization fee details.
     pub calculate_priority_fee: bool,
+    /// Price, in micro-lamports per CU, used for the prioritization fee details
+    /// instead of querying recent fees. Makes fee details deterministic and
+    /// independent of RPC; takes precedence over `RollUpChannel::with_fee_model`.
+    pub fixed_fee_per_cu: Option<u64>,
     /// If `Some(tag_string)`, stores analysis results under this tag.
     pub tag: Option<String>,
+    /// Log substrings treated as failures. A transaction that succeeds but logs
+    /// a line containing any of them is reported as failed. Matching is
+    /// case-sensitive; empty by default.
+    pub fail_on_log_patterns: Vec<String>,
+}
+
+/// Safety multipliers applied to a simulated CU value.
+#[derive(Debug, Clone, Copy)]
+pub struct CuMarginConfig {
+    /// Multiplier used for `CuEstimate::recommended`.
+    pub recommended_multiplier: f64,
+    /// Multiplier used for `CuEstimate::p95`.
+    pub p95_multiplier: f64,
+}
+
+impl Default for CuMarginConfig {
+    fn default() -> Self {
+        Self {
+            recommended_multiplier: 1.1,
+            p95_multiplier: 1.5,
+        }
+    }
+}
+
+impl CuMarginConfig {
+    /// Derives a `CuEstimate` from a simulated CU value.
+    ///
+    /// Derived limits are capped at the maximum compute unit limit per transaction.
+    pub fn apply(&self, point: u64) -> CuEstimate {
+        let scale = |multiplier: f64| {
+            ((point as f64 * multiplier).round() as u64).min(MAX_COMPUTE_UNIT_LIMIT as u64)
+        };
+        CuEstimate {
+            point,
+            recommended: scale(self.recommended_multiplier),
+            p95: scale(self.p95_multiplier),
+        }
+    }
+}
+
+/// Which simulation produced a compute unit estimate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CuEstimateSource {
+    /// Local SVM simulation.
+    Local,
+    /// RPC `simulateTransaction`, used when a program couldn't be loaded locally.
+    Rpc,
+}
+
+/// Which simulation `estimate_compute_units_hybrid_with_config` trusts for CUs.
+///
+/// The local SVM's `executed_units` and RPC's `unitsConsumed` can differ
+/// slightly, e.g. when the cluster runs a different feature set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CuSource {
+    /// Local simulation only; fails if a program can't be loaded locally.
+    LocalOnly,
+    /// RPC `simulateTransaction` only.
+    RpcOnly,
+    /// Both simulations, keeping the higher count: the safest limit against
+    /// running out of CUs on-chain. Falls back to RPC alone if a program can't
+    /// be loaded locally.
+    #[default]
+    MaxOfBoth,
+}
+
+/// Configuration for `estimate_compute_units_hybrid_with_config`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HybridEstimateConfig {
+    /// Simulation the estimate comes from.
+    pub cu_source: CuSource,
+}
+
+/// Compute budget values already set by a transaction's instructions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComputeBudgetSettings {
+    /// Value of a `SetComputeUnitLimit` instruction, if present.
+    pub unit_limit: Option<u32>,
+    /// Value of a `SetComputeUnitPrice` instruction, in micro-lamports, if present.
+    pub unit_price: Option<u64>,
+    /// Value of a `RequestHeapFrame` instruction, in bytes, if present.
+    pub heap_size: Option<u32>,
+    /// Value of a `SetLoadedAccountsDataSizeLimit` instruction, in bytes, if present.
+    pub loaded_accounts_data_size_limit: Option<u32>,
+}
+
+/// Reads the Compute Budget instructions already present in a transaction.
+///
+/// Useful to decide whether to override them before inserting new ones, since
+/// a transaction with duplicate Compute Budget instructions fails. Instructions
+/// that can't be decoded are ignored; if an instruction is repeated, the last
+/// value wins.
+pub fn parse_compute_budget(transaction: &Transaction) -> ComputeBudgetSettings {
+    let message = &transaction.message;
+    let mut settings = ComputeBudgetSettings::default();
+    for ix in &message.instructions {
+        if message.account_keys.get(ix.program_id_index as usize)
+            != Some(&solana_sdk::compute_budget::id())
+        {
+            continue;
+        }
+        match decode_compute_budget_instruction(&ix.data) {
+            Some(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => {
+                settings.unit_limit = Some(limit)
+            }
+            Some(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => {
+                settings.unit_price = Some(price)
+            }
+            Some(ComputeBudgetInstruction::RequestHeapFrame(bytes)) => {
+                settings.heap_size = Some(bytes)
+            }
+            Some(ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(bytes)) => {
+                settings.loaded_accounts_data_size_limit = Some(bytes)
+            }
+            _ => {}
+        }
+    }
+    settings
+}
+
+/// Reports the serialized size, signature count and shape of a transaction.
+///
+/// Use `TxMetrics::exceeds_packet_limit` to check that it fits in the
+/// 1232-byte packet limit before sending.
+pub fn transaction_metrics(transaction: &Transaction) -> TxMetrics {
+    let message = &transaction.message;
+    TxMetrics {
+        serialized_size: bincode::serialized_size(transaction)
+            .map_or(usize::MAX, |size| size as usize),
+        num_signatures: message.header.num_required_signatures,
+        num_accounts: message.account_keys.len(),
+        num_instructions: message.instructions.len(),
+    }
+}
+
+/// Renders a transaction's instructions as human-readable text, for debugging.
+///
+/// Lists each instruction's program id, the length of its data and the
+/// accounts it references, flagged as signer and/or writable. System, Compute
+/// Budget and SPL Token instructions are also decoded to their type.
+pub fn describe_transaction(transaction: &Transaction) -> String {
+    let message = &transaction.message;
+    let mut description = String::new();
+    for (i, ix) in message.instructions.iter().enumerate() {
+        let program_id = message
+            .account_keys
+            .get(ix.program_id_index as usize)
+            .copied()
+            .unwrap_or_default();
+        match describe_known_instruction(&program_id, &ix.data) {
+            Some(known) => {
+                description.push_str(&format!("Instruction {}: {} ({})\n", i, program_id, known))
+            }
+            None => description.push_str(&format!("Instruction {}: {}\n", i, program_id)),
+        }
+        description.push_str(&format!("  data: {} bytes\n", ix.data.len()));
+        for &index in &ix.accounts {
+            let index = index as usize;
+            let pubkey = message.account_keys.get(index).copied().unwrap_or_default();
+            let roles: Vec<&str> = [
+                (message.is_signer(index), "signer"),
+                (message.is_maybe_writable(index, None), "writable"),
+            ]
+            .into_iter()
+            .filter_map(|(has_role, role)| has_role.then_some(role))
+            .collect();
+            if roles.is_empty() {
+                description.push_str(&format!("  account {}\n", pubkey));
+            } else {
+                description.push_str(&format!("  account {} [{}]\n", pubkey, roles.join(", ")));
+            }
+        }
+    }
+    description
+}
+
+/// Checks a transaction's signatures against its message, without simulating it.
+///
+/// Local simulations skip signature verification, so use this as a pre-check
+/// on received transactions. Returns the indices of the required signatures
+/// that are missing or don't verify.
+pub fn verify_signatures(transaction: &Transaction) -> Result<(), Vec<usize>> {
+    let verified = transaction.verify_with_results();
+    let num_required = transaction.message.header.num_required_signatures as usize;
+    let invalid: Vec<usize> = (0..num_required)
+        .filter(|&i| !verified.get(i).copied().unwrap_or(false))
+        .collect();
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid)
+    }
 }
 
 /// Wraps `RpcClient` to provide stateful, tagged analysis results.
@@ -264,6 +495,51 @@ pub struct EstimatedPrioritizationFee {
     pub total_fee_lamports: u64,
 }
 
+impl EstimatedPrioritizationFee {
+    /// Prices `cu` compute units at `fee_per_cu_micro_lamports`, rounding up to
+    /// whole lamports like the runtime does.
+    pub fn from_price(fee_per_cu_micro_lamports: u64, cu: u64) -> Self {
+        // 1 lamport = 1_000_000 micro-lamports
+        let total_lamports = (fee_per_cu_micro_lamports as u128 * cu as u128).div_ceil(1_000_000);
+        Self {
+            fee_per_cu_micro_lamports,
+            total_fee_lamports: total_lamports as u64,
+        }
+    }
+
+    /// Builds the `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions
+    /// for this fee, ready to prepend to a transaction's instructions.
+    pub fn to_instructions(&self, cu_limit: u32) -> [Instruction; 2] {
+        [
+            ComputeBudgetInstruction::set_compute_unit_limit(cu_limit),
+            ComputeBudgetInstruction::set_compute_unit_price(self.fee_per_cu_micro_lamports),
+        ]
+    }
+
+    /// Returns the total fee in SOL.
+    pub fn total_fee_sol(&self) -> f64 {
+        lamports_to_sol(self.total_fee_lamports)
+    }
+
+    /// Renders the total fee for display, e.g. `"0.000012 SOL (12000 lamports)"`.
+    pub fn format_fee(&self) -> String {
+        format_lamports(self.total_fee_lamports)
+    }
+}
+
+/// Returns `estimate`, or `cu` priced at `fallback_fee_per_cu_micro_lamports`
+/// if it failed, as the `*_with_fallback` estimators do.
+fn estimate_or_fallback(
+    estimate: Result<EstimatedPrioritizationFee>,
+    cu: u64,
+    fallback_fee_per_cu_micro_lamports: u64,
+) -> EstimatedPrioritizationFee {
+    estimate.unwrap_or_else(|err| {
+        log::warn!("{}; using the fallback priority fee", err);
+        EstimatedPrioritizationFee::from_price(fallback_fee_per_cu_micro_lamports, cu)
+    })
+}
+
 #[async_trait::async_trait]
 pub trait RpcClientExtAsync {
     /// Estimates the total prioritization fee in lamports for the given CU.
@@ -274,6 +550,33 @@ pub trait RpcClientExtAsync {
         accounts: Option<&[Pubkey]>,
         cu: u64,
     ) -> Result<EstimatedPrioritizationFee>;
+
+    /// Like `estimate_priority_fee_for_cu`, but prices the CUs at
+    /// `fallback_fee_per_cu_micro_lamports` if recent fees can't be fetched,
+    /// instead of failing.
+    async fn estimate_priority_fee_for_cu_with_fallback(
+        &self,
+        accounts: Option<&[Pubkey]>,
+        cu: u64,
+        fallback_fee_per_cu_micro_lamports: u64,
+    ) -> EstimatedPrioritizationFee {
+        let estimate = self.estimate_priority_fee_for_cu(accounts, cu).await;
+        estimate_or_fallback(estimate, cu, fallback_fee_per_cu_micro_lamports)
+    }
+
+    /// Estimates the prioritization fee of several transactions at once.
+    ///
+    /// Takes one `(accounts, cu)` pair per transaction, as passed to
+    /// `estimate_priority_fee_for_cu`, and returns the estimates in the same
+    /// order. Recent fees are fetched once per distinct account list, so a batch
+    /// touching the same accounts costs a single RPC call.
+    async fn estimate_priority_fees_for_cus(
+        &self,
+        requests: &[(Option<Vec<Pubkey>>, u64)],
+    ) -> Result<Vec<EstimatedPrioritizationFee>>;
+
+    /// Async version of `RpcClientExt::get_fee_for_message`.
+    async fn get_fee_for_message(&self, message: &Message) -> Result<u64>;
 }
 
 pub trait RpcClientExt {
@@ -283,48 +586,438 @@ pub trait RpcClientExt {
     ///
     /// ## Safety ⚠️
     /// No signature verification; on-chain results may differ.
+    ///
+    /// ## Partially signed transactions
+    /// Since signatures are never checked, transactions with some or all
+    /// signatures missing (e.g. a multisig transaction signed by only one
+    /// signer) can be estimated as-is, before the remaining signatures are collected.
     fn estimate_compute_units_unsigned_tx<'a, I: Signers + ?Sized>(
         &self,
         transaction: &Transaction,
         _signers: &'a I,
     ) -> Result<Vec<u64>, Box<dyn std::error::Error + 'static>>;
 
+    /// Estimates CUs for a message knowing only its signers' public keys.
+    ///
+    /// For hardware wallet flows, where private keys can't sign until the user
+    /// confirms on the device. The message is simulated locally, which skips
+    /// signature verification, so the signatures don't affect the result. Fails
+    /// with `SolanaClientExtError::MissingSigner` if a signer the message
+    /// requires is missing from `signer_pubkeys`, and with `SimulationFailed` if
+    /// the simulation fails.
+    fn estimate_compute_units_with_pubkeys(
+        &self,
+        message: &Message,
+        signer_pubkeys: &[Pubkey],
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>>;
+
+    /// Estimates CUs for a batch of **unsigned transactions**, reporting each
+    /// transaction's outcome on its own.
+    ///
+    /// Unlike `estimate_compute_units_unsigned_tx`, a failed transaction doesn't
+    /// fail the whole call: its entry holds the error and logs instead, while
+    /// the others still report their CUs. Results are in input order. A
+    /// transaction whose required signature is neither on it nor among
+    /// `signers` isn't simulated, and fails with `SignatureFailure`.
+    fn try_estimate_compute_units<'a, I: Signers + ?Sized>(
+        &self,
+        transactions: &[Transaction],
+        signers: &'a I,
+    ) -> Vec<Result<u64, SimulationFailure>>;
+
+    /// Estimates CUs locally, falling back to RPC simulation if a program can't be loaded.
+    ///
+    /// Local simulation is tried first. If it fails because a program is missing
+    /// or can't be executed locally, the transaction is signed with `signers` and
+    /// simulated with RPC `simulateTransaction` instead. Any other failure is
+    /// returned as an error. Returns the CUs and which simulation produced them.
+    fn estimate_compute_units_hybrid<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+    ) -> Result<(u64, CuEstimateSource), Box<dyn std::error::Error + 'static>>;
+
+    /// Like `estimate_compute_units_hybrid`, but `config.cu_source` picks which
+    /// simulation the estimate comes from.
+    ///
+    /// With `CuSource::MaxOfBoth`, the returned source is the simulation that
+    /// reported more CUs (local on a tie).
+    fn estimate_compute_units_hybrid_with_config<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+        config: &HybridEstimateConfig,
+    ) -> Result<(u64, CuEstimateSource), Box<dyn std::error::Error + 'static>>;
+
+    /// Estimates CUs for an **unsigned transaction** and derives safety margins.
+    ///
+    /// The point estimate comes from rollup-based simulation; `recommended` and
+    /// `p95` are derived from it using the multipliers in `margin`.
+    fn estimate_compute_units_with_margin<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+        margin: &CuMarginConfig,
+    ) -> Result<CuEstimate, Box<dyn std::error::Error + 'static>>;
+
+    /// Estimates CUs for an **unsigned transaction** and checks them against a range.
+    ///
+    /// Returns the estimated CUs if they fall within `min..=max`, or
+    /// `SolanaClientExtError::CuOutOfRange` with the actual value otherwise.
+    /// Meant for CU regression checks in program test suites.
+    fn check_cu_within<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+        min: u64,
+        max: u64,
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>>;
+
+    /// Panicking variant of `check_cu_within`, for use in tests.
+    ///
+    /// # Panics
+    /// If estimation fails or the estimated CUs fall outside `min..=max`.
+    fn assert_cu_within<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+        min: u64,
+        max: u64,
+    ) {
+        if let Err(err) = self.check_cu_within(transaction, signers, min, max) {
+            panic!("CU assertion failed: {}", err);
+        }
+    }
+
+    /// Projects the fee payer's balance after the transaction, in lamports.
+    ///
+    /// Simulates the transaction locally like `estimate_compute_units_unsigned_tx`;
+    /// the projected balance accounts for the base and priority fees charged by
+    /// the runtime as well as any lamports the transaction moves. Returns
+    /// `SolanaClientExtError::InsufficientFunds` if the payer can't cover the fees,
+    /// `AccountNotFound` if it doesn't exist, and `MissingSigner` if a required
+    /// signature is neither on the transaction nor among `signers`.
+    fn simulate_fee_payer_balance<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>>;
+
+    /// Estimates CUs both locally and through RPC `simulateTransaction`, and
+    /// reports how far apart they are.
+    ///
+    /// Use it to size safety margins, or to spot transactions the local SVM
+    /// doesn't model faithfully (e.g. missing builtins). Fails if the local
+    /// simulation fails; an RPC failure is reported in `rpc`.
+    fn compare_local_vs_rpc<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+    ) -> Result<CuComparison, Box<dyn std::error::Error + 'static>>;
+
+    /// Estimates everything the transaction takes from the payer's wallet.
+    ///
+    /// Simulates the transaction locally, then adds up the base fee, the
+    /// priority fee `fee_strategy` charges for the consumed CUs, and the
+    /// rent-exempt minimum, at the cluster's `Rent` sysvar, of every account
+    /// the transaction creates. Fails with `SolanaClientExtError::FeeModelError`
+    /// if `fee_strategy` can't price the transaction.
+    fn estimate_full_cost(
+        &self,
+        transaction: &Transaction,
+        fee_strategy: &dyn FeeModel,
+    ) -> Result<FullCost, Box<dyn std::error::Error + 'static>>;
+
     /// Estimates CUs for a message via real transaction simulation.
     ///
     /// Signs and simulates the transaction.
-    /// Returns `Ok(u64)` (CUs) or `Err` on failure/missing CU data.
+    /// Returns `Ok(u64)` (CUs) or `Err` on failure/missing CU data. Fails with
+    /// `SolanaClientExtError::TransactionBuildError` if `signers` can't sign
+    /// the message, e.g. when a required signer is missing.
     fn estimate_compute_units_msg<'a, I: Signers + ?Sized>(
         &self,
         msg: &Message,
         signers: &'a I,
     ) -> Result<u64, Box<dyn std::error::Error + 'static>>;
 
+    /// Returns the exact simulated CUs for an **unsigned transaction**, without margin.
+    ///
+    /// Separates estimation from the margin policy applied by the optimize methods,
+    /// so callers can set their own limit precisely.
+    fn estimate_optimal_compute_units_unsigned_tx<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+    ) -> Result<u32, Box<dyn std::error::Error + 'static>>;
+
+    /// Estimates CUs for a message via real transaction simulation with a custom config.
+    ///
+    /// Like `estimate_compute_units_msg`, but exposes the full
+    /// `RpcSimulateTransactionConfig` (e.g. `sig_verify`, `replace_recent_blockhash`,
+    /// `commitment`). Note that `sig_verify` and `replace_recent_blockhash`
+    /// cannot both be enabled.
+    fn estimate_compute_units_msg_with_config<'a, I: Signers + ?Sized>(
+        &self,
+        msg: &Message,
+        signers: &'a I,
+        config: RpcSimulateTransactionConfig,
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>>;
+
+    /// Estimates CUs for a message via RPC simulation without any signers.
+    ///
+    /// Simulates with `sig_verify: false` and `replace_recent_blockhash: true`,
+    /// so CUs can be estimated before all signatures are collected (e.g. multisig).
+    fn estimate_compute_units_msg_no_sigverify(
+        &self,
+        msg: &Message,
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>>;
+
+    /// Estimates the cumulative CU curve of a list of instructions.
+    ///
+    /// Simulates messages made of the first 1, 2, ..., N instructions via
+    /// `estimate_compute_units_msg` and returns the CUs at each step. Every
+    /// prefix is signed with `signers`, so each prefix must require exactly
+    /// those signers (e.g. the payer signs for every instruction).
+    fn cu_curve<'a, I: Signers + ?Sized>(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &'a I,
+    ) -> Result<Vec<u64>, Box<dyn std::error::Error + 'static>>;
+
+    /// Suggests how to split instructions into transactions that fit the CU cap.
+    ///
+    /// Greedily grows each group one instruction at a time, re-simulating the
+    /// group with the limit raised to `MAX_COMPUTE_UNIT_LIMIT`, and starts a new
+    /// group once the next instruction makes the simulation fail or reach the
+    /// cap. Returns each group's instruction indices and CUs, in order. Each group
+    /// is simulated on its own, so instructions that depend on state created by
+    /// an earlier group land in a group of their own, or fail to estimate.
+    /// Fails if a single instruction doesn't fit within the cap on its own.
+    fn suggest_instruction_split<'a, I: Signers + ?Sized>(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &'a I,
+    ) -> Result<Vec<InstructionGroup>, Box<dyn std::error::Error + 'static>>;
+
+    /// Packs instructions into signed transactions that fit the CU cap and the
+    /// 1232-byte packet limit.
+    ///
+    /// Splits the instructions with `suggest_instruction_split`, then splits any
+    /// group further whose transaction would be too large to send. Each
+    /// transaction is simulated again, gets a `SetComputeUnitLimit` instruction
+    /// sized from that estimate (with `CuMarginConfig::default()` margin), and is
+    /// signed by `signers` over `blockhash`, which must be recent enough to
+    /// simulate with. Instruction order is preserved. Fails if a single
+    /// instruction doesn't fit in a transaction on its own, or if a packed
+    /// transaction fails in simulation or reaches the CU cap.
+    fn pack_into_transactions<'a, I: Signers + ?Sized>(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &'a I,
+        blockhash: Hash,
+    ) -> Result<Vec<Transaction>, Box<dyn std::error::Error + 'static>>;
+
     /// Inserts a compute budget instruction into an unsigned transaction.
     ///
-    /// Uses CU estimation for guidance. Modifies the transaction **in-place**.
+    /// Uses CU estimation for guidance: the limit is the estimate with
+    /// `CuMarginConfig::default()` margin. Modifies the transaction **in-place**.
+    /// Returns the estimated CUs, without margin.
     fn optimize_compute_units_unsigned_tx<'a, I: Signers + ?Sized>(
         &self,
         unsigned_transaction: &mut Transaction,
         signers: &'a I,
     ) -> Result<u32, Box<dyn std::error::Error + 'static>>;
 
+    /// Like `optimize_compute_units_unsigned_tx`, but sets the limit to the
+    /// `recommended` value `margin` derives from the estimate, capped at
+    /// `MAX_COMPUTE_UNIT_LIMIT`.
+    ///
+    /// The estimate is taken with the `SetComputeUnitLimit` instruction in
+    /// place, so the CUs it costs are covered. An existing limit is replaced.
+    fn optimize_compute_units_unsigned_tx_with_margin<'a, I: Signers + ?Sized>(
+        &self,
+        unsigned_transaction: &mut Transaction,
+        signers: &'a I,
+        margin: &CuMarginConfig,
+    ) -> Result<u32, Box<dyn std::error::Error + 'static>>;
+
+    /// Like `optimize_compute_units_unsigned_tx`, but first requests a
+    /// `heap_bytes` heap frame, if given, for programs that need more than the
+    /// default 32 KiB.
+    ///
+    /// The request is in place while CUs are estimated, so the simulation runs
+    /// with the larger heap and counts the CUs it costs. `heap_bytes` must be a
+    /// multiple of 1024 between 32 KiB and 256 KiB; other sizes fail with
+    /// `SolanaClientExtError::InvalidHeapSize` before anything is simulated.
+    fn optimize_compute_units_unsigned_tx_with_heap<'a, I: Signers + ?Sized>(
+        &self,
+        unsigned_transaction: &mut Transaction,
+        signers: &'a I,
+        heap_bytes: Option<u32>,
+    ) -> Result<u32, Box<dyn std::error::Error + 'static>> {
+        if let Some(heap_bytes) = heap_bytes {
+            set_heap_frame(&mut unsigned_transaction.message, heap_bytes)?;
+        }
+        self.optimize_compute_units_unsigned_tx(unsigned_transaction, signers)
+    }
+
+    /// Sets a `SetLoadedAccountsDataSizeLimit` matching the account data the
+    /// transaction loads. Modifies the transaction **in-place**.
+    ///
+    /// Simulates the transaction locally without a limit, then requests the
+    /// observed size plus 10%, rounded up to a whole KiB and capped at the 64 MiB
+    /// maximum. A lower limit makes the transaction cheaper to schedule, but it
+    /// fails if the accounts outgrow it. Returns the limit, in bytes. Fails with
+    /// `SolanaClientExtError::MissingSigner` if a required signature is neither
+    /// on the transaction nor among `signers`, and with `SimulationFailed` if the
+    /// simulation fails.
+    fn optimize_loaded_accounts_data_size_unsigned_tx<'a, I: Signers + ?Sized>(
+        &self,
+        unsigned_transaction: &mut Transaction,
+        signers: &'a I,
+    ) -> Result<u32, Box<dyn std::error::Error + 'static>>;
+
+    /// Inserts Compute Budget instructions whose total fee stays within
+    /// `max_fee_lamports`. Modifies the transaction **in-place**.
+    ///
+    /// Sets a `SetComputeUnitLimit` from the estimated CUs (with
+    /// `CuMarginConfig::default()` margin) and a `SetComputeUnitPrice` from
+    /// recent fees on the transaction's accounts, lowered as far as needed
+    /// (down to 0) to keep the base plus priority fee under the cap. Fails with
+    /// `SolanaClientExtError::FeeCapExceeded` if the base fee alone is above it.
+    /// Returns the fees the transaction will pay.
+    fn optimize_with_fee_cap<'a, I: Signers + ?Sized>(
+        &self,
+        unsigned_transaction: &mut Transaction,
+        signers: &'a I,
+        max_fee_lamports: u64,
+    ) -> Result<TotalFee, Box<dyn std::error::Error + 'static>>;
+
+    /// Returns the Compute Budget instructions `optimize_compute_units_unsigned_tx`
+    /// would insert, without modifying the transaction.
+    ///
+    /// The first instruction sets a compute unit limit with the same margin,
+    /// covering the CUs of both instructions. A `SetComputeUnitPrice`
+    /// instruction follows, priced from recent fees on the transaction's
+    /// accounts, unless the transaction already sets a price or recent fees are
+    /// zero. Insert them wherever suits the transaction.
+    fn plan_compute_budget<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+    ) -> Result<Vec<Instruction>, Box<dyn std::error::Error + 'static>>;
+
     ///
     /// Optimizes CUs at the message level.
     ///
-    /// Similar to `optimize_compute_units_unsigned_tx`.
-    /// Useful for later transaction construction.
+    /// Similar to `optimize_compute_units_unsigned_tx`, with CUs estimated via
+    /// RPC simulation. Useful for later transaction construction.
     fn optimize_compute_units_msg<'a, I: Signers + ?Sized>(
         &self,
         message: &mut Message,
         signers: &'a I,
     ) -> Result<u32, Box<dyn std::error::Error + 'static>>;
 
+    /// Like `optimize_compute_units_msg`, but sizes the limit with `margin`.
+    ///
+    /// See `optimize_compute_units_unsigned_tx_with_margin`.
+    fn optimize_compute_units_msg_with_margin<'a, I: Signers + ?Sized>(
+        &self,
+        message: &mut Message,
+        signers: &'a I,
+        margin: &CuMarginConfig,
+    ) -> Result<u32, Box<dyn std::error::Error + 'static>>;
+
+    /// Like `optimize_compute_units_msg`, but first requests a `heap_bytes`
+    /// heap frame, if given.
+    ///
+    /// See `optimize_compute_units_unsigned_tx_with_heap`.
+    fn optimize_compute_units_msg_with_heap<'a, I: Signers + ?Sized>(
+        &self,
+        message: &mut Message,
+        signers: &'a I,
+        heap_bytes: Option<u32>,
+    ) -> Result<u32, Box<dyn std::error::Error + 'static>> {
+        if let Some(heap_bytes) = heap_bytes {
+            set_heap_frame(message, heap_bytes)?;
+        }
+        self.optimize_compute_units_msg(message, signers)
+    }
+
+    /// Builds a signed v0 transaction from a legacy message, with fees attached.
+    ///
+    /// Estimates the message's CUs, prices them with `fee_strategy` and prepends
+    /// the matching Compute Budget instructions (replacing any already in the
+    /// message). The message is then compiled against `lookup_tables`, so the
+    /// addresses they hold are referenced by index, and signed over the latest
+    /// blockhash. Fails with `SolanaClientExtError::FeeModelError` if
+    /// `fee_strategy` can't price the message.
+    fn build_versioned_with_lookup<'a, I: Signers + ?Sized>(
+        &self,
+        message: &Message,
+        lookup_tables: &[AddressLookupTableAccount],
+        fee_strategy: &dyn FeeModel,
+        signers: &'a I,
+    ) -> Result<VersionedTransaction, Box<dyn std::error::Error + 'static>>;
+
+    /// Reports which accounts referenced by a transaction exist on-chain.
+    ///
+    /// Fetches the message's account keys with `get_multiple_accounts` (in
+    /// batches of 100) and maps each key to `true` if the account exists.
+    fn check_accounts_exist(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<HashMap<Pubkey, bool>, Box<dyn std::error::Error + 'static>>;
+
+    /// Returns the base fee the cluster charges for a message, in lamports,
+    /// with RPC `getFeeForMessage`.
+    ///
+    /// Unlike `estimate_base_fee`, this is the cluster's own figure, including
+    /// any fee it charges that this crate doesn't model. The message's
+    /// `recent_blockhash` must be recent; fails with
+    /// `SolanaClientExtError::BlockhashError` if the cluster doesn't know it
+    /// (e.g. it expired). `RpcClient` has an inherent method of the same name
+    /// without that error, so call this one as
+    /// `RpcClientExt::get_fee_for_message(&client, &message)`.
+    fn get_fee_for_message(
+        &self,
+        message: &Message,
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>>;
+
     /// Estimates the total prioritization fee for the given CU (synchronous).
+    ///
+    /// Fails with `SolanaClientExtError::PriorityFeeFetchError` if recent fees
+    /// can't be fetched.
     fn estimate_priority_fee_for_cu_sync(
         &self,
         accounts: Option<&[Pubkey]>,
         cu: u64,
     ) -> Result<EstimatedPrioritizationFee>;
+
+    /// Like `estimate_priority_fee_for_cu_sync`, but prices the CUs at
+    /// `fallback_fee_per_cu_micro_lamports` if recent fees can't be fetched,
+    /// instead of failing.
+    fn estimate_priority_fee_for_cu_sync_with_fallback(
+        &self,
+        accounts: Option<&[Pubkey]>,
+        cu: u64,
+        fallback_fee_per_cu_micro_lamports: u64,
+    ) -> EstimatedPrioritizationFee {
+        let estimate = self.estimate_priority_fee_for_cu_sync(accounts, cu);
+        estimate_or_fallback(estimate, cu, fallback_fee_per_cu_micro_lamports)
+    }
+
+    /// Buckets recent prioritization fees (micro-lamports per CU) into a histogram.
+    ///
+    /// Returns `(bucket_lower_bound, count)` pairs; empty if no fee data is available.
+    fn priority_fee_histogram(
+        &self,
+        accounts: Option<&[Pubkey]>,
+        buckets: usize,
+    ) -> Result<Vec<(u64, usize)>>;
 }
 
 #[async_trait::async_trait]
@@ -337,23 +1030,49 @@ impl RpcClientExtAsync for RpcClient {
         cu: u64,                     // Target compute unit budget for which to estimate fees
     ) -> Result<EstimatedPrioritizationFee> {
         // Fetch recent prioritization fees using provided accounts or empty list if None
-        let fees: Vec<RpcPrioritizationFee> = match accounts {
-            Some(addrs) => self.get_recent_prioritization_fees(addrs).await?,
-            None => self.get_recent_prioritization_fees(&[]).await?,
-        };
+        let addrs = accounts.unwrap_or(&[]);
+        let fees: Vec<RpcPrioritizationFee> = self
+            .get_recent_prioritization_fees(addrs)
+            .await
+            .map_err(|err| priority_fee_fetch_error(addrs, err))?;
 
         // Extract the highest fee per compute unit (in micro-lamports) from the results
         let best_fee_per_cu_micro = fees.iter().map(|f| f.prioritization_fee).max().unwrap_or(0);
 
-        // Calculate total fee by multiplying best micro-lamport rate with requested CU,
-        // then convert from micro-lamports to lamports (1 lamport = 1_000_000 micro-lamports)
-        let total_lamports = (best_fee_per_cu_micro as u128 * cu as u128) / 1_000_000;
+        // Calculate total fee by multiplying best micro-lamport rate with requested CU
+        Ok(EstimatedPrioritizationFee::from_price(best_fee_per_cu_micro, cu))
+    }
 
-        // Return the total estimated fee in lamports
-        Ok(EstimatedPrioritizationFee {
-            fee_per_cu_micro_lamports: best_fee_per_cu_micro,
-            total_fee_lamports: total_lamports as u64,
-        })
+    async fn estimate_priority_fees_for_cus(
+        &self,
+        requests: &[(Option<Vec<Pubkey>>, u64)],
+    ) -> Result<Vec<EstimatedPrioritizationFee>> {
+        // Highest recent fee per account list; `None` shares the empty list.
+        let mut best_fees: HashMap<&[Pubkey], u64> = HashMap::new();
+        let mut estimates = Vec::with_capacity(requests.len());
+        for (accounts, cu) in requests {
+            let addrs = accounts.as_deref().unwrap_or(&[]);
+            let best_fee_per_cu_micro = match best_fees.get(addrs) {
+                Some(&fee) => fee,
+                None => {
+                    let fees = self
+                        .get_recent_prioritization_fees(addrs)
+                        .await
+                        .map_err(|err| priority_fee_fetch_error(addrs, err))?;
+                    let fee = fees.iter().map(|f| f.prioritization_fee).max().unwrap_or(0);
+                    best_fees.insert(addrs, fee);
+                    fee
+                }
+            };
+            estimates.push(EstimatedPrioritizationFee::from_price(best_fee_per_cu_micro, *cu));
+        }
+        Ok(estimates)
+    }
+
+    async fn get_fee_for_message(&self, message: &Message) -> Result<u64> {
+        RpcClient::get_fee_for_message(self, message)
+            .await
+            .map_err(|err| fee_for_message_error(message, err).into())
     }
 }
 
@@ -369,6 +1088,7 @@ impl RpcClientExt for solana_client::rpc_client::RpcClient {
             estimate_compute_units: true,
             calculate_priority_fee: false,
             tag: None,
+            ..AnalysisConfig::default()
         });
 
         let mut cus = Vec::new();
@@ -392,6 +1112,290 @@ impl RpcClientExt for solana_client::rpc_client::RpcClient {
         Ok(cus)
     }
 
+    fn estimate_compute_units_with_pubkeys(
+        &self,
+        message: &Message,
+        signer_pubkeys: &[Pubkey],
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>> {
+        if let Some(&pubkey) = message
+            .signer_keys()
+            .into_iter()
+            .find(|signer| !signer_pubkeys.contains(signer))
+        {
+            return Err(Box::new(SolanaClientExtError::MissingSigner { pubkey }));
+        }
+        // Local simulation skips signature verification, so the message can be
+        // simulated unsigned.
+        let channel = RollUpChannel::new(message.account_keys.clone(), self);
+        let result = channel
+            .simulate_transactions_raw(
+                &[Transaction::new_unsigned(message.clone())],
+                &AnalysisConfig {
+                    estimate_compute_units: true,
+                    ..AnalysisConfig::default()
+                },
+            )
+            .pop()
+            .unwrap_or_else(RawSimulationResult::base_no_results);
+        if !result.success {
+            return Err(Box::new(SolanaClientExtError::SimulationFailed(result.result)));
+        }
+        Ok(result.cu)
+    }
+
+    fn try_estimate_compute_units<'a, I: Signers + ?Sized>(
+        &self,
+        transactions: &[Transaction],
+        signers: &'a I,
+    ) -> Vec<Result<u64, SimulationFailure>> {
+        let missing_signers: Vec<Option<Pubkey>> = transactions
+            .iter()
+            .map(|tx| missing_signer(tx, signers))
+            .collect();
+        // Transactions missing a signature couldn't land, so later ones
+        // shouldn't see their changes.
+        let signed: Vec<Transaction> = transactions
+            .iter()
+            .zip(&missing_signers)
+            .filter(|(_, missing)| missing.is_none())
+            .map(|(tx, _)| tx.clone())
+            .collect();
+        let accounts: Vec<Pubkey> = signed
+            .iter()
+            .flat_map(|tx| tx.message.account_keys.iter().copied())
+            .collect();
+        let channel = RollUpChannel::new(accounts, self);
+        let mut results = channel
+            .simulate_transactions_raw(&signed, &AnalysisConfig::default())
+            .into_iter();
+        missing_signers
+            .into_iter()
+            .map(|missing| {
+                if let Some(pubkey) = missing {
+                    return Err(SimulationFailure {
+                        error: Some(TransactionError::SignatureFailure),
+                        message: SolanaClientExtError::MissingSigner { pubkey }.to_string(),
+                        logs: Vec::new(),
+                    });
+                }
+                let res = results
+                    .next()
+                    .unwrap_or_else(RawSimulationResult::base_no_results);
+                if res.success {
+                    Ok(res.cu)
+                } else {
+                    Err(SimulationFailure {
+                        error: res.error,
+                        message: res.result,
+                        logs: res.logs,
+                    })
+                }
+            })
+            .collect()
+    }
+
+    fn estimate_compute_units_hybrid<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+    ) -> Result<(u64, CuEstimateSource), Box<dyn std::error::Error + 'static>> {
+        let channel = RollUpChannel::new(transaction.message.account_keys.clone(), self);
+        let result = channel
+            .simulate_transactions_raw(
+                std::slice::from_ref(transaction),
+                &AnalysisConfig::default(),
+            )
+            .pop()
+            .unwrap_or_else(RawSimulationResult::base_no_results);
+        if result.success {
+            return Ok((result.cu, CuEstimateSource::Local));
+        }
+        if result.error.as_ref().is_some_and(is_program_load_error) {
+            let cu = self.estimate_compute_units_msg(&transaction.message, signers)?;
+            return Ok((cu, CuEstimateSource::Rpc));
+        }
+        Err(Box::new(SolanaClientExtError::ComputeUnitsError(format!(
+            "Transaction simulation failed:\n{}",
+            result.result
+        ))))
+    }
+
+    fn estimate_compute_units_hybrid_with_config<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+        config: &HybridEstimateConfig,
+    ) -> Result<(u64, CuEstimateSource), Box<dyn std::error::Error + 'static>> {
+        if config.cu_source == CuSource::RpcOnly {
+            let cu = self.estimate_compute_units_msg(&transaction.message, signers)?;
+            return Ok((cu, CuEstimateSource::Rpc));
+        }
+
+        let channel = RollUpChannel::new(transaction.message.account_keys.clone(), self);
+        let result = channel
+            .simulate_transactions_raw(
+                std::slice::from_ref(transaction),
+                &AnalysisConfig::default(),
+            )
+            .pop()
+            .unwrap_or_else(RawSimulationResult::base_no_results);
+        let program_missing = result.error.as_ref().is_some_and(is_program_load_error);
+        if !result.success && !(config.cu_source == CuSource::MaxOfBoth && program_missing) {
+            return Err(Box::new(SolanaClientExtError::ComputeUnitsError(format!(
+                "Transaction simulation failed:\n{}",
+                result.result
+            ))));
+        }
+        if config.cu_source == CuSource::LocalOnly {
+            return Ok((result.cu, CuEstimateSource::Local));
+        }
+
+        let rpc_cu = self.estimate_compute_units_msg(&transaction.message, signers)?;
+        if result.success && result.cu >= rpc_cu {
+            Ok((result.cu, CuEstimateSource::Local))
+        } else {
+            Ok((rpc_cu, CuEstimateSource::Rpc))
+        }
+    }
+
+    fn simulate_fee_payer_balance<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>> {
+        let fee_payer = *transaction.message.account_keys.first().ok_or_else(|| {
+            Box::new(SolanaClientExtError::TransactionBuildError(
+                "Transaction has no fee payer.".to_string(),
+            ))
+        })?;
+        if let Some(pubkey) = missing_signer(transaction, signers) {
+            return Err(Box::new(SolanaClientExtError::MissingSigner { pubkey }));
+        }
+        let channel = RollUpChannel::new(transaction.message.account_keys.clone(), self);
+        let result = channel
+            .simulate_transactions_raw(&[transaction.clone()], &AnalysisConfig::default())
+            .pop()
+            .unwrap_or_else(RawSimulationResult::base_no_results);
+
+        match result.error {
+            Some(TransactionError::InsufficientFundsForFee) => {
+                return Err(Box::new(SolanaClientExtError::InsufficientFunds { fee_payer }));
+            }
+            Some(TransactionError::AccountNotFound) => {
+                return Err(Box::new(SolanaClientExtError::AccountNotFound {
+                    pubkey: fee_payer,
+                }));
+            }
+            _ => {}
+        }
+        if !result.success {
+            return Err(Box::new(SolanaClientExtError::SimulationFailed(result.result)));
+        }
+        result
+            .post_accounts
+            .iter()
+            .find(|(pubkey, _)| *pubkey == fee_payer)
+            .map(|(_, account)| account.lamports())
+            .ok_or_else(|| {
+                Box::new(SolanaClientExtError::SimulationFailed(
+                    "Fee payer missing from simulation results.".to_string(),
+                )) as Box<dyn std::error::Error>
+            })
+    }
+
+    fn compare_local_vs_rpc<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+    ) -> Result<CuComparison, Box<dyn std::error::Error + 'static>> {
+        let local = self.estimate_optimal_compute_units_unsigned_tx(transaction, signers)? as u64;
+        let rpc = self
+            .estimate_compute_units_msg(&transaction.message, signers)
+            .map_err(|err| err.to_string());
+        Ok(CuComparison {
+            local,
+            difference: rpc.as_ref().map_or(0, |&rpc| rpc as i64 - local as i64),
+            rpc,
+        })
+    }
+
+    fn estimate_full_cost(
+        &self,
+        transaction: &Transaction,
+        fee_strategy: &dyn FeeModel,
+    ) -> Result<FullCost, Box<dyn std::error::Error + 'static>> {
+        let channel = RollUpChannel::new(transaction.message.account_keys.clone(), self);
+        let result = channel
+            .simulate_transactions_raw(
+                std::slice::from_ref(transaction),
+                &AnalysisConfig::default(),
+            )
+            .pop()
+            .unwrap_or_else(RawSimulationResult::base_no_results);
+        if !result.success {
+            return Err(Box::new(SolanaClientExtError::ComputeUnitsError(format!(
+                "Transaction simulation failed:\n{}",
+                result.result
+            ))));
+        }
+
+        let base_fee = estimate_base_fee(&transaction.message, &FeeStructure::default(), None);
+        let priority_fee = fee_strategy
+            .estimate_priority_fee(&transaction.message.account_keys, result.cu)
+            .map_err(|err| Box::new(SolanaClientExtError::FeeModelError(err.to_string())))?
+            .total_fee_lamports;
+        let rent = channel.rent();
+        let rent_for_new_accounts = result
+            .post_accounts
+            .iter()
+            .filter(|(pubkey, _)| result.created_accounts.contains(pubkey))
+            .map(|(_, account)| rent.minimum_balance(account.data().len()))
+            .sum::<u64>();
+
+        Ok(FullCost {
+            base_fee,
+            priority_fee,
+            rent_for_new_accounts,
+            total: base_fee
+                .saturating_add(priority_fee)
+                .saturating_add(rent_for_new_accounts),
+        })
+    }
+
+    fn estimate_compute_units_with_margin<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+        margin: &CuMarginConfig,
+    ) -> Result<CuEstimate, Box<dyn std::error::Error + 'static>> {
+        let cus = self.estimate_compute_units_unsigned_tx(transaction, signers)?;
+        let point = *cus.first().ok_or_else(|| {
+            Box::new(SolanaClientExtError::ComputeUnitsError(
+                "CU estimation returned no results.".to_string(),
+            ))
+        })?;
+        Ok(margin.apply(point))
+    }
+
+    fn check_cu_within<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+        min: u64,
+        max: u64,
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>> {
+        let cus = self.estimate_compute_units_unsigned_tx(transaction, signers)?;
+        let actual = *cus.first().ok_or_else(|| {
+            Box::new(SolanaClientExtError::ComputeUnitsError(
+                "CU estimation returned no results.".to_string(),
+            ))
+        })?;
+        if !(min..=max).contains(&actual) {
+            return Err(Box::new(SolanaClientExtError::CuOutOfRange { actual, min, max }));
+        }
+        Ok(actual)
+    }
+
     fn estimate_compute_units_msg<'a, I: Signers + ?Sized>(
         &self,
         message: &Message,
@@ -401,79 +1405,452 @@ impl RpcClientExt for solana_client::rpc_client::RpcClient {
             sig_verify: true,
             ..RpcSimulateTransactionConfig::default()
         };
+        self.estimate_compute_units_msg_with_config(message, signers, config)
+    }
+
+    fn estimate_compute_units_msg_with_config<'a, I: Signers + ?Sized>(
+        &self,
+        message: &Message,
+        signers: &'a I,
+        config: RpcSimulateTransactionConfig,
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>> {
         let mut tx = Transaction::new_unsigned(message.clone());
-        tx.sign(signers, self.get_latest_blockhash()?);
-        let result = self.simulate_transaction_with_config(&tx, config)?;
-        let consumed_cu = result.value.units_consumed.ok_or_else(|| {
-            Box::new(SolanaClientExtError::ComputeUnitsError(
-                "Missing Compute Units from transaction simulation.".into(),
-            ))
+        let blockhash = self.get_latest_blockhash().map_err(|err| {
+            Box::new(SolanaClientExtError::BlockhashError(format!(
+                "failed to fetch blockhash for CU estimation: {}",
+                err
+            )))
+        })?;
+        tx.try_sign(signers, blockhash).map_err(|err| {
+            Box::new(SolanaClientExtError::TransactionBuildError(format!(
+                "failed to sign transaction for CU estimation: {}",
+                err
+            )))
         })?;
-        if consumed_cu == 0 && result.value.err.is_some() {
-            return Err(Box::new(SolanaClientExtError::RpcError(
-                format!(
-                    "Transaction simulation failed: {:?}",
-                    result.value.err.unwrap()
-                )
-                .into(),
-            )));
+        let result = self
+            .simulate_transaction_with_config(&tx, config)
+            .map_err(SolanaClientExtError::from)?;
+        Ok(units_consumed_from_simulation(&result.value)?)
+    }
+
+    fn estimate_compute_units_msg_no_sigverify(
+        &self,
+        message: &Message,
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>> {
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            ..RpcSimulateTransactionConfig::default()
+        };
+        let tx = Transaction::new_unsigned(message.clone());
+        let result = self
+            .simulate_transaction_with_config(&tx, config)
+            .map_err(SolanaClientExtError::from)?;
+        Ok(units_consumed_from_simulation(&result.value)?)
+    }
+
+    fn cu_curve<'a, I: Signers + ?Sized>(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &'a I,
+    ) -> Result<Vec<u64>, Box<dyn std::error::Error + 'static>> {
+        (1..=instructions.len())
+            .map(|len| {
+                let message = Message::new(&instructions[..len], Some(payer));
+                self.estimate_compute_units_msg(&message, signers)
+            })
+            .collect()
+    }
+
+    fn suggest_instruction_split<'a, I: Signers + ?Sized>(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &'a I,
+    ) -> Result<Vec<InstructionGroup>, Box<dyn std::error::Error + 'static>> {
+        let blockhash = self.get_latest_blockhash().map_err(|err| {
+            Box::new(SolanaClientExtError::BlockhashError(format!(
+                "failed to fetch blockhash for CU estimation: {}",
+                err
+            )))
+        })?;
+        let simulate_group = |indices: &[usize]| {
+            let group: Vec<Instruction> =
+                indices.iter().map(|&index| instructions[index].clone()).collect();
+            simulate_at_max_cu_limit(self, &group, payer, signers, blockhash)
+        };
+
+        let mut groups: Vec<InstructionGroup> = Vec::new();
+        let mut current = InstructionGroup::default();
+        for index in 0..instructions.len() {
+            current.indices.push(index);
+            let (mut cu, mut err) = simulate_group(&current.indices)?;
+            if !fits_cu_cap(cu, &err) && current.indices.len() > 1 {
+                current.indices.pop();
+                groups.push(std::mem::take(&mut current));
+                current.indices.push(index);
+                (cu, err) = simulate_group(&current.indices)?;
+            }
+            // A lone instruction that doesn't fit can't be split any further.
+            if !fits_cu_cap(cu, &err) {
+                let err = match err {
+                    Some(err) if cu < MAX_COMPUTE_UNIT_LIMIT as u64 => {
+                        SolanaClientExtError::RpcError(format!(
+                            "Transaction simulation failed: {:?}",
+                            err
+                        ))
+                    }
+                    _ => SolanaClientExtError::ComputeUnitsError(format!(
+                        "Instruction {} alone needs more than the {} CU limit",
+                        index, MAX_COMPUTE_UNIT_LIMIT
+                    )),
+                };
+                return Err(Box::new(err));
+            }
+            current.cu_deltas.push(cu.saturating_sub(current.cu));
+            current.cu = cu;
         }
-        Ok(consumed_cu)
+        if !current.indices.is_empty() {
+            groups.push(current);
+        }
+        Ok(groups)
     }
 
-    fn optimize_compute_units_unsigned_tx<'a, I: Signers + ?Sized>(
+    fn pack_into_transactions<'a, I: Signers + ?Sized>(
         &self,
-        transaction: &mut Transaction,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &'a I,
+        blockhash: Hash,
+    ) -> Result<Vec<Transaction>, Box<dyn std::error::Error + 'static>> {
+        // The limit's value doesn't change the instruction's size, so the
+        // maximum stands in for it while measuring.
+        let with_limit = |cu_limit: u32, indices: &[usize]| {
+            let mut group = vec![ComputeBudgetInstruction::set_compute_unit_limit(cu_limit)];
+            group.extend(indices.iter().map(|&index| instructions[index].clone()));
+            Message::new(&group, Some(payer))
+        };
+        let fits_packet = |indices: &[usize]| {
+            let transaction =
+                Transaction::new_unsigned(with_limit(MAX_COMPUTE_UNIT_LIMIT, indices));
+            bincode::serialized_size(&transaction)
+                .is_ok_and(|size| size as usize <= PACKET_DATA_SIZE)
+        };
+
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for cu_group in self.suggest_instruction_split(instructions, payer, signers)? {
+            let mut current: Vec<usize> = Vec::new();
+            for index in cu_group.indices {
+                current.push(index);
+                if !fits_packet(&current) {
+                    current.pop();
+                    if current.is_empty() {
+                        return Err(Box::new(SolanaClientExtError::TransactionBuildError(
+                            format!(
+                                "Instruction {} doesn't fit in a {}-byte transaction",
+                                index, PACKET_DATA_SIZE
+                            ),
+                        )));
+                    }
+                    groups.push(std::mem::take(&mut current));
+                    current.push(index);
+                }
+            }
+            if !current.is_empty() {
+                groups.push(current);
+            }
+        }
+
+        // Packet splitting can move instructions away from state they set up, so
+        // each group is simulated again before it is signed.
+        let mut transactions = Vec::with_capacity(groups.len());
+        for (i, indices) in groups.iter().enumerate() {
+            let group: Vec<Instruction> =
+                indices.iter().map(|&index| instructions[index].clone()).collect();
+            let (cu, err) = simulate_at_max_cu_limit(self, &group, payer, signers, blockhash)?;
+            if !fits_cu_cap(cu, &err) {
+                return Err(Box::new(SolanaClientExtError::ComputeUnitsError(format!(
+                    "Packed transaction {} doesn't fit the {} CU limit: {:?}",
+                    i, MAX_COMPUTE_UNIT_LIMIT, err
+                ))));
+            }
+            let cu_limit = CuMarginConfig::default().apply(cu).recommended;
+            let mut transaction =
+                Transaction::new_unsigned(with_limit(u32::try_from(cu_limit)?, indices));
+            transaction.try_sign(signers, blockhash).map_err(|err| {
+                Box::new(SolanaClientExtError::TransactionBuildError(err.to_string()))
+            })?;
+            transactions.push(transaction);
+        }
+        Ok(transactions)
+    }
+
+    fn estimate_optimal_compute_units_unsigned_tx<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
         signers: &'a I,
     ) -> Result<u32, Box<dyn std::error::Error + 'static>> {
         let optimal_cu_vec = self.estimate_compute_units_unsigned_tx(transaction, signers)?;
-        let optimal_cu = *optimal_cu_vec.get(0).ok_or_else(|| {
+        let optimal_cu = *optimal_cu_vec.first().ok_or_else(|| {
             Box::new(SolanaClientExtError::ComputeUnitsError(
                 "CU estimation returned no results.".to_string(),
             ))
-        })? as u32;
-        let optimize_ix =
-            ComputeBudgetInstruction::set_compute_unit_limit(optimal_cu.saturating_add(optimal_cu));
-        transaction
-            .message
-            .account_keys
-            .push(solana_sdk::compute_budget::id());
-        let compiled_ix = transaction.message.compile_instruction(&optimize_ix);
-        transaction.message.instructions.insert(0, compiled_ix);
+        })?;
+        Ok(u32::try_from(optimal_cu)?)
+    }
+
+    fn optimize_compute_units_unsigned_tx<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &mut Transaction,
+        signers: &'a I,
+    ) -> Result<u32, Box<dyn std::error::Error + 'static>> {
+        self.optimize_compute_units_unsigned_tx_with_margin(
+            transaction,
+            signers,
+            &CuMarginConfig::default(),
+        )
+    }
+
+    fn optimize_compute_units_unsigned_tx_with_margin<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &mut Transaction,
+        signers: &'a I,
+        margin: &CuMarginConfig,
+    ) -> Result<u32, Box<dyn std::error::Error + 'static>> {
+        // Estimate with the limit instruction in place, so its CUs are counted.
+        let mut with_limit = transaction.clone();
+        set_compute_unit_limit(&mut with_limit.message, MAX_COMPUTE_UNIT_LIMIT);
+        let optimal_cu = self.estimate_optimal_compute_units_unsigned_tx(&with_limit, signers)?;
+        let cu_limit = margin.apply(optimal_cu as u64).recommended;
+        set_compute_unit_limit(&mut with_limit.message, u32::try_from(cu_limit)?);
+        *transaction = with_limit;
         Ok(optimal_cu)
     }
 
+    fn optimize_loaded_accounts_data_size_unsigned_tx<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &mut Transaction,
+        signers: &'a I,
+    ) -> Result<u32, Box<dyn std::error::Error + 'static>> {
+        if let Some(pubkey) = missing_signer(transaction, signers) {
+            return Err(Box::new(SolanaClientExtError::MissingSigner { pubkey }));
+        }
+        let max_limit = MAX_LOADED_ACCOUNTS_DATA_SIZE_BYTES.get();
+        // Lift any existing limit, so the simulation measures the real size.
+        let mut unlimited = transaction.clone();
+        set_loaded_accounts_data_size_limit(&mut unlimited.message, max_limit);
+
+        let channel = RollUpChannel::new(transaction.message.account_keys.clone(), self);
+        let result = channel
+            .simulate_transactions_raw(&[unlimited], &AnalysisConfig::default())
+            .pop()
+            .unwrap_or_else(RawSimulationResult::base_no_results);
+        if !result.success {
+            return Err(Box::new(SolanaClientExtError::SimulationFailed(result.result)));
+        }
+
+        let with_margin = result
+            .loaded_accounts_data_size
+            .saturating_add(result.loaded_accounts_data_size / 10);
+        let limit = with_margin
+            .div_ceil(1024)
+            .max(1)
+            .saturating_mul(1024)
+            .min(max_limit as u64) as u32;
+        set_loaded_accounts_data_size_limit(&mut transaction.message, limit);
+        Ok(limit)
+    }
+
+    fn optimize_with_fee_cap<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &mut Transaction,
+        signers: &'a I,
+        max_fee_lamports: u64,
+    ) -> Result<TotalFee, Box<dyn std::error::Error + 'static>> {
+        // Estimate with both instructions in place, so their CUs are counted.
+        let mut with_budget = transaction.clone();
+        set_compute_unit_limit(&mut with_budget.message, MAX_COMPUTE_UNIT_LIMIT);
+        set_compute_unit_price(&mut with_budget.message, 0);
+        let cu = self.estimate_optimal_compute_units_unsigned_tx(&with_budget, signers)?;
+        let cu_limit = CuMarginConfig::default().apply(cu as u64).recommended.max(1);
+
+        let base_fee = estimate_base_fee(&with_budget.message, &FeeStructure::default(), None);
+        if base_fee > max_fee_lamports {
+            return Err(Box::new(SolanaClientExtError::FeeCapExceeded {
+                base_fee,
+                max_fee: max_fee_lamports,
+            }));
+        }
+        let fee = self.estimate_priority_fee_for_cu_sync(
+            Some(&with_budget.message.account_keys),
+            cu_limit,
+        )?;
+        // The runtime rounds the priority fee up, so round the price down.
+        let max_price = (max_fee_lamports - base_fee) as u128 * 1_000_000 / cu_limit as u128;
+        let price = (fee.fee_per_cu_micro_lamports as u128).min(max_price) as u64;
+
+        set_compute_unit_limit(&mut with_budget.message, u32::try_from(cu_limit)?);
+        set_compute_unit_price(&mut with_budget.message, price);
+        *transaction = with_budget;
+        let priority_fee = compute_priority_fee_from_tx(transaction);
+        Ok(TotalFee {
+            cu: cu_limit,
+            base_fee_lamports: base_fee,
+            priority_fee_lamports: priority_fee,
+            total_lamports: base_fee + priority_fee,
+        })
+    }
+
+    fn plan_compute_budget<'a, I: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &'a I,
+    ) -> Result<Vec<Instruction>, Box<dyn std::error::Error + 'static>> {
+        // Estimate with both instructions in place, so their CUs are counted.
+        let needs_price = parse_compute_budget(transaction).unit_price.is_none();
+        let mut with_budget = transaction.clone();
+        set_compute_unit_limit(&mut with_budget.message, MAX_COMPUTE_UNIT_LIMIT);
+        if needs_price {
+            set_compute_unit_price(&mut with_budget.message, 0);
+        }
+        let optimal_cu = self.estimate_optimal_compute_units_unsigned_tx(&with_budget, signers)?;
+        let cu_limit = CuMarginConfig::default().apply(optimal_cu as u64).recommended;
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+            u32::try_from(cu_limit)?,
+        )];
+
+        if needs_price {
+            let fee = self.estimate_priority_fee_for_cu_sync(
+                Some(&transaction.message.account_keys),
+                cu_limit,
+            )?;
+            if fee.fee_per_cu_micro_lamports > 0 {
+                instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+                    fee.fee_per_cu_micro_lamports,
+                ));
+            }
+        }
+        Ok(instructions)
+    }
+
     fn optimize_compute_units_msg<'a, I: Signers + ?Sized>(
         &self,
         message: &mut Message,
         signers: &'a I,
     ) -> Result<u32, Box<dyn std::error::Error + 'static>> {
-        let optimal_cu = u32::try_from(self.estimate_compute_units_msg(message, signers)?)?;
-        let optimize_ix = ComputeBudgetInstruction::set_compute_unit_limit(
-            optimal_cu.saturating_add(150 /*optimal_cu.saturating_div(100)*100*/),
-        );
-        message.account_keys.push(solana_sdk::compute_budget::id());
-        let compiled_ix = message.compile_instruction(&optimize_ix);
-        message.instructions.insert(0, compiled_ix);
+        self.optimize_compute_units_msg_with_margin(message, signers, &CuMarginConfig::default())
+    }
+
+    fn optimize_compute_units_msg_with_margin<'a, I: Signers + ?Sized>(
+        &self,
+        message: &mut Message,
+        signers: &'a I,
+        margin: &CuMarginConfig,
+    ) -> Result<u32, Box<dyn std::error::Error + 'static>> {
+        let mut with_limit = message.clone();
+        set_compute_unit_limit(&mut with_limit, MAX_COMPUTE_UNIT_LIMIT);
+        let optimal_cu = u32::try_from(self.estimate_compute_units_msg(&with_limit, signers)?)?;
+        let cu_limit = margin.apply(optimal_cu as u64).recommended;
+        set_compute_unit_limit(&mut with_limit, u32::try_from(cu_limit)?);
+        *message = with_limit;
         Ok(optimal_cu)
     }
 
+    fn build_versioned_with_lookup<'a, I: Signers + ?Sized>(
+        &self,
+        message: &Message,
+        lookup_tables: &[AddressLookupTableAccount],
+        fee_strategy: &dyn FeeModel,
+        signers: &'a I,
+    ) -> Result<VersionedTransaction, Box<dyn std::error::Error + 'static>> {
+        let payer = *message.account_keys.first().ok_or_else(|| {
+            Box::new(SolanaClientExtError::TransactionBuildError(
+                "Message has no fee payer.".to_string(),
+            ))
+        })?;
+
+        // Drop existing Compute Budget instructions so they aren't duplicated.
+        let instructions: Vec<Instruction> = decompile_instructions(message)
+            .into_iter()
+            .filter(|ix| ix.program_id != solana_sdk::compute_budget::id())
+            .collect();
+        let stripped = Message::new(&instructions, Some(&payer));
+
+        let cu = self.estimate_compute_units_msg(&stripped, signers)?;
+        let cu_limit = CuMarginConfig::default().apply(cu).recommended;
+        let fee = fee_strategy
+            .estimate_priority_fee(&stripped.account_keys, cu_limit)
+            .map_err(|err| Box::new(SolanaClientExtError::FeeModelError(err.to_string())))?;
+
+        let mut with_fees = fee.to_instructions(u32::try_from(cu_limit)?).to_vec();
+        with_fees.extend(instructions);
+        let blockhash = self.get_latest_blockhash().map_err(|err| {
+            Box::new(SolanaClientExtError::BlockhashError(format!(
+                "failed to fetch blockhash for transaction: {}",
+                err
+            )))
+        })?;
+        let compiled = v0::Message::try_compile(&payer, &with_fees, lookup_tables, blockhash)
+            .map_err(|err| {
+                Box::new(SolanaClientExtError::TransactionBuildError(err.to_string()))
+            })?;
+        let transaction = VersionedTransaction::try_new(VersionedMessage::V0(compiled), signers)
+            .map_err(|err| {
+                Box::new(SolanaClientExtError::TransactionBuildError(err.to_string()))
+            })?;
+        Ok(transaction)
+    }
+
+    fn get_fee_for_message(
+        &self,
+        message: &Message,
+    ) -> Result<u64, Box<dyn std::error::Error + 'static>> {
+        solana_client::rpc_client::RpcClient::get_fee_for_message(self, message)
+            .map_err(|err| fee_for_message_error(message, err).into())
+    }
+
+    fn check_accounts_exist(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<HashMap<Pubkey, bool>, Box<dyn std::error::Error + 'static>> {
+        // `getMultipleAccounts` accepts at most 100 keys per request.
+        let mut existence = HashMap::new();
+        for keys in transaction.message.account_keys.chunks(100) {
+            let accounts = self
+                .get_multiple_accounts(keys)
+                .map_err(SolanaClientExtError::from)?;
+            for (key, account) in keys.iter().zip(accounts) {
+                existence.insert(*key, account.is_some());
+            }
+        }
+        Ok(existence)
+    }
+
     fn estimate_priority_fee_for_cu_sync(
         &self,
         accounts: Option<&[Pubkey]>,
         cu: u64,
     ) -> Result<EstimatedPrioritizationFee> {
-        let fees = match accounts {
-            Some(addrs) => self.get_recent_prioritization_fees(addrs)?,
-            None => self.get_recent_prioritization_fees(&[])?,
-        };
+        let addrs = accounts.unwrap_or(&[]);
+        let fees = self
+            .get_recent_prioritization_fees(addrs)
+            .map_err(|err| priority_fee_fetch_error(addrs, err))?;
 
         let best_fee_per_cu_micro = fees.iter().map(|f| f.prioritization_fee).max().unwrap_or(0);
-        let total_lamports = (best_fee_per_cu_micro as u128 * cu as u128) / 1_000_000;
+        Ok(EstimatedPrioritizationFee::from_price(best_fee_per_cu_micro, cu))
+    }
 
-        Ok(EstimatedPrioritizationFee {
-            fee_per_cu_micro_lamports: best_fee_per_cu_micro,
-            total_fee_lamports: total_lamports as u64,
-        })
+    fn priority_fee_histogram(
+        &self,
+        accounts: Option<&[Pubkey]>,
+        buckets: usize,
+    ) -> Result<Vec<(u64, usize)>> {
+        let addrs = accounts.unwrap_or(&[]);
+        let fees = self
+            .get_recent_prioritization_fees(addrs)
+            .map_err(|err| priority_fee_fetch_error(addrs, err))?;
+        let fee_values: Vec<u64> = fees.iter().map(|f| f.prioritization_fee).collect();
+        Ok(bucket_fees(&fee_values, buckets))
     }
 }
@@ -0,0 +1,69 @@
+//! Serializes local simulation results into Solana's JSON-RPC response shapes.
+//!
+//! Lets a local SVM simulation be served behind an HTTP endpoint that existing
+//! `simulateTransaction` clients can consume unchanged.
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde_json::{json, Value};
+use solana_sdk::account::{AccountSharedData, ReadableAccount};
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::state::return_struct::RawSimulationResult;
+
+/// Builds the `simulateTransaction` JSON-RPC result envelope for a simulation.
+///
+/// The returned value has the same `{ "context": { "slot" }, "value": { ... } }`
+/// layout as the RPC response. `account_addresses` mirrors the `accounts.addresses`
+/// request option: when set, the post-execution state of each address is
+/// included in order, with `null` for accounts the transaction didn't touch.
+pub fn to_rpc_simulate_response(
+    result: &RawSimulationResult,
+    slot: Slot,
+    account_addresses: Option<&[Pubkey]>,
+) -> Value {
+    let accounts = account_addresses.map(|addresses| {
+        addresses
+            .iter()
+            .map(|address| {
+                result
+                    .post_accounts
+                    .iter()
+                    .find(|(pubkey, _)| pubkey == address)
+                    .map_or(Value::Null, |(_, account)| ui_account_json(account))
+            })
+            .collect::<Vec<Value>>()
+    });
+
+    let return_data = result.return_data.as_ref().map(|return_data| {
+        json!({
+            "programId": return_data.program_id.to_string(),
+            "data": [BASE64_STANDARD.encode(&return_data.data), "base64"],
+        })
+    });
+
+    json!({
+        "context": { "slot": slot },
+        "value": {
+            "err": result.error,
+            "logs": result.logs,
+            "accounts": accounts,
+            "unitsConsumed": result.cu,
+            "returnData": return_data,
+            "innerInstructions": null,
+            "replacementBlockhash": null,
+        },
+    })
+}
+
+/// Encodes an account the way the RPC does with `base64` encoding.
+fn ui_account_json(account: &AccountSharedData) -> Value {
+    json!({
+        "lamports": account.lamports(),
+        "data": [BASE64_STANDARD.encode(account.data()), "base64"],
+        "owner": account.owner().to_string(),
+        "executable": account.executable(),
+        "rentEpoch": account.rent_epoch(),
+        "space": account.data().len(),
+    })
+}
@@ -0,0 +1,214 @@
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{account::AccountSharedData, pubkey::Pubkey};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::error::SolanaClientExtError;
+use crate::utils::client::RpcClientHandle;
+
+/// Where `RollUpAccountLoader` fetches accounts that aren't cached yet.
+///
+/// Implemented for `RpcClient`, which reads live cluster state, and for
+/// `MockAccountSource`, which serves seeded accounts fully offline.
+pub trait AccountSource: Send + Sync {
+    /// Fetches an account, returning `Ok(None)` if it doesn't exist and an
+    /// error if it can't be read (e.g. the request failed).
+    fn get_account(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<Option<AccountSharedData>, SolanaClientExtError>;
+
+    /// Fetches several accounts, returning one entry per key, in order.
+    ///
+    /// Calls `get_account` for each key by default, failing if any of them
+    /// fails; sources with a batch API override it.
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<AccountSharedData>>, SolanaClientExtError> {
+        pubkeys
+            .iter()
+            .map(|pubkey| self.get_account(pubkey))
+            .collect()
+    }
+}
+
+impl AccountSource for RpcClient {
+    fn get_account(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<Option<AccountSharedData>, SolanaClientExtError> {
+        let response = self.get_account_with_commitment(pubkey, self.commitment())?;
+        Ok(response.value.map(Into::into))
+    }
+
+    /// Uses a single `getMultipleAccounts` request, so `pubkeys` must hold at
+    /// most 100 keys.
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<AccountSharedData>>, SolanaClientExtError> {
+        let accounts = RpcClient::get_multiple_accounts(self, pubkeys)?;
+        Ok(accounts
+            .into_iter()
+            .map(|account| account.map(Into::into))
+            .collect())
+    }
+}
+
+impl AccountSource for RpcClientHandle<'_> {
+    fn get_account(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<Option<AccountSharedData>, SolanaClientExtError> {
+        AccountSource::get_account(&**self, pubkey)
+    }
+
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<AccountSharedData>>, SolanaClientExtError> {
+        AccountSource::get_multiple_accounts(&**self, pubkeys)
+    }
+}
+
+impl<T: AccountSource + ?Sized> AccountSource for Arc<T> {
+    fn get_account(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<Option<AccountSharedData>, SolanaClientExtError> {
+        (**self).get_account(pubkey)
+    }
+
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<AccountSharedData>>, SolanaClientExtError> {
+        (**self).get_multiple_accounts(pubkeys)
+    }
+}
+
+/// Account source that fetches from several RPC endpoints, in order.
+///
+/// Each request goes to the first endpoint; if it fails (e.g. a provider
+/// outage), the next one is tried, and so on. An account reported missing by
+/// an endpoint that answered is not looked up elsewhere. If every endpoint
+/// fails, the last error is returned.
+pub struct FailoverAccountSource<'a> {
+    sources: Vec<Box<dyn AccountSource + 'a>>,
+}
+
+impl<'a> FailoverAccountSource<'a> {
+    /// Creates a source trying `endpoints` in order.
+    pub fn new(endpoints: Vec<&'a RpcClient>) -> Self {
+        Self::from_sources(
+            endpoints
+                .into_iter()
+                .map(|endpoint| {
+                    Box::new(RpcClientHandle::Borrowed(endpoint)) as Box<dyn AccountSource + 'a>
+                })
+                .collect(),
+        )
+    }
+
+    /// Creates a source trying arbitrary account sources in order, e.g. to
+    /// fall back from a cluster to a `MockAccountSource`.
+    pub fn from_sources(sources: Vec<Box<dyn AccountSource + 'a>>) -> Self {
+        Self { sources }
+    }
+}
+
+impl AccountSource for FailoverAccountSource<'_> {
+    fn get_account(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<Option<AccountSharedData>, SolanaClientExtError> {
+        let mut last_err = None;
+        for (i, source) in self.sources.iter().enumerate() {
+            match source.get_account(pubkey) {
+                Ok(account) => return Ok(account),
+                Err(err) => {
+                    log::warn!("account source {} failed to fetch {}: {}", i, pubkey, err);
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(no_endpoints))
+    }
+
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<AccountSharedData>>, SolanaClientExtError> {
+        let mut last_err = None;
+        for (i, source) in self.sources.iter().enumerate() {
+            match source.get_multiple_accounts(pubkeys) {
+                Ok(accounts) => return Ok(accounts),
+                Err(err) => {
+                    log::warn!("account source {} failed to fetch accounts: {}", i, err);
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(no_endpoints))
+    }
+}
+
+/// Error returned by a `FailoverAccountSource` without endpoints.
+fn no_endpoints() -> SolanaClientExtError {
+    SolanaClientExtError::RpcError("no RPC endpoints to fetch accounts from".to_string())
+}
+
+/// In-memory account source for deterministic, offline simulations and tests.
+///
+/// Only accounts added with `insert` exist; everything else is reported as
+/// missing. Builtin programs invoked by a transaction (e.g. the System Program)
+/// must be seeded too, for instance with
+/// `solana_sdk::native_loader::create_loadable_account_for_test`.
+///
+/// Wrap it in an `Arc` to keep seeding accounts after handing it to a loader.
+#[derive(Debug, Default)]
+pub struct MockAccountSource {
+    accounts: RwLock<HashMap<Pubkey, AccountSharedData>>,
+    /// If set, every fetch fails, like an unreachable RPC endpoint.
+    unavailable: AtomicBool,
+}
+
+impl MockAccountSource {
+    /// Creates an empty `MockAccountSource`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces an account.
+    pub fn insert(&self, pubkey: Pubkey, account: AccountSharedData) {
+        self.accounts.write().unwrap().insert(pubkey, account);
+    }
+
+    /// Removes an account, returning it if it was present.
+    pub fn remove(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.accounts.write().unwrap().remove(pubkey)
+    }
+
+    /// Makes every fetch fail with `SolanaClientExtError::RpcError` while
+    /// `unavailable` is set, to test how failed requests are handled.
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.unavailable.store(unavailable, Ordering::Relaxed);
+    }
+}
+
+impl AccountSource for MockAccountSource {
+    fn get_account(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<Option<AccountSharedData>, SolanaClientExtError> {
+        if self.unavailable.load(Ordering::Relaxed) {
+            return Err(SolanaClientExtError::RpcError(format!(
+                "account source unavailable: failed to fetch {}",
+                pubkey
+            )));
+        }
+        Ok(self.accounts.read().unwrap().get(pubkey).cloned())
+    }
+}
@@ -10,20 +10,25 @@ use solana_sdk::clock::Slot;
 /// program visibility and slot relationships during transaction simulation.
 ///
 /// In our case, we don’t need full fork tracking for local CU estimation or isolated
-/// transaction simulation, so we stub it with an empty struct.
+/// transaction simulation, so we stub it with an empty struct over a single chain.
 pub(crate) struct ForkRollUpGraph {}
 /// Implements the `ForkGraph` trait for our mocked graph.
 ///
 /// The `relationship()` method defines how two slots relate to each other.
-/// For simulation purposes, we simply return `BlockRelation::Unknown` to indicate
-/// that we make no assumptions about slot ancestry.
+/// For simulation purposes, every slot lies on a single chain, so an earlier
+/// slot is an ancestor of a later one.
 ///
 /// This is sufficient because:
-/// - We simulate in a single slot (usually slot 1)
+/// - We simulate in a single slot (the `Clock` sysvar's, or slot 1)
 /// - We're not validating or resolving forks
-/// - We're not simulating program activation/deactivation across slots
+/// - Programs deployed at an earlier slot, like every upgradeable program read
+///   from the cluster, must be visible to the program cache at the current one
 impl ForkGraph for ForkRollUpGraph {
-    fn relationship(&self, _a: Slot, _b: Slot) -> BlockRelation {
-        BlockRelation::Unknown
+    fn relationship(&self, a: Slot, b: Slot) -> BlockRelation {
+        match a.cmp(&b) {
+            std::cmp::Ordering::Less => BlockRelation::Ancestor,
+            std::cmp::Ordering::Equal => BlockRelation::Equal,
+            std::cmp::Ordering::Greater => BlockRelation::Descendant,
+        }
     }
 }
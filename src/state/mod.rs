@@ -1,7 +1,14 @@
+//! Simulation state: the rollup channel, its account loader and result types.
+//!
+//! Every public type here is also re-exported from the crate root, which is
+//! the preferred import path.
+
+pub mod account_source;
+
 pub mod return_struct;
 
 pub mod rollup_channel;
 
-pub mod fork_rollup_graph;
+pub(crate) mod fork_rollup_graph;
 
 pub mod rollup_account_loader;
@@ -1,3 +1,18 @@
+use std::fmt;
+
+use solana_sdk::account::AccountSharedData;
+use solana_sdk::native_token::lamports_to_sol;
+use solana_sdk::nonce::state::Data as NonceData;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::transaction::TransactionError;
+use solana_sdk::transaction_context::TransactionReturnData;
+
+use crate::error::SolanaClientExtError;
+use crate::fees::format_lamports;
+use crate::utils::logs::top_level_bpf_units;
+
 /// Encapsulates the outcome of a simulated or real transaction execution.
 ///
 /// Useful for tracking:
@@ -15,6 +30,160 @@ pub struct RawSimulationResult {
     pub result: String,
     /// Optional prioritization fee details.
     pub prioritization_fee_details: Option<PrioritizationFeeDetails>,
+    /// Program log messages emitted during execution, in the format RPC
+    /// `simulateTransaction` returns them (`"Program <id> invoke [1]"`,
+    /// `"Program log: ..."`, `"Program <id> success"`, ...), so existing log
+    /// parsers can read them.
+    pub logs: Vec<String>,
+    /// Whether the logs hit the byte limit and were cut short. See
+    /// `RollUpChannelConfig::max_log_bytes`.
+    pub logs_truncated: bool,
+    /// Deepest program invocation level seen in the logs (1 = top-level, 0 = no invocations).
+    pub max_cpi_depth: u32,
+    /// Number of cross-program invocations, not counting top-level instructions.
+    /// Logs cut short by the log byte limit undercount it.
+    pub cpi_count: u32,
+    /// Every program invoked during execution, including through CPI, in order
+    /// of first invocation.
+    pub invoked_programs: Vec<Pubkey>,
+    /// Structured transaction error, if the transaction failed.
+    pub error: Option<TransactionError>,
+    /// Code of an `InstructionError::Custom` error returned by a program, e.g.
+    /// 6000 and up for Anchor programs. `None` for any other outcome.
+    pub custom_error_code: Option<u32>,
+    /// `true` if execution ran out of compute units.
+    pub exceeded_compute_budget: bool,
+    /// Data set by the last program to call `set_return_data`, if any.
+    pub return_data: Option<TransactionReturnData>,
+    /// Transaction accounts as they were after execution.
+    pub post_accounts: Vec<(Pubkey, AccountSharedData)>,
+    /// Set when the transaction failed to load but would still have paid fees.
+    pub fees_only: Option<FeesOnlyDetails>,
+    /// Data length of each writable account before and after execution.
+    pub account_data_sizes: Vec<AccountDataSizeChange>,
+    /// Every CPI made during execution, with the compute units it consumed.
+    /// Empty unless CPI recording is enabled in the processing config.
+    pub inner_instructions: Vec<InnerInstructionCu>,
+    /// Accounts the transaction created: writable accounts that were missing or
+    /// had no lamports before execution and hold lamports after it. Only set
+    /// for successful transactions.
+    pub created_accounts: Vec<Pubkey>,
+    /// Accounts the transaction closed: writable accounts that held lamports
+    /// before execution and none after it. Only set for successful transactions.
+    pub closed_accounts: Vec<Pubkey>,
+    /// Total data size of the accounts the transaction loads, in bytes, as the
+    /// SVM counts it against the transaction's loaded accounts data size
+    /// limit, e.g. including the ProgramData of upgradeable programs. Compare
+    /// it against a `SetLoadedAccountsDataSizeLimit`.
+    pub loaded_accounts_data_size: u64,
+    /// State of the durable nonce account after execution, if the transaction
+    /// advanced it. `durable_nonce` holds the new nonce value. Only set for
+    /// successful transactions.
+    pub advanced_nonce: Option<NonceData>,
+    /// Prioritization fee implied by the transaction's `SetComputeUnitPrice`
+    /// and the compute units it actually consumed (`price * cu / 1_000_000`,
+    /// rounded up), or `None` if it sets no price. The runtime charges the
+    /// price against the requested limit instead, see
+    /// `compute_priority_fee_from_tx`; both agree once the limit is tightened
+    /// to the consumption.
+    pub implied_priority_fee_lamports: Option<u64>,
+    /// Fee the SVM charged the fee payer, in lamports: the base fee plus the
+    /// prioritization fee. Set for executed transactions, including those whose
+    /// instructions failed; a transaction that failed to load reports it in
+    /// `fees_only` instead.
+    pub fee_lamports: Option<u64>,
+}
+
+/// A CPI recorded during execution and the compute units it consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InnerInstructionCu {
+    /// Index of the top-level instruction the CPI was made from.
+    pub instruction_index: usize,
+    /// Invocation stack height (2 for a CPI made directly by a top-level instruction).
+    pub stack_height: u8,
+    /// The invoked program.
+    pub program_id: Pubkey,
+    /// Compute units consumed by the CPI, including its own nested CPIs.
+    /// Builtins charge a fixed amount per invocation; BPF programs report their
+    /// consumption in the logs, so it's `None` if the logs were truncated
+    /// before it.
+    pub consumed_cu: Option<u64>,
+}
+
+/// Data length of a writable account before and after execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountDataSizeChange {
+    /// The account's address.
+    pub pubkey: Pubkey,
+    /// Data length before execution (0 if the account didn't exist).
+    pub data_len_before: usize,
+    /// Data length after execution.
+    pub data_len_after: usize,
+}
+
+impl AccountDataSizeChange {
+    /// Returns the change in data length, negative if the account shrank.
+    pub fn growth(&self) -> i64 {
+        self.data_len_after as i64 - self.data_len_before as i64
+    }
+
+    /// Returns the extra lamports needed to keep the account rent-exempt
+    /// after its data grew. Zero if the account didn't grow.
+    pub fn additional_rent_lamports(&self, rent: &Rent) -> u64 {
+        rent.minimum_balance(self.data_len_after)
+            .saturating_sub(rent.minimum_balance(self.data_len_before))
+    }
+}
+
+/// How a simulated transaction changed one account, as listed in a
+/// `TransactionSummary`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountChangeSummary {
+    /// The account's address.
+    pub pubkey: Pubkey,
+    /// Balance before execution (0 if the account didn't exist).
+    pub lamports_before: u64,
+    /// Balance after execution.
+    pub lamports_after: u64,
+    /// Data length before execution (0 if the account didn't exist).
+    pub data_len_before: usize,
+    /// Data length after execution.
+    pub data_len_after: usize,
+    /// Whether the account's data changed, even if its length didn't.
+    pub data_changed: bool,
+}
+
+impl AccountChangeSummary {
+    /// Returns the change in balance, negative if the account was debited.
+    pub fn lamports_change(&self) -> i128 {
+        self.lamports_after as i128 - self.lamports_before as i128
+    }
+}
+
+/// Explorer-style overview of a simulated transaction, as returned by
+/// `RollUpChannel::summarize`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSummary {
+    /// `Ok` if the transaction succeeded, or the error it failed with.
+    pub status: Result<(), String>,
+    /// Base plus prioritization fee the transaction pays, in lamports.
+    pub fee_lamports: u64,
+    /// Compute units consumed.
+    pub compute_units: u64,
+    /// Writable accounts whose balance or data changed, in message order.
+    /// Empty if the transaction failed.
+    pub account_changes: Vec<AccountChangeSummary>,
+    /// Program log messages.
+    pub logs: Vec<String>,
+}
+
+/// Outcome of a transaction that failed to load but was still charged fees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeesOnlyDetails {
+    /// The error that prevented the transaction from loading.
+    pub load_error: Option<TransactionError>,
+    /// Total fee charged to the fee payer, in lamports.
+    pub fee_lamports: u64,
 }
 
 impl RawSimulationResult {
@@ -28,6 +197,25 @@ impl RawSimulationResult {
                 cu
             ),
             prioritization_fee_details: None,
+            logs: Vec::new(),
+            logs_truncated: false,
+            max_cpi_depth: 0,
+            cpi_count: 0,
+            invoked_programs: Vec::new(),
+            error: None,
+            custom_error_code: None,
+            exceeded_compute_budget: false,
+            return_data: None,
+            post_accounts: Vec::new(),
+            fees_only: None,
+            account_data_sizes: Vec::new(),
+            inner_instructions: Vec::new(),
+            created_accounts: Vec::new(),
+            closed_accounts: Vec::new(),
+            loaded_accounts_data_size: 0,
+            advanced_nonce: None,
+            implied_priority_fee_lamports: None,
+            fee_lamports: None,
         }
     }
 
@@ -38,6 +226,38 @@ impl RawSimulationResult {
             cu: 0, // Or from simulation if available even on failure
             result: error.to_string(),
             prioritization_fee_details: None,
+            logs: Vec::new(),
+            logs_truncated: false,
+            max_cpi_depth: 0,
+            cpi_count: 0,
+            invoked_programs: Vec::new(),
+            error: None,
+            custom_error_code: None,
+            exceeded_compute_budget: false,
+            return_data: None,
+            post_accounts: Vec::new(),
+            fees_only: None,
+            account_data_sizes: Vec::new(),
+            inner_instructions: Vec::new(),
+            created_accounts: Vec::new(),
+            closed_accounts: Vec::new(),
+            loaded_accounts_data_size: 0,
+            advanced_nonce: None,
+            implied_priority_fee_lamports: None,
+            fee_lamports: None,
+        }
+    }
+
+    /// Splits the consumed compute units between builtin and BPF programs.
+    ///
+    /// BPF units are taken from the top-level `consumed` log lines; the rest of
+    /// the total is attributed to builtins.
+    pub fn cu_breakdown(&self) -> CuBreakdown {
+        let bpf = top_level_bpf_units(&self.logs).min(self.cu);
+        CuBreakdown {
+            builtin: self.cu - bpf,
+            bpf,
+            total: self.cu,
         }
     }
 
@@ -48,12 +268,194 @@ impl RawSimulationResult {
             cu: 0,
             result: "No base simulation results returned".to_string(),
             prioritization_fee_details: None,
+            logs: Vec::new(),
+            logs_truncated: false,
+            max_cpi_depth: 0,
+            cpi_count: 0,
+            invoked_programs: Vec::new(),
+            error: None,
+            custom_error_code: None,
+            exceeded_compute_budget: false,
+            return_data: None,
+            post_accounts: Vec::new(),
+            fees_only: None,
+            account_data_sizes: Vec::new(),
+            inner_instructions: Vec::new(),
+            created_accounts: Vec::new(),
+            closed_accounts: Vec::new(),
+            loaded_accounts_data_size: 0,
+            advanced_nonce: None,
+            implied_priority_fee_lamports: None,
+            fee_lamports: None,
+        }
+    }
+}
+
+/// Split of consumed compute units between builtin and BPF programs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CuBreakdown {
+    /// Compute units attributed to builtin programs (e.g. System Program).
+    pub builtin: u64,
+    /// Compute units attributed to BPF programs, including their CPIs.
+    pub bpf: u64,
+    /// Total compute units consumed by the transaction.
+    pub total: u64,
+}
+
+/// Sums the compute units consumed by the successful results of a batch.
+///
+/// Failed transactions are skipped; use `try_total_compute_units` to treat
+/// any failure as an error instead.
+pub fn total_compute_units(results: &[RawSimulationResult]) -> u64 {
+    results
+        .iter()
+        .filter(|res| res.success)
+        .map(|res| res.cu)
+        .sum()
+}
+
+/// Sums the compute units consumed by a batch, failing if any transaction failed.
+///
+/// The error names the index and message of the first failed result.
+pub fn try_total_compute_units(
+    results: &[RawSimulationResult],
+) -> Result<u64, SolanaClientExtError> {
+    if let Some((i, failed)) = results.iter().enumerate().find(|(_, res)| !res.success) {
+        return Err(SolanaClientExtError::ComputeUnitsError(format!(
+            "Transaction {} in batch failed: {}",
+            i, failed.result
+        )));
+    }
+    Ok(results.iter().map(|res| res.cu).sum())
+}
+
+/// Compute unit estimate with safety margins derived from the simulated value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CuEstimate {
+    /// Compute units consumed in simulation.
+    pub point: u64,
+    /// Suggested compute unit limit for regular sends.
+    pub recommended: u64,
+    /// Conservative compute unit limit covering most on-chain variance.
+    pub p95: u64,
+}
+
+/// Why a transaction failed in simulation, as returned by
+/// `RpcClientExt::try_estimate_compute_units`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulationFailure {
+    /// Structured transaction error, if the failure came from the runtime.
+    pub error: Option<TransactionError>,
+    /// Human-readable failure message.
+    pub message: String,
+    /// Program log messages emitted before the failure.
+    pub logs: Vec<String>,
+}
+
+/// Compute units measured locally and by the cluster, as returned by
+/// `RpcClientExt::compare_local_vs_rpc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CuComparison {
+    /// Compute units consumed in the local SVM simulation.
+    pub local: u64,
+    /// Compute units consumed in the RPC simulation, or why it failed.
+    pub rpc: Result<u64, String>,
+    /// `rpc - local`; positive when the cluster used more. 0 if `rpc` failed.
+    pub difference: i64,
+}
+
+/// A run of instructions that fits one transaction under the CU cap, as
+/// returned by `RpcClientExt::suggest_instruction_split`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstructionGroup {
+    /// Indices of the group's instructions, in order.
+    pub indices: Vec<usize>,
+    /// Compute units the group consumed in simulation, including its
+    /// `SetComputeUnitLimit` instruction.
+    pub cu: u64,
+    /// Compute units each instruction added to the group, in `indices` order.
+    /// The first delta also covers the `SetComputeUnitLimit` instruction.
+    pub cu_deltas: Vec<u64>,
+}
+
+/// A reason a local simulation of a transaction may be inaccurate, as
+/// reported by `RollUpChannel::diagnose`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// An instruction invokes a program account that doesn't exist.
+    MissingProgram { program_id: Pubkey },
+    /// An instruction invokes an account that isn't executable.
+    NotExecutable { program_id: Pubkey },
+    /// An instruction invokes a native program that isn't registered as a
+    /// builtin in the local SVM.
+    UnsupportedBuiltin { program_id: Pubkey },
+    /// An instruction invokes a program owned by a loader the local SVM can't
+    /// run programs for.
+    UnsupportedLoader { program_id: Pubkey, loader: Pubkey },
+    /// An instruction invokes an upgradeable program whose ProgramData account
+    /// (holding its bytecode) can't be loaded.
+    MissingProgramData {
+        program_id: Pubkey,
+        programdata_address: Pubkey,
+    },
+    /// An account the transaction references doesn't exist. Expected for
+    /// accounts the transaction creates.
+    MissingAccount { pubkey: Pubkey },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::MissingProgram { program_id } => {
+                write!(f, "program {} doesn't exist", program_id)
+            }
+            Diagnostic::NotExecutable { program_id } => {
+                write!(f, "program {} isn't executable", program_id)
+            }
+            Diagnostic::UnsupportedBuiltin { program_id } => {
+                write!(f, "builtin program {} isn't available locally", program_id)
+            }
+            Diagnostic::UnsupportedLoader { program_id, loader } => write!(
+                f,
+                "program {} is owned by loader {}, which isn't available locally",
+                program_id, loader
+            ),
+            Diagnostic::MissingProgramData {
+                program_id,
+                programdata_address,
+            } => write!(
+                f,
+                "program {} is missing its ProgramData account {}",
+                program_id, programdata_address
+            ),
+            Diagnostic::MissingAccount { pubkey } => write!(f, "account {} doesn't exist", pubkey),
         }
     }
 }
 
 // New Type Definitions for Analysis Results
 
+/// Size and shape of a transaction, as returned by `transaction_metrics`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxMetrics {
+    /// Size of the bincode-serialized (wire format) transaction, in bytes.
+    pub serialized_size: usize,
+    /// Number of signatures the message requires.
+    pub num_signatures: u8,
+    /// Number of account keys in the message.
+    pub num_accounts: usize,
+    /// Number of instructions in the message.
+    pub num_instructions: usize,
+}
+
+impl TxMetrics {
+    /// Returns `true` if the transaction doesn't fit in a single packet
+    /// (`PACKET_DATA_SIZE`, 1232 bytes) and would be rejected by the cluster.
+    pub fn exceeds_packet_limit(&self) -> bool {
+        self.serialized_size > PACKET_DATA_SIZE
+    }
+}
+
 /// Details related to compute unit estimation.
 #[derive(Debug, Clone)]
 pub struct ComputeUnitsDetails {
@@ -76,6 +478,18 @@ pub struct PrioritizationFeeDetails {
     pub error_message: Option<String>,
 }
 
+impl PrioritizationFeeDetails {
+    /// Returns the total fee in SOL.
+    pub fn total_fee_sol(&self) -> f64 {
+        lamports_to_sol(self.total_fee_lamports)
+    }
+
+    /// Renders the total fee for display, e.g. `"0.000012 SOL (12000 lamports)"`.
+    pub fn format_fee(&self) -> String {
+        format_lamports(self.total_fee_lamports)
+    }
+}
+
 /// Enum for different types of analysis result details.
 #[derive(Debug, Clone)]
 pub enum AnalysisResultDetail {
@@ -1,19 +1,92 @@
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::account::ReadableAccount;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
 use solana_sdk::{account::AccountSharedData, pubkey::Pubkey};
 use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
-use std::collections::HashMap;
-use std::sync::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+use crate::error::SolanaClientExtError;
+use crate::state::account_source::{AccountSource, FailoverAccountSource};
+use crate::utils::client::RpcClientHandle;
+
+/// What the loader does with an account whose data exceeds `max_account_data_bytes`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OversizedAccountPolicy {
+    /// Refuse to load the account and report `SolanaClientExtError::AccountDataTooLarge`.
+    #[default]
+    Error,
+    /// Keep the account but truncate its data to the limit, logging a warning
+    /// through the `log` facade.
+    Truncate,
+}
+
+/// Number of `prefetch` requests sent at once unless configured.
+pub const DEFAULT_PREFETCH_CONCURRENCY: usize = 4;
+
+/// Maximum number of keys in a single `getMultipleAccounts` request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Callback invoked with each account fetched from RPC.
+pub type AccountLoadHook<'a> = Box<dyn Fn(&Pubkey, &AccountSharedData) + Send + Sync + 'a>;
+
+/// A request made while simulating, as recorded by `RollUpAccountLoader::with_rpc_call_log`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcCall {
+    /// Name of the JSON-RPC method, e.g. `"getAccountInfo"`.
+    pub method: &'static str,
+    /// Accounts the request was made for.
+    pub args: Vec<Pubkey>,
+}
+
+/// An account held in the loader cache, with the time it was fetched.
+struct CachedAccount {
+    /// `None` if the source reported the account missing, so it isn't
+    /// requested again until the entry expires.
+    account: Option<AccountSharedData>,
+    /// When the account was fetched from RPC. `None` for accounts inserted
+    /// manually, which never expire.
+    loaded_at: Option<Instant>,
+    /// Loader tick of the last time the account was stored or read, for LRU eviction.
+    last_used: AtomicU64,
+}
 
 /// Lightweight account loader with an in-memory cache.
 ///
-/// Retrieves account data via RPC and caches it for fast repeated access.
+/// Retrieves account data via RPC (or any other `AccountSource`) and caches it
+/// for fast repeated access.
 /// Implements `TransactionProcessingCallback` for SVM integration.
 pub struct RollUpAccountLoader<'a> {
-    /// Local, thread-safe cache of account data (Pubkey -> AccountSharedData).
-    cache: RwLock<HashMap<Pubkey, AccountSharedData>>,
-    /// RPC client reference for fetching uncached accounts.
-    rpc_client: &'a RpcClient,
+    /// Local, thread-safe cache of account data (Pubkey -> CachedAccount).
+    cache: RwLock<HashMap<Pubkey, CachedAccount>>,
+    /// How long a cached account stays fresh. `None` means entries never expire.
+    cache_ttl: Option<Duration>,
+    /// Source used for fetching uncached accounts, usually the RPC client.
+    source: Box<dyn AccountSource + 'a>,
+    /// Upper bound on the data size of a loaded account. `None` means no limit.
+    max_account_data_bytes: Option<usize>,
+    /// How accounts above `max_account_data_bytes` are handled.
+    oversized_account_policy: OversizedAccountPolicy,
+    /// Optional hook called after each account is fetched from RPC (cache misses only).
+    on_load: Option<AccountLoadHook<'a>>,
+    /// Maximum number of batch requests `prefetch` sends at once.
+    prefetch_concurrency: usize,
+    /// Maximum number of fetched accounts kept in the cache. `None` means no limit.
+    max_cache_entries: Option<usize>,
+    /// Counter stamped on cache entries when used, ordering them by recency.
+    tick: AtomicU64,
+    /// Fetched accounts by the tick of their last use, oldest first, so the
+    /// least recently used one is found without scanning the cache. Always
+    /// locked after `cache`.
+    recency: Mutex<BTreeMap<u64, Pubkey>>,
+    /// Requests made so far, if recording is enabled.
+    rpc_call_log: Option<Mutex<Vec<RpcCall>>>,
+    /// Accounts the SVM was told are missing because they failed to load, with
+    /// the thread that loaded them, until that thread's simulation collects them.
+    load_errors: Mutex<Vec<(ThreadId, Pubkey, SolanaClientExtError)>>,
 }
 
 impl<'a> RollUpAccountLoader<'a> {
@@ -21,11 +94,434 @@ impl<'a> RollUpAccountLoader<'a> {
     ///
     /// Uses the given RPC client and caches retrieved accounts.
     pub fn new(rpc_client: &'a RpcClient) -> Self {
+        Self::with_client_handle(RpcClientHandle::Borrowed(rpc_client))
+    }
+
+    /// Creates a new `RollUpAccountLoader` that shares ownership of the RPC client.
+    ///
+    /// The resulting loader is `'static` and can be moved across threads.
+    pub fn new_shared(rpc_client: Arc<RpcClient>) -> RollUpAccountLoader<'static> {
+        RollUpAccountLoader::with_client_handle(RpcClientHandle::Shared(rpc_client))
+    }
+
+    pub(crate) fn with_client_handle(rpc_client: RpcClientHandle<'a>) -> Self {
+        Self::with_source(rpc_client)
+    }
+
+    /// Creates a new `RollUpAccountLoader` that fails over between RPC endpoints.
+    ///
+    /// Accounts are fetched from the first endpoint that answers, trying them
+    /// in order, so one provider's outage doesn't break simulations. See
+    /// `FailoverAccountSource`.
+    pub fn with_endpoints(endpoints: Vec<&'a RpcClient>) -> Self {
+        Self::with_source(FailoverAccountSource::new(endpoints))
+    }
+
+    /// Creates a new `RollUpAccountLoader` that fetches accounts from `source`.
+    ///
+    /// Pair it with a `MockAccountSource` to simulate fully offline.
+    pub fn with_source(source: impl AccountSource + 'a) -> Self {
         Self {
             cache: RwLock::new(HashMap::new()),
-            rpc_client,
+            cache_ttl: None,
+            source: Box::new(source),
+            max_account_data_bytes: None,
+            oversized_account_policy: OversizedAccountPolicy::default(),
+            on_load: None,
+            prefetch_concurrency: DEFAULT_PREFETCH_CONCURRENCY,
+            max_cache_entries: None,
+            tick: AtomicU64::new(0),
+            recency: Mutex::new(BTreeMap::new()),
+            rpc_call_log: None,
+            load_errors: Mutex::new(Vec::new()),
         }
     }
+
+    /// Limits the data size of accounts loaded from RPC.
+    ///
+    /// Accounts above `max_bytes` are rejected or truncated depending on `policy`.
+    /// Simulations fail the transactions that reference a rejected account.
+    /// Protects long-running simulation services from loading huge accounts
+    /// (e.g. large lookup tables or program data) by accident.
+    pub fn with_max_account_data_bytes(
+        mut self,
+        max_bytes: usize,
+        policy: OversizedAccountPolicy,
+    ) -> Self {
+        self.max_account_data_bytes = Some(max_bytes);
+        self.oversized_account_policy = policy;
+        self
+    }
+
+    /// Expires cached accounts after `ttl`, so they are re-fetched from RPC.
+    ///
+    /// Keeps long-running services from simulating against stale on-chain state.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Registers a hook invoked after each successful account load from RPC.
+    ///
+    /// Cache hits don't trigger the hook. Useful for logging, metrics or
+    /// auditing which accounts simulations touch.
+    pub fn with_on_load(
+        mut self,
+        on_load: impl Fn(&Pubkey, &AccountSharedData) + Send + Sync + 'a,
+    ) -> Self {
+        self.on_load = Some(Box::new(on_load));
+        self
+    }
+
+    /// Sets how many batch requests `prefetch` sends at once (at least 1).
+    pub fn with_prefetch_concurrency(mut self, prefetch_concurrency: usize) -> Self {
+        self.prefetch_concurrency = prefetch_concurrency.max(1);
+        self
+    }
+
+    /// Bounds the cache to `max_entries` fetched accounts (at least 1).
+    ///
+    /// Once full, caching another account evicts the least recently used one;
+    /// evicted accounts are fetched again when needed. Accounts added with
+    /// `insert_account` or `clone_from` don't count towards the limit and are
+    /// never evicted. Keeps the memory of long-running services bounded.
+    pub fn with_max_cache_entries(mut self, max_entries: usize) -> Self {
+        self.max_cache_entries = Some(max_entries.max(1));
+        self
+    }
+
+    /// Records every request the loader sends to its account source, and the
+    /// priority fee requests of channels using it, for `rpc_call_log`.
+    ///
+    /// Useful to find out why a simulation is slow, e.g. one `getAccountInfo`
+    /// per account where a `prefetch` would batch them. Requests are named after
+    /// the RPC method they map to, even for sources other than RPC.
+    pub fn with_rpc_call_log(mut self) -> Self {
+        self.rpc_call_log = Some(Mutex::new(Vec::new()));
+        self
+    }
+
+    /// Returns the requests recorded so far, in order. Empty unless enabled
+    /// with `with_rpc_call_log`.
+    pub fn rpc_call_log(&self) -> Vec<RpcCall> {
+        self.rpc_call_log
+            .as_ref()
+            .map(|log| log.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    /// Forgets the requests recorded so far.
+    pub fn clear_rpc_call_log(&self) {
+        if let Some(log) = &self.rpc_call_log {
+            log.lock().unwrap().clear();
+        }
+    }
+
+    /// Appends a request to the call log, if recording is enabled.
+    pub(crate) fn record_rpc_call(&self, method: &'static str, args: &[Pubkey]) {
+        if let Some(log) = &self.rpc_call_log {
+            log.lock().unwrap().push(RpcCall {
+                method,
+                args: args.to_vec(),
+            });
+        }
+    }
+
+    /// Returns the load errors recorded on the calling thread since the last
+    /// call, oldest first.
+    ///
+    /// The SVM can only see a failed load as a missing account; simulations
+    /// collect the errors afterwards to fail the transactions that needed them.
+    /// Loads run on the simulating thread, so concurrent simulations sharing
+    /// the loader don't see each other's errors.
+    pub(crate) fn take_load_errors(&self) -> Vec<(Pubkey, SolanaClientExtError)> {
+        let current = std::thread::current().id();
+        let mut load_errors = self.load_errors.lock().unwrap();
+        let (taken, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut *load_errors)
+            .into_iter()
+            .partition(|(thread, _, _)| *thread == current);
+        *load_errors = kept;
+        taken
+            .into_iter()
+            .map(|(_, pubkey, err)| (pubkey, err))
+            .collect()
+    }
+
+    /// Records why `pubkey` failed to load on the calling thread, replacing
+    /// an earlier error for it.
+    fn record_load_error(&self, pubkey: &Pubkey, err: SolanaClientExtError) {
+        let current = std::thread::current().id();
+        let mut load_errors = self.load_errors.lock().unwrap();
+        load_errors.retain(|(thread, key, _)| *thread != current || key != pubkey);
+        load_errors.push((current, *pubkey, err));
+    }
+
+    /// Inserts an account into the cache, taking precedence over RPC data.
+    ///
+    /// Inserted accounts never expire, but are removed by `invalidate` and `clear_cache`.
+    pub fn insert_account(&self, pubkey: Pubkey, account: AccountSharedData) {
+        let mut cache = self.cache.write().unwrap();
+        let replaced = cache.insert(
+            pubkey,
+            CachedAccount {
+                account: Some(account),
+                loaded_at: None,
+                last_used: AtomicU64::new(self.next_tick()),
+            },
+        );
+        self.unindex(replaced);
+    }
+
+    /// Fetches an account from another cluster and inserts it into the cache,
+    /// like `solana-test-validator --clone`.
+    ///
+    /// Lets simulations run against e.g. mainnet state while the loader's own
+    /// source points at devnet. Cloning an upgradeable program clones its
+    /// ProgramData account too. Cloned accounts behave like those added with
+    /// `insert_account`.
+    pub fn clone_from(
+        &self,
+        source_rpc: &RpcClient,
+        pubkey: &Pubkey,
+    ) -> Result<AccountSharedData, SolanaClientExtError> {
+        let account: AccountSharedData = source_rpc.get_account(pubkey)?.into();
+        self.insert_account(*pubkey, account.clone());
+        if let Some(programdata_address) = programdata_address(&account) {
+            let programdata = source_rpc.get_account(&programdata_address)?;
+            self.insert_account(programdata_address, programdata.into());
+        }
+        Ok(account)
+    }
+
+    /// Removes every account from the cache.
+    pub fn clear_cache(&self) {
+        let mut cache = self.cache.write().unwrap();
+        cache.clear();
+        self.recency.lock().unwrap().clear();
+    }
+
+    /// Removes a single account from the cache, forcing a re-fetch on next load.
+    pub fn invalidate(&self, pubkey: &Pubkey) {
+        let mut cache = self.cache.write().unwrap();
+        let removed = cache.remove(pubkey);
+        self.unindex(removed);
+    }
+
+    /// Loads an account, checking cache first and falling back to RPC.
+    ///
+    /// Returns `Ok(None)` if the account does not exist, the source's error if
+    /// it can't be fetched, and `Err(SolanaClientExtError::AccountDataTooLarge)`
+    /// if it exceeds the configured size limit under
+    /// `OversizedAccountPolicy::Error`. Failed fetches aren't cached. Missing
+    /// accounts are cached as missing, and expire like fetched ones, so e.g.
+    /// sysvars the cluster doesn't have aren't requested on every simulation.
+    pub fn load_account(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<Option<AccountSharedData>, SolanaClientExtError> {
+        if let Some(cached) = self.cache.read().unwrap().get(pubkey) {
+            if self.is_fresh(cached) {
+                self.touch(pubkey, cached);
+                return Ok(cached.account.clone());
+            }
+        }
+
+        // If not cached, fetch from the account source
+        self.record_rpc_call("getAccountInfo", std::slice::from_ref(pubkey));
+        match self.source.get_account(pubkey)? {
+            Some(account) => self.store_fetched(pubkey, account).map(Some),
+            None => {
+                self.cache_fetched(pubkey, None);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Fetches every uncached account in `pubkeys` ahead of time.
+    ///
+    /// Keys are fetched in batches of at most 100 (the `getMultipleAccounts`
+    /// limit), with up to `prefetch_concurrency` batches in flight at once.
+    /// Missing accounts are cached as missing. If a batch request fails or an
+    /// account is rejected by the size guard, the remaining batches are still
+    /// fetched and the first error is returned; the accounts it concerns stay
+    /// uncached, so `load_account` fetches them again.
+    pub fn prefetch(&self, pubkeys: &[Pubkey]) -> Result<(), SolanaClientExtError> {
+        let mut missing: Vec<Pubkey> = {
+            let cache = self.cache.read().unwrap();
+            pubkeys
+                .iter()
+                .filter(|pubkey| !cache.get(pubkey).is_some_and(|cached| self.is_fresh(cached)))
+                .copied()
+                .collect()
+        };
+        missing.sort_unstable();
+        missing.dedup();
+
+        let batches: Vec<&[Pubkey]> = missing.chunks(MAX_MULTIPLE_ACCOUNTS).collect();
+        for keys in &batches {
+            self.record_rpc_call("getMultipleAccounts", keys);
+        }
+        let mut first_err = None;
+        for group in batches.chunks(self.prefetch_concurrency) {
+            let source = &self.source;
+            let fetched: Vec<Result<Vec<Option<AccountSharedData>>, SolanaClientExtError>> =
+                if group.len() == 1 {
+                    vec![source.get_multiple_accounts(group[0])]
+                } else {
+                    std::thread::scope(|scope| {
+                        let handles: Vec<_> = group
+                            .iter()
+                            .map(|keys| scope.spawn(move || source.get_multiple_accounts(keys)))
+                            .collect();
+                        handles
+                            .into_iter()
+                            .map(|handle| handle.join().unwrap())
+                            .collect()
+                    })
+                };
+
+            for (keys, accounts) in group.iter().zip(fetched) {
+                let stored = accounts.and_then(|accounts| {
+                    keys.iter()
+                        .zip(accounts)
+                        .map(|(pubkey, account)| match account {
+                            Some(account) => self.store_fetched(pubkey, account).map(|_| ()),
+                            None => {
+                                self.cache_fetched(pubkey, None);
+                                Ok(())
+                            }
+                        })
+                        .fold(Ok(()), |first, stored| first.and(stored))
+                });
+                if let Err(err) = stored {
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+        first_err.map_or(Ok(()), Err)
+    }
+
+    /// Returns whether a cache entry is still within the cache TTL.
+    fn is_fresh(&self, cached: &CachedAccount) -> bool {
+        match (self.cache_ttl, cached.loaded_at) {
+            (Some(ttl), Some(loaded_at)) => loaded_at.elapsed() < ttl,
+            _ => true,
+        }
+    }
+
+    /// Returns the next recency stamp.
+    fn next_tick(&self) -> u64 {
+        self.tick.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Marks a fetched account as just used. Accounts inserted manually are
+    /// never evicted, so their recency isn't tracked.
+    fn touch(&self, pubkey: &Pubkey, cached: &CachedAccount) {
+        if cached.loaded_at.is_none() {
+            return;
+        }
+        let mut recency = self.recency.lock().unwrap();
+        let tick = self.next_tick();
+        recency.remove(&cached.last_used.swap(tick, Ordering::Relaxed));
+        recency.insert(tick, *pubkey);
+    }
+
+    /// Drops an entry removed from the cache from the recency index.
+    fn unindex(&self, removed: Option<CachedAccount>) {
+        if let Some(removed) = removed.filter(|removed| removed.loaded_at.is_some()) {
+            self.recency
+                .lock()
+                .unwrap()
+                .remove(&removed.last_used.into_inner());
+        }
+    }
+
+    /// Caches the result of a fetch, evicting the least recently used fetched
+    /// accounts while there are more than `max_cache_entries` of them.
+    fn cache_fetched(&self, pubkey: &Pubkey, account: Option<AccountSharedData>) {
+        // Loads of the account now hit the cache, so earlier errors are stale.
+        self.load_errors
+            .lock()
+            .unwrap()
+            .retain(|(_, key, _)| key != pubkey);
+
+        let mut cache = self.cache.write().unwrap();
+        let tick = self.next_tick();
+        let replaced = cache.insert(
+            *pubkey,
+            CachedAccount {
+                account,
+                loaded_at: Some(Instant::now()),
+                last_used: AtomicU64::new(tick),
+            },
+        );
+        self.unindex(replaced);
+
+        let mut recency = self.recency.lock().unwrap();
+        recency.insert(tick, *pubkey);
+        let Some(max_entries) = self.max_cache_entries else {
+            return;
+        };
+        while recency.len() > max_entries {
+            if let Some((_, evicted)) = recency.pop_first() {
+                cache.remove(&evicted);
+            }
+        }
+    }
+
+    /// Applies the size guard to a freshly fetched account, caches it and
+    /// runs the load hook.
+    fn store_fetched(
+        &self,
+        pubkey: &Pubkey,
+        mut account: AccountSharedData,
+    ) -> Result<AccountSharedData, SolanaClientExtError> {
+        if let Some(max_bytes) = self.max_account_data_bytes {
+            let data_len = account.data().len();
+            if data_len > max_bytes {
+                match self.oversized_account_policy {
+                    OversizedAccountPolicy::Error => {
+                        return Err(SolanaClientExtError::AccountDataTooLarge {
+                            pubkey: *pubkey,
+                            data_len,
+                            max_data_len: max_bytes,
+                        });
+                    }
+                    OversizedAccountPolicy::Truncate => {
+                        log::warn!(
+                            "truncating account {} data from {} to {} bytes",
+                            pubkey,
+                            data_len,
+                            max_bytes
+                        );
+                        account.resize(max_bytes, 0);
+                    }
+                }
+            }
+        }
+
+        // Cache for future lookups
+        self.cache_fetched(pubkey, Some(account.clone()));
+
+        if let Some(on_load) = &self.on_load {
+            on_load(pubkey, &account);
+        }
+
+        Ok(account)
+    }
+}
+
+/// Returns the ProgramData address if `account` is a BPF-Upgradeable program account.
+pub(crate) fn programdata_address(account: &AccountSharedData) -> Option<Pubkey> {
+    if !bpf_loader_upgradeable::check_id(account.owner()) {
+        return None;
+    }
+    match bincode::deserialize(account.data()) {
+        Ok(UpgradeableLoaderState::Program {
+            programdata_address,
+        }) => Some(programdata_address),
+        _ => None,
+    }
 }
 
 /// Implements `TransactionProcessingCallback` for SVM transaction processing.
@@ -35,18 +531,16 @@ impl TransactionProcessingCallback for RollUpAccountLoader<'_> {
     /// Retrieves account data for a given public key.
     ///
     /// Checks cache first, then fetches via RPC and caches if not found.
+    /// Accounts that fail to load (e.g. rejected by the size guard) are
+    /// reported as missing, and the error is kept for `take_load_errors`.
     fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
-        if let Some(account) = self.cache.read().unwrap().get(pubkey) {
-            return Some(account.clone());
+        match self.load_account(pubkey) {
+            Ok(account) => account,
+            Err(err) => {
+                self.record_load_error(pubkey, err);
+                None
+            }
         }
-
-        // If not cached, fetch from RPC
-        let account: AccountSharedData = self.rpc_client.get_account(pubkey).ok()?.into();
-
-        // Cache for future lookups
-        self.cache.write().unwrap().insert(*pubkey, account.clone());
-
-        Some(account)
     }
 
     /// Checks if an account is owned by one of the provided owners.
@@ -57,3 +551,70 @@ impl TransactionProcessingCallback for RollUpAccountLoader<'_> {
             .and_then(|account| owners.iter().position(|key| account.owner().eq(key)))
     }
 }
+
+/// Layers per-call account overrides on top of another account source.
+///
+/// Overridden accounts are served first and never written to the underlying
+/// cache, so they only affect the simulation they are passed to.
+pub(crate) struct OverriddenAccountLoader<'b, CB: TransactionProcessingCallback> {
+    /// Account source used for keys without an override.
+    base: &'b CB,
+    /// Accounts that take precedence over `base`.
+    overrides: &'b HashMap<Pubkey, AccountSharedData>,
+}
+
+impl<'b, CB: TransactionProcessingCallback> OverriddenAccountLoader<'b, CB> {
+    /// Creates a loader serving `overrides` before falling back to `base`.
+    pub(crate) fn new(base: &'b CB, overrides: &'b HashMap<Pubkey, AccountSharedData>) -> Self {
+        Self { base, overrides }
+    }
+}
+
+impl<CB: TransactionProcessingCallback> TransactionProcessingCallback
+    for OverriddenAccountLoader<'_, CB>
+{
+    fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.overrides
+            .get(pubkey)
+            .cloned()
+            .or_else(|| self.base.get_account_shared_data(pubkey))
+    }
+
+    fn account_matches_owners(&self, account: &Pubkey, owners: &[Pubkey]) -> Option<usize> {
+        self.get_account_shared_data(account)
+            .and_then(|account| owners.iter().position(|key| account.owner().eq(key)))
+    }
+}
+
+/// Serves fallback accounts for keys another account source has no account for.
+///
+/// Unlike overrides, accounts of the underlying source take precedence, so
+/// fallbacks only stand in for accounts the source lacks.
+pub(crate) struct FallbackAccountLoader<'b, CB: TransactionProcessingCallback> {
+    /// Account source tried first.
+    base: &'b CB,
+    /// Accounts served for keys `base` has no account for.
+    fallbacks: &'b HashMap<Pubkey, AccountSharedData>,
+}
+
+impl<'b, CB: TransactionProcessingCallback> FallbackAccountLoader<'b, CB> {
+    /// Creates a loader falling back to `fallbacks` for keys `base` lacks.
+    pub(crate) fn new(base: &'b CB, fallbacks: &'b HashMap<Pubkey, AccountSharedData>) -> Self {
+        Self { base, fallbacks }
+    }
+}
+
+impl<CB: TransactionProcessingCallback> TransactionProcessingCallback
+    for FallbackAccountLoader<'_, CB>
+{
+    fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.base
+            .get_account_shared_data(pubkey)
+            .or_else(|| self.fallbacks.get(pubkey).cloned())
+    }
+
+    fn account_matches_owners(&self, account: &Pubkey, owners: &[Pubkey]) -> Option<usize> {
+        self.get_account_shared_data(account)
+            .and_then(|account| owners.iter().position(|key| account.owner().eq(key)))
+    }
+}
@@ -1,41 +1,186 @@
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
 
 use solana_client::rpc_client::RpcClient;
 use solana_compute_budget::compute_budget::ComputeBudget;
+use solana_compute_budget::compute_budget_limits::MAX_COMPUTE_UNIT_LIMIT;
+use solana_sdk::account::{
+    create_account_shared_data_for_test, AccountSharedData, ReadableAccount, WritableAccount,
+};
+use solana_sdk::clock::{Clock, Epoch, Slot};
 use solana_sdk::fee::FeeStructure;
 use solana_sdk::hash::Hash;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
 use solana_sdk::rent_collector::RentCollector;
-use solana_sdk::transaction::{SanitizedTransaction as SolanaSanitizedTransaction, Transaction};
+use solana_sdk::sysvar::{self, Sysvar};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::address_lookup_table::state::AddressLookupTable;
+use solana_sdk::native_loader;
+use solana_sdk::message::v0::LoadedAddresses;
+use solana_sdk::message::{AddressLookupTableAccount, VersionedMessage};
+use solana_sdk::transaction::{
+    SanitizedTransaction as SolanaSanitizedTransaction, Transaction, TransactionError,
+    VersionedTransaction,
+};
 
 use agave_feature_set::FeatureSet;
+use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
+use solana_svm_rent_collector::svm_rent_collector::SVMRentCollector;
 use solana_svm::transaction_processing_result::ProcessedTransaction;
 use solana_svm::transaction_processor::{
-    TransactionProcessingConfig, TransactionProcessingEnvironment,
+    ExecutionRecordingConfig, TransactionBatchProcessor, TransactionProcessingConfig,
+    TransactionProcessingEnvironment,
 };
 
 use crate::state::return_struct::{
-    AnalysisResultDetail, ComputeUnitsDetails, RawSimulationResult, SimulationAnalysisResult,
-    PrioritizationFeeDetails,
+    AccountChangeSummary, AccountDataSizeChange, AnalysisResultDetail, ComputeUnitsDetails,
+    Diagnostic, FeesOnlyDetails, InnerInstructionCu, RawSimulationResult, SimulationAnalysisResult,
+    PrioritizationFeeDetails, TransactionSummary,
+};
+use crate::state::rollup_account_loader::{
+    programdata_address, FallbackAccountLoader, OverriddenAccountLoader, RollUpAccountLoader,
+    RpcCall,
 };
-use crate::state::rollup_account_loader::RollUpAccountLoader;
-use crate::utils::helpers::{create_transaction_batch_processor, get_transaction_check_results};
+use crate::utils::client::RpcClientHandle;
+use crate::error::SolanaClientExtError;
+use crate::utils::helpers::{
+    builtin_compute_units, builtin_program_ids, clock_sysvar, create_transaction_batch_processor,
+    decode_transaction, get_transaction_check_results, nonce_data, program_account_from_elf,
+    rent_sysvar, set_compute_unit_limit, stake_accounts, v0_to_legacy_message, BPF_LOADERS,
+};
+use crate::utils::logs::{cpi_count, inner_invocation_units, invoked_programs, max_invoke_depth};
+use crate::fees::{estimate_base_fee, BatchFeeEstimate, FeeModel, TotalFee};
 use crate::AnalysisConfig;
+use crate::EstimatedPrioritizationFee;
+use crate::parse_compute_budget;
 use crate::ForkRollUpGraph;
 use crate::RpcClientExt;
 
+/// Slot simulations run at if the account source has no `Clock` sysvar.
+const DEFAULT_SLOT: Slot = 1;
+
+/// Epoch simulations run in unless `RollUpChannelConfig::epoch` is set.
+const DEFAULT_EPOCH: Epoch = 1;
+
+/// Chunk size used by `simulate_transactions_iter` unless configured.
+pub const DEFAULT_ITER_CHUNK_SIZE: usize = 64;
+
+/// Runtime settings used by `RollUpChannel` simulations.
+///
+/// Defaults match the channel's previous hardcoded behavior.
+#[derive(Debug, Clone, Default)]
+pub struct RollUpChannelConfig {
+    /// Compute budget used to build the program runtime environment.
+    pub compute_budget: ComputeBudget,
+    /// Rent collector passed to the SVM. `None` disables rent collection and
+    /// the rent-state checks that go with it.
+    pub rent_collector: Option<RentCollector>,
+    /// Highest compute unit limit `simulate_with_auto_budget` will try.
+    /// `None` means `MAX_COMPUTE_UNIT_LIMIT`.
+    pub auto_budget_ceiling: Option<u64>,
+    /// Number of transactions `simulate_transactions_iter` simulates at a time.
+    /// `None` means `DEFAULT_ITER_CHUNK_SIZE`.
+    pub iter_chunk_size: Option<usize>,
+    /// If `true`, `simulate_transactions_raw` keeps its transaction batch
+    /// processor between calls, so builtins are registered and programs loaded
+    /// only once. Sysvars are read when the processor is built; call
+    /// `RollUpChannel::reset_processor` after changing them or redeploying a
+    /// program in the account source.
+    pub reuse_processor: bool,
+    /// Runtime features active during simulation, e.g. fetched from the
+    /// cluster to match its CU costs and behavior. `None` means
+    /// `FeatureSet::all_enabled()`.
+    pub feature_set: Option<Arc<FeatureSet>>,
+    /// Byte limit on each transaction's logs; later lines are dropped and
+    /// `RawSimulationResult::logs_truncated` is set. Overrides the processing
+    /// config's `log_messages_bytes_limit`. `None` keeps that limit.
+    pub max_log_bytes: Option<usize>,
+    /// Total active stake of the current epoch, in lamports, as returned to
+    /// programs by the `sol_get_epoch_stake` syscall. Set it to the cluster's
+    /// value for programs with stake-weighted logic; defaults to 0.
+    pub epoch_total_stake: u64,
+    /// Fee charged per signature, in lamports. Used for the fee the SVM
+    /// charges, for the durable nonce check and by `estimate_batch_fees`.
+    /// `None` means the default fee structure's 5,000 lamports.
+    pub lamports_per_signature: Option<u64>,
+    /// Epoch the processor runs in. Rent is collected for this epoch too
+    /// (with `rent_collector`, or a default collector at the cluster's `Rent`),
+    /// so the `rent_epoch` of loaded accounts is consistent with it, and the
+    /// `Clock` sysvar programs read reports it. `None` means epoch 1, with rent
+    /// and the `Clock` sysvar left as they are.
+    pub epoch: Option<Epoch>,
+}
+
+/// A transaction batch processor and the runtime state it was built with.
+struct BatchProcessor {
+    processor: TransactionBatchProcessor<ForkRollUpGraph>,
+    feature_set: Arc<FeatureSet>,
+    /// Keeps the fork graph alive; the processor only holds a weak reference to it.
+    _fork_graph: Arc<RwLock<ForkRollUpGraph>>,
+}
+
+impl BatchProcessor {
+    fn new<CB: TransactionProcessingCallback>(
+        callbacks: &CB,
+        feature_set: Arc<FeatureSet>,
+        compute_budget: &ComputeBudget,
+        slot: Slot,
+        epoch: Epoch,
+    ) -> Self {
+        // Creates an SVM-compatible transaction batch processor.
+        // Entry point for executing transactions against Solana runtime logic.
+        let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph {}));
+        let processor = create_transaction_batch_processor(
+            callbacks,
+            &feature_set,
+            compute_budget,
+            Arc::clone(&fork_graph),
+            slot,
+            epoch,
+        );
+
+        Self {
+            processor,
+            feature_set,
+            _fork_graph: fork_graph,
+        }
+    }
+}
+
 /// Handles a group of accounts and simulates transactions using Solana's SVM.
 ///
-/// Uses preconfigured defaults for the SVM runtime.
+/// Uses preconfigured defaults for the SVM runtime, which can be overridden
+/// with `RollUpChannel::with_config`. Transactions execute at the slot of the
+/// `Clock` sysvar loaded from the account source (slot 1 if it has none), so
+/// the processor agrees with the clock programs read.
 pub struct RollUpChannel<'a> {
     /// Account keys from the transaction, used for SVM simulation.
     #[allow(dead_code)]
     keys: Vec<Pubkey>,
-    /// RPC client reference for fetching account and cluster data.
-    rpc_client: &'a RpcClient,
-    /// Stores `SimulationAnalysisResult` for tagged transactions.
-    tagged_results: HashMap<String, Vec<SimulationAnalysisResult>>,
+    /// RPC client used for fetching account and cluster data.
+    rpc_client: RpcClientHandle<'a>,
+    /// Account loader shared across simulations, so its cache persists between calls.
+    account_loader: RollUpAccountLoader<'a>,
+    /// Custom priority fee model. `None` uses the built-in RPC-based estimate.
+    fee_model: Option<Box<dyn FeeModel + 'a>>,
+    /// Runtime settings for simulations.
+    config: RollUpChannelConfig,
+    /// SVM processing config (recording, log byte limit, ...) used for simulations.
+    processing_config: TransactionProcessingConfig<'a>,
+    /// `log_messages_bytes_limit` of the processing config as it was set, used
+    /// when `RollUpChannelConfig::max_log_bytes` is `None`.
+    log_messages_bytes_limit: Option<usize>,
+    /// Stores `SimulationAnalysisResult` for tagged transactions. Shared and
+    /// lock-protected so analyses can be tagged concurrently through `&self`.
+    tagged_results: Arc<RwLock<HashMap<String, Vec<SimulationAnalysisResult>>>>,
+    /// Processor kept across `simulate_transactions_raw` calls when
+    /// `RollUpChannelConfig::reuse_processor` is set.
+    warm_processor: Mutex<Option<Arc<BatchProcessor>>>,
+    /// Address lookup tables used to resolve `v0` transactions, by address.
+    /// Tables not listed here are loaded through the account loader.
+    lookup_tables: HashMap<Pubkey, AddressLookupTableAccount>,
 }
 
 impl<'a> RollUpChannel<'a> {
@@ -43,72 +188,944 @@ impl<'a> RollUpChannel<'a> {
     ///
     /// Takes a list of public keys and an RPC client reference.
     pub fn new(keys: Vec<Pubkey>, rpc_client: &'a RpcClient) -> Self {
+        Self::with_client_handle(keys, RpcClientHandle::Borrowed(rpc_client))
+    }
+
+    /// Constructs a `RollUpChannel` that shares ownership of the RPC client.
+    ///
+    /// The resulting channel is `'static`, `Send` and `Sync`, so it can be put
+    /// behind an `Arc` and used from several worker threads with a single,
+    /// shared account cache.
+    pub fn new_shared(keys: Vec<Pubkey>, rpc_client: Arc<RpcClient>) -> RollUpChannel<'static> {
+        RollUpChannel::with_client_handle(keys, RpcClientHandle::Shared(rpc_client))
+    }
+
+    fn with_client_handle(keys: Vec<Pubkey>, rpc_client: RpcClientHandle<'a>) -> Self {
         Self {
             keys,
+            account_loader: RollUpAccountLoader::with_client_handle(rpc_client.clone()),
             rpc_client,
-            tagged_results: HashMap::new(),
+            fee_model: None,
+            config: RollUpChannelConfig::default(),
+            processing_config: Self::default_processing_config(),
+            log_messages_bytes_limit: None,
+            tagged_results: Arc::new(RwLock::new(HashMap::new())),
+            warm_processor: Mutex::new(None),
+            lookup_tables: HashMap::new(),
         }
     }
 
+    /// Replaces the channel's account loader.
+    ///
+    /// Use this to simulate with a custom-configured loader, e.g. one with a
+    /// `max_account_data_bytes` guard.
+    pub fn with_account_loader(mut self, account_loader: RollUpAccountLoader<'a>) -> Self {
+        self.account_loader = account_loader;
+        self.reset_processor();
+        self
+    }
+
+    /// Replaces the channel's runtime settings.
+    pub fn with_config(mut self, config: RollUpChannelConfig) -> Self {
+        self.config = config;
+        self.apply_max_log_bytes();
+        self.reset_processor();
+        self
+    }
+
+    /// Supplies pre-fetched address lookup tables, keyed by table address.
+    ///
+    /// `simulate_versioned_transactions` resolves lookups from these tables
+    /// instead of loading them, which saves a fetch per table when many `v0`
+    /// transactions share the same tables.
+    pub fn with_lookup_tables(
+        mut self,
+        lookup_tables: HashMap<Pubkey, AddressLookupTableAccount>,
+    ) -> Self {
+        self.lookup_tables = lookup_tables;
+        self
+    }
+
+    /// Returns the feature set simulations run with.
+    fn feature_set(&self) -> Arc<FeatureSet> {
+        self.config
+            .feature_set
+            .clone()
+            .unwrap_or_else(|| Arc::new(FeatureSet::all_enabled()))
+    }
+
+    /// Returns the fee structure simulations charge, with the configured
+    /// `RollUpChannelConfig::lamports_per_signature`.
+    fn fee_structure(&self) -> FeeStructure {
+        let fee_structure = FeeStructure::default();
+        FeeStructure {
+            lamports_per_signature: self
+                .config
+                .lamports_per_signature
+                .unwrap_or(fee_structure.lamports_per_signature),
+            ..fee_structure
+        }
+    }
+
+    /// Returns the slot simulations loading accounts through `callbacks` run
+    /// at: the `Clock` sysvar's, so the processor and the clock programs read
+    /// agree, or `DEFAULT_SLOT` if there is no clock.
+    fn slot<CB: TransactionProcessingCallback>(callbacks: &CB) -> Slot {
+        clock_sysvar(callbacks).map_or(DEFAULT_SLOT, |clock| clock.slot)
+    }
+
+    /// Returns the rent simulated accounts pay: the configured
+    /// `RollUpChannelConfig::rent_collector`'s, else the `Rent` sysvar of the
+    /// account source, else `Rent::default()`.
+    pub(crate) fn rent(&self) -> Rent {
+        self.config
+            .rent_collector
+            .as_ref()
+            .map(|rent_collector| rent_collector.rent.clone())
+            .or_else(|| rent_sysvar(&self.account_loader))
+            .unwrap_or_default()
+    }
+
+    /// Returns the epoch simulations run in.
+    fn epoch(&self) -> Epoch {
+        self.config.epoch.unwrap_or(DEFAULT_EPOCH)
+    }
+
+    /// Returns the `Clock` sysvar to serve instead of the one `callbacks` load,
+    /// keyed by its id: theirs (or the default clock at the simulated slot)
+    /// with `epoch` set to `RollUpChannelConfig::epoch`. Empty if no epoch is
+    /// configured.
+    fn clock_override<CB: TransactionProcessingCallback>(
+        &self,
+        callbacks: &CB,
+    ) -> HashMap<Pubkey, AccountSharedData> {
+        let Some(epoch) = self.config.epoch else {
+            return HashMap::new();
+        };
+        let clock = Clock {
+            slot: Self::slot(callbacks),
+            epoch,
+            ..clock_sysvar(callbacks).unwrap_or_default()
+        };
+        HashMap::from([(sysvar::clock::id(), create_account_shared_data_for_test(&clock))])
+    }
+
+    /// Returns the accounts served when `callbacks` lack them, so staking
+    /// instructions simulate against any account source: `stake_accounts` with
+    /// a `Clock` at the simulated slot and epoch, and the simulated rent.
+    fn fallback_accounts<CB: TransactionProcessingCallback>(
+        &self,
+        callbacks: &CB,
+    ) -> HashMap<Pubkey, AccountSharedData> {
+        let clock = Clock {
+            slot: Self::slot(callbacks),
+            epoch: self.epoch(),
+            ..Clock::default()
+        };
+        stake_accounts(&clock, &self.rent())
+    }
+
+    /// Builds a processor over `callbacks` with `compute_budget`, running at
+    /// their `Clock` sysvar's slot in the configured epoch.
+    fn new_processor<CB: TransactionProcessingCallback>(
+        &self,
+        callbacks: &CB,
+        compute_budget: &ComputeBudget,
+    ) -> BatchProcessor {
+        // The sysvar cache is filled from the callbacks, so `Clock::get` sees
+        // the configured epoch too, and sysvars the source lacks are defaulted.
+        let fallbacks = self.fallback_accounts(callbacks);
+        let callbacks = FallbackAccountLoader::new(callbacks, &fallbacks);
+        let clock_override = self.clock_override(&callbacks);
+        let callbacks = OverriddenAccountLoader::new(&callbacks, &clock_override);
+        BatchProcessor::new(
+            &callbacks,
+            self.feature_set(),
+            compute_budget,
+            Self::slot(&callbacks),
+            self.epoch(),
+        )
+    }
+
+    /// Drops the processor kept by `RollUpChannelConfig::reuse_processor`,
+    /// so the next simulation rebuilds it from the current accounts.
+    pub fn reset_processor(&self) {
+        *self.warm_processor.lock().unwrap() = None;
+    }
+
+    /// Returns the channel's runtime settings.
+    pub fn config(&self) -> &RollUpChannelConfig {
+        &self.config
+    }
+
+    /// Replaces the SVM processing config used for simulations.
+    ///
+    /// Useful to raise `log_messages_bytes_limit` for programs with long logs,
+    /// or to change what is recorded during execution. Start from
+    /// `RollUpChannel::default_processing_config` to keep log and return data
+    /// recording, which the result fields rely on. `RollUpChannelConfig::max_log_bytes`,
+    /// if set, takes precedence over its `log_messages_bytes_limit`.
+    pub fn with_processing_config(
+        mut self,
+        processing_config: TransactionProcessingConfig<'a>,
+    ) -> Self {
+        self.log_messages_bytes_limit = processing_config.log_messages_bytes_limit;
+        self.processing_config = processing_config;
+        self.apply_max_log_bytes();
+        self
+    }
+
+    /// Sets the processing config's log byte limit to
+    /// `RollUpChannelConfig::max_log_bytes`, or to the processing config's own
+    /// limit if it is `None`.
+    fn apply_max_log_bytes(&mut self) {
+        self.processing_config.log_messages_bytes_limit =
+            self.config.max_log_bytes.or(self.log_messages_bytes_limit);
+    }
+
+    /// Returns the processing config used when none is set: the SVM default
+    /// with log, return data and CPI recording enabled.
+    pub fn default_processing_config() -> TransactionProcessingConfig<'static> {
+        TransactionProcessingConfig {
+            recording_config: ExecutionRecordingConfig {
+                enable_cpi_recording: true,
+                enable_log_recording: true,
+                enable_return_data_recording: true,
+            },
+            ..TransactionProcessingConfig::default()
+        }
+    }
+
+    /// Uses a custom `FeeModel` for priority fee calculation.
+    ///
+    /// The model is called with each executed transaction's accounts and CU
+    /// instead of the built-in RPC-based estimate.
+    pub fn with_fee_model(mut self, fee_model: impl FeeModel + 'a) -> Self {
+        self.fee_model = Some(Box::new(fee_model));
+        self
+    }
+
+    /// Returns the account loader used for simulations.
+    pub fn account_loader(&self) -> &RollUpAccountLoader<'a> {
+        &self.account_loader
+    }
+
+    /// Loads a compiled program (`.so` ELF) from disk for use in simulations.
+    ///
+    /// The program is injected into the account cache as an executable account
+    /// owned by the BPF Loader v2, so transactions invoking `program_id` run this
+    /// build instead of whatever is deployed on-chain.
+    pub fn load_program_from_file(
+        &self,
+        program_id: Pubkey,
+        path: impl AsRef<Path>,
+    ) -> std::io::Result<()> {
+        let elf = std::fs::read(path)?;
+        self.load_program_bytes(program_id, &elf);
+        Ok(())
+    }
+
+    /// Loads a compiled program from an in-memory ELF for use in simulations.
+    ///
+    /// Same as `load_program_from_file`, for builds that never touch the disk
+    /// (e.g. handed over by a CI pipeline).
+    pub fn load_program_bytes(&self, program_id: Pubkey, elf: &[u8]) {
+        self.account_loader
+            .insert_account(program_id, program_account_from_elf(elf));
+        // A kept processor may have the previous build in its program cache.
+        self.reset_processor();
+    }
+
+    /// Replaces a sysvar for simulations, e.g. `SlotHashes` for programs that
+    /// check recent slot hashes.
+    ///
+    /// The sysvar is stored in the account cache under its id, so it is also
+    /// what transactions see when they pass the sysvar account explicitly. The
+    /// runtime's sysvar cache, read by `Sysvar::get` and builtins, is filled
+    /// from these accounts for `Clock`, `EpochSchedule`, `EpochRewards`, `Rent`,
+    /// `SlotHashes`, `StakeHistory`, `LastRestartSlot`, `Fees` and
+    /// `RecentBlockhashes`; other sysvars are only visible as accounts.
+    ///
+    /// If `RollUpChannelConfig::epoch` is set, it replaces the `epoch` of a
+    /// `Clock` set here.
+    pub fn set_sysvar<S: Sysvar>(&self, sysvar: &S) {
+        self.account_loader
+            .insert_account(S::id(), create_account_shared_data_for_test(sysvar));
+        // A kept processor has the previous value in its sysvar cache.
+        self.reset_processor();
+    }
+
+    /// Checks whether the local SVM can simulate a transaction faithfully.
+    ///
+    /// Flags the invoked programs it can't run (missing, not executable,
+    /// unregistered builtins, unsupported loaders, upgradeable programs without
+    /// their ProgramData) and the referenced accounts that don't exist. An empty
+    /// result means the estimate can be trusted as far as this check goes.
+    pub fn diagnose(&self, transaction: &Transaction) -> Vec<Diagnostic> {
+        let message = &transaction.message;
+        // Accounts that fail to prefetch are fetched again, one at a time, by
+        // the checks below.
+        let _ = self.account_loader.prefetch(&message.account_keys);
+        let builtins = builtin_program_ids();
+        let supported_loaders = BPF_LOADERS.map(|(loader_id, _)| loader_id);
+
+        let mut program_ids: Vec<Pubkey> = Vec::new();
+        for ix in &message.instructions {
+            let program_id = message
+                .account_keys
+                .get(ix.program_id_index as usize)
+                .copied()
+                .unwrap_or_default();
+            if !program_ids.contains(&program_id) {
+                program_ids.push(program_id);
+            }
+        }
+
+        let mut diagnostics = Vec::new();
+        for &program_id in &program_ids {
+            if builtins.contains(&program_id) {
+                continue;
+            }
+            let Some(account) = self.account_loader.get_account_shared_data(&program_id) else {
+                diagnostics.push(Diagnostic::MissingProgram { program_id });
+                continue;
+            };
+            let loader = *account.owner();
+            if !account.executable() {
+                diagnostics.push(Diagnostic::NotExecutable { program_id });
+            } else if native_loader::check_id(&loader) {
+                diagnostics.push(Diagnostic::UnsupportedBuiltin { program_id });
+            } else if !supported_loaders.contains(&loader) {
+                diagnostics.push(Diagnostic::UnsupportedLoader { program_id, loader });
+            } else if let Some(programdata_address) = programdata_address(&account) {
+                if self
+                    .account_loader
+                    .get_account_shared_data(&programdata_address)
+                    .is_none()
+                {
+                    diagnostics.push(Diagnostic::MissingProgramData {
+                        program_id,
+                        programdata_address,
+                    });
+                }
+            }
+        }
+
+        diagnostics.extend(
+            message
+                .account_keys
+                .iter()
+                .filter(|pubkey| !program_ids.contains(pubkey))
+                .filter(|pubkey| self.account_loader.get_account_shared_data(pubkey).is_none())
+                .map(|&pubkey| Diagnostic::MissingAccount { pubkey }),
+        );
+        diagnostics
+    }
+
+    /// Returns the RPC requests made so far by the channel's account loader and
+    /// priority fee estimates. Empty unless the loader was built with
+    /// `RollUpAccountLoader::with_rpc_call_log`.
+    pub fn rpc_call_log(&self) -> Vec<RpcCall> {
+        self.account_loader.rpc_call_log()
+    }
+
     /// Performs base simulation of transactions and returns raw results.
     ///
     /// This is the core simulation logic without extra analysis or tagging.
+    /// With `RollUpChannelConfig::reuse_processor`, the transaction batch
+    /// processor and its program cache are kept for the next call. A
+    /// transaction referencing an account that fails to load (e.g. one the
+    /// loader's size guard rejects) fails with the load error.
     pub fn simulate_transactions_raw(
         &self,
         transactions: &[Transaction],
         analysis_config: &AnalysisConfig,
     ) -> Vec<RawSimulationResult> {
+        // Fetch the accounts of the whole batch in a few requests, rather than
+        // one request per account during execution. Accounts that fail to
+        // prefetch are fetched again during execution, where an error fails the
+        // transactions referencing them.
+        let account_keys: Vec<Pubkey> = transactions
+            .iter()
+            .flat_map(|tx| tx.message.account_keys.iter().copied())
+            .collect();
+        let _ = self.account_loader.prefetch(&account_keys);
+
+        let processor = if self.config.reuse_processor {
+            self.warm_processor()
+        } else {
+            Arc::new(self.new_processor(&self.account_loader, &self.config.compute_budget))
+        };
+        self.simulate_with_callbacks(
+            &self.account_loader,
+            transactions,
+            analysis_config,
+            &processor,
+        )
+    }
+
+    /// Returns the kept processor, building it on first use.
+    fn warm_processor(&self) -> Arc<BatchProcessor> {
+        let mut warm_processor = self.warm_processor.lock().unwrap();
+        Arc::clone(warm_processor.get_or_insert_with(|| {
+            Arc::new(self.new_processor(&self.account_loader, &self.config.compute_budget))
+        }))
+    }
+
+    /// Lazily simulates a stream of transactions, in chunks.
+    ///
+    /// Pulls `RollUpChannelConfig::iter_chunk_size` transactions at a time from
+    /// `transactions`, simulates them as one batch and yields their results in
+    /// order, so large batches never need to be held in memory at once. The
+    /// account cache is shared across chunks.
+    pub fn simulate_transactions_iter<'s, I>(
+        &'s self,
+        transactions: I,
+        analysis_config: AnalysisConfig,
+    ) -> impl Iterator<Item = RawSimulationResult> + 's
+    where
+        I: IntoIterator<Item = Transaction>,
+        I::IntoIter: 's,
+    {
+        let chunk_size = self
+            .config
+            .iter_chunk_size
+            .unwrap_or(DEFAULT_ITER_CHUNK_SIZE)
+            .max(1);
+        let mut transactions = transactions.into_iter();
+        std::iter::from_fn(move || {
+            let chunk: Vec<Transaction> = transactions.by_ref().take(chunk_size).collect();
+            if chunk.is_empty() {
+                None
+            } else {
+                Some(self.simulate_transactions_raw(&chunk, &analysis_config))
+            }
+        })
+        .flatten()
+    }
+
+    /// Performs base simulation with per-call account overrides.
+    ///
+    /// Accounts in `overrides` take precedence over cached and RPC data for this
+    /// call only; the loader cache is left untouched. Useful for "what-if"
+    /// simulations, e.g. with a different account balance.
+    ///
+    /// An override may also change an account's owner (see
+    /// `account_with_owner`). The SVM then treats the spoofed owner as the real
+    /// one: programs reading the account see it, and only that program may
+    /// change the account's data or debit its lamports. This is how to check
+    /// that a program rejects accounts owned by the wrong program.
+    pub fn simulate_transactions_raw_with_overrides(
+        &self,
+        transactions: &[Transaction],
+        analysis_config: &AnalysisConfig,
+        overrides: &HashMap<Pubkey, AccountSharedData>,
+    ) -> Vec<RawSimulationResult> {
+        let account_loader = OverriddenAccountLoader::new(&self.account_loader, overrides);
+        let processor = self.new_processor(&account_loader, &self.config.compute_budget);
+        self.simulate_with_callbacks(&account_loader, transactions, analysis_config, &processor)
+    }
+
+    /// Returns the current state of `pubkey` with its owner set to `owner`.
+    ///
+    /// The account is read through the loader (fetching it if needed). Pass the
+    /// result to `simulate_transactions_raw_with_overrides` to simulate as if
+    /// `owner` owned the account. Returns `None` if the account doesn't exist.
+    pub fn account_with_owner(
+        &self,
+        pubkey: &Pubkey,
+        owner: &Pubkey,
+    ) -> Option<AccountSharedData> {
+        let mut account = self.account_loader.get_account_shared_data(pubkey)?;
+        account.set_owner(*owner);
+        Some(account)
+    }
+
+    /// Simulates a transaction and returns its program logs, line for line as
+    /// RPC `simulateTransaction` would, e.g. to feed an Anchor event decoder.
+    ///
+    /// The logs of failed transactions are returned too. Empty if the
+    /// transaction couldn't be executed (e.g. its fee payer can't be loaded).
+    pub fn simulate_logs(&self, transaction: &Transaction) -> Vec<String> {
+        self.simulate_transactions_raw(
+            std::slice::from_ref(transaction),
+            &AnalysisConfig::default(),
+        )
+        .pop()
+        .map(|result| result.logs)
+        .unwrap_or_default()
+    }
+
+    /// Simulates a transaction and sums up its outcome the way a block explorer
+    /// shows a landed transaction: status, fee, CUs, account changes and logs.
+    ///
+    /// The fee is the one the SVM charged: the base fee plus the prioritization
+    /// fee set by the transaction's own Compute Budget instructions. A
+    /// transaction that fails to load reports the fee it would still be
+    /// charged.
+    pub fn summarize(&self, transaction: &Transaction) -> TransactionSummary {
+        let result = self
+            .simulate_transactions_raw(
+                std::slice::from_ref(transaction),
+                &AnalysisConfig::default(),
+            )
+            .pop()
+            .unwrap_or_else(RawSimulationResult::base_no_results);
+
+        let fee_lamports = match &result.fees_only {
+            Some(fees_only) => fees_only.fee_lamports,
+            None => result.fee_lamports.unwrap_or(0),
+        };
+        let message = &transaction.message;
+        let account_changes = if result.success {
+            result
+                .post_accounts
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| message.is_maybe_writable(*index, None))
+                .filter_map(|(_, (pubkey, after))| {
+                    let before = self
+                        .account_loader
+                        .get_account_shared_data(pubkey)
+                        .unwrap_or_default();
+                    let change = AccountChangeSummary {
+                        pubkey: *pubkey,
+                        lamports_before: before.lamports(),
+                        lamports_after: after.lamports(),
+                        data_len_before: before.data().len(),
+                        data_len_after: after.data().len(),
+                        data_changed: before.data() != after.data(),
+                    };
+                    (change.lamports_before != change.lamports_after || change.data_changed)
+                        .then_some(change)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        TransactionSummary {
+            status: if result.success {
+                Ok(())
+            } else {
+                Err(result
+                    .error
+                    .as_ref()
+                    .map_or_else(|| result.result.clone(), ToString::to_string))
+            },
+            fee_lamports,
+            compute_units: result.cu,
+            account_changes,
+            logs: result.logs,
+        }
+    }
+
+    /// Splits a batch into the transactions worth sending and those that would
+    /// fail.
+    ///
+    /// Simulates the batch with `simulate_transactions_raw`, so each transaction
+    /// sees the account changes of the ones before it. Returns the indices of
+    /// the successful transactions, then the indices and error messages of the
+    /// failed ones, both in input order.
+    pub fn filter_viable(&self, transactions: &[Transaction]) -> (Vec<usize>, Vec<(usize, String)>) {
+        let mut viable = Vec::new();
+        let mut failed = Vec::new();
+        let results = self.simulate_transactions_raw(transactions, &AnalysisConfig::default());
+        for (i, result) in results.into_iter().enumerate() {
+            if result.success {
+                viable.push(i);
+            } else {
+                failed.push((i, result.result));
+            }
+        }
+        (viable, failed)
+    }
+
+    /// Re-simulates the transactions that failed in an earlier run, keeping the
+    /// earlier results of those that succeeded.
+    ///
+    /// `prior` holds the results of simulating `transactions`, in order.
+    /// Transactions without a prior result are simulated too. The failed ones
+    /// run as a single batch, so they see each other's account changes but not
+    /// those of the successful ones.
+    pub fn resimulate_failures(
+        &self,
+        transactions: &[Transaction],
+        prior: &[RawSimulationResult],
+        analysis_config: &AnalysisConfig,
+    ) -> Vec<RawSimulationResult> {
+        let failed: Vec<usize> = (0..transactions.len())
+            .filter(|&i| !prior.get(i).is_some_and(|result| result.success))
+            .collect();
+        let retried: Vec<Transaction> = failed.iter().map(|&i| transactions[i].clone()).collect();
+        let mut fresh = self
+            .simulate_transactions_raw(&retried, analysis_config)
+            .into_iter();
+
+        (0..transactions.len())
+            .map(|i| {
+                if failed.binary_search(&i).is_ok() {
+                    fresh
+                        .next()
+                        .unwrap_or_else(RawSimulationResult::base_no_results)
+                } else {
+                    prior[i].clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Simulates a transaction and returns the accounts it would create.
+    ///
+    /// An account counts as created if it was missing or had no lamports before
+    /// execution and holds lamports after it, e.g. to warn users about the rent
+    /// they'll pay. Returns an empty list if the simulation fails.
+    pub fn detects_account_creation(&self, transaction: &Transaction) -> Vec<Pubkey> {
+        self.simulate_transactions_raw(
+            std::slice::from_ref(transaction),
+            &AnalysisConfig::default(),
+        )
+        .pop()
+        .map(|result| result.created_accounts)
+        .unwrap_or_default()
+    }
+
+    /// Simulates a transaction and returns the accounts it would close.
+    ///
+    /// An account counts as closed if it held lamports before execution and
+    /// none after it, e.g. to warn users that an account will be closed and its
+    /// rent reclaimed. Returns an empty list if the simulation fails.
+    pub fn detects_account_closure(&self, transaction: &Transaction) -> Vec<Pubkey> {
+        self.simulate_transactions_raw(
+            std::slice::from_ref(transaction),
+            &AnalysisConfig::default(),
+        )
+        .pop()
+        .map(|result| result.closed_accounts)
+        .unwrap_or_default()
+    }
+
+    /// Estimates base and priority fees for a batch of transactions.
+    ///
+    /// CUs are estimated locally for each transaction, the priority fee comes
+    /// from `strategy` and the base fee from `estimate_base_fee` with the
+    /// channel's fee structure. Fails if a transaction's simulation or fee
+    /// estimate fails.
+    pub fn estimate_batch_fees(
+        &self,
+        transactions: &[Transaction],
+        strategy: &dyn FeeModel,
+    ) -> anyhow::Result<BatchFeeEstimate> {
+        let results = self.simulate_transactions_raw(transactions, &AnalysisConfig::default());
+        let fee_structure = self.fee_structure();
+
+        let mut estimate = BatchFeeEstimate::default();
+        for (i, (transaction, result)) in transactions.iter().zip(&results).enumerate() {
+            if !result.success {
+                anyhow::bail!("Transaction {} simulation failed: {}", i, result.result);
+            }
+            let priority_fee = strategy
+                .estimate_priority_fee(&transaction.message.account_keys, result.cu)?
+                .total_fee_lamports;
+            let base_fee = estimate_base_fee(&transaction.message, &fee_structure, None);
+            let total_lamports = base_fee.saturating_add(priority_fee);
+            estimate.total_lamports = estimate.total_lamports.saturating_add(total_lamports);
+            estimate.per_tx.push(TotalFee {
+                cu: result.cu,
+                base_fee_lamports: base_fee,
+                priority_fee_lamports: priority_fee,
+                total_lamports,
+            });
+        }
+        Ok(estimate)
+    }
+
+    /// Simulates the same transaction once per slot.
+    ///
+    /// Each run executes at the given slot, with the `Clock` sysvar's `slot` set
+    /// to match (the other clock fields come from the account source, or the
+    /// default clock if it has none). Useful to compare slot-dependent behavior
+    /// such as vesting or staking. Slot 0 runs at slot 1, so programs deployed
+    /// at genesis are visible. Returns one result per slot, in order.
+    pub fn simulate_at_slots(
+        &self,
+        transaction: &Transaction,
+        slots: &[Slot],
+    ) -> Vec<RawSimulationResult> {
+        let base_clock = clock_sysvar(&self.account_loader).unwrap_or_default();
+
+        slots
+            .iter()
+            .map(|&slot| {
+                let clock = Clock {
+                    slot,
+                    ..base_clock.clone()
+                };
+                let overrides = HashMap::from([(
+                    sysvar::clock::id(),
+                    create_account_shared_data_for_test(&clock),
+                )]);
+                let account_loader =
+                    OverriddenAccountLoader::new(&self.account_loader, &overrides);
+                let processor = self.new_processor(&account_loader, &self.config.compute_budget);
+                self.simulate_with_callbacks(
+                    &account_loader,
+                    std::slice::from_ref(transaction),
+                    &AnalysisConfig::default(),
+                    &processor,
+                )
+                .pop()
+                .unwrap_or_else(RawSimulationResult::base_no_results)
+            })
+            .collect()
+    }
+
+    /// Simulates versioned (legacy or `v0`) transactions.
+    ///
+    /// Address table lookups are resolved with the tables given to
+    /// `with_lookup_tables`, falling back to loading the table accounts. Each
+    /// `v0` transaction then runs as an equivalent legacy transaction; signatures
+    /// aren't carried over, as simulations don't verify them. Transactions whose
+    /// lookups can't be resolved fail with `AddressLookupTableNotFound` or
+    /// `InvalidAddressLookupTableIndex` without affecting the others.
+    pub fn simulate_versioned_transactions(
+        &self,
+        transactions: &[VersionedTransaction],
+        analysis_config: &AnalysisConfig,
+    ) -> Vec<RawSimulationResult> {
+        let resolved: Vec<Result<Transaction, TransactionError>> = transactions
+            .iter()
+            .map(|transaction| self.resolve_versioned(transaction))
+            .collect();
+        let legacy: Vec<Transaction> = resolved
+            .iter()
+            .filter_map(|transaction| transaction.as_ref().ok().cloned())
+            .collect();
+        let mut results = self
+            .simulate_transactions_raw(&legacy, analysis_config)
+            .into_iter();
+
+        resolved
+            .into_iter()
+            .enumerate()
+            .map(|(i, transaction)| match transaction {
+                Ok(_) => results
+                    .next()
+                    .unwrap_or_else(RawSimulationResult::base_no_results),
+                Err(err) => {
+                    let mut res = RawSimulationResult::base_failure(format!(
+                        "Transaction {} failed: {}",
+                        i, err
+                    ));
+                    res.error = Some(err);
+                    res
+                }
+            })
+            .collect()
+    }
+
+    /// Converts a versioned transaction to a legacy one, resolving its lookups.
+    fn resolve_versioned(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> Result<Transaction, TransactionError> {
+        transaction
+            .sanitize()
+            .map_err(|_| TransactionError::SanitizeFailure)?;
+        let message = match &transaction.message {
+            VersionedMessage::Legacy(message) => {
+                return Ok(Transaction {
+                    signatures: transaction.signatures.clone(),
+                    message: message.clone(),
+                })
+            }
+            VersionedMessage::V0(message) => message,
+        };
+
+        let mut loaded = LoadedAddresses::default();
+        for lookup in &message.address_table_lookups {
+            let addresses = self
+                .lookup_table_addresses(&lookup.account_key)
+                .ok_or(TransactionError::AddressLookupTableNotFound)?;
+            let resolve = |indexes: &[u8]| {
+                indexes
+                    .iter()
+                    .map(|&index| addresses.get(index as usize).copied())
+                    .collect::<Option<Vec<Pubkey>>>()
+                    .ok_or(TransactionError::InvalidAddressLookupTableIndex)
+            };
+            loaded.writable.extend(resolve(&lookup.writable_indexes)?);
+            loaded.readonly.extend(resolve(&lookup.readonly_indexes)?);
+        }
+        Ok(Transaction::new_unsigned(v0_to_legacy_message(message, &loaded)))
+    }
+
+    /// Returns the addresses stored in a lookup table, preferring the
+    /// pre-fetched tables over the account loader.
+    fn lookup_table_addresses(&self, table: &Pubkey) -> Option<Vec<Pubkey>> {
+        if let Some(table) = self.lookup_tables.get(table) {
+            return Some(table.addresses.clone());
+        }
+        let account = self.account_loader.load_account(table).ok()??;
+        AddressLookupTable::deserialize(account.data())
+            .ok()
+            .map(|table| table.addresses.to_vec())
+    }
+
+    /// Decodes wire-format transactions and simulates them.
+    ///
+    /// Each entry is a bincode-serialized `VersionedTransaction`, encoded as
+    /// base64 or base58 (base64 is tried first). Legacy transactions and
+    /// versioned transactions with a legacy message are supported; `v0`
+    /// messages are rejected since simulations don't resolve address lookup tables.
+    /// Returns `SolanaClientExtError::TransactionDecodeError` for malformed input.
+    pub fn simulate_encoded(
+        &self,
+        encoded_transactions: &[String],
+        analysis_config: &AnalysisConfig,
+    ) -> Result<Vec<RawSimulationResult>, SolanaClientExtError> {
+        let transactions = encoded_transactions
+            .iter()
+            .enumerate()
+            .map(|(i, encoded)| {
+                decode_transaction(encoded).map_err(|err| {
+                    SolanaClientExtError::TransactionDecodeError(format!(
+                        "transaction {}: {}",
+                        i, err
+                    ))
+                })
+            })
+            .collect::<Result<Vec<Transaction>, _>>()?;
+        Ok(self.simulate_transactions_raw(&transactions, analysis_config))
+    }
+
+    /// Simulates the same transaction once per compute budget preset.
+    ///
+    /// Each run builds the runtime environment from the preset and sets the
+    /// transaction's compute unit limit to its `compute_unit_limit`, so results
+    /// can be compared to find the minimum viable budget. The account cache is
+    /// shared across runs. Returns one result per budget, in order.
+    pub fn simulate_across_budgets(
+        &self,
+        transaction: &Transaction,
+        budgets: &[ComputeBudget],
+    ) -> Vec<RawSimulationResult> {
+        budgets
+            .iter()
+            .map(|budget| self.simulate_with_budget(transaction, budget))
+            .collect()
+    }
+
+    /// Simulates a transaction, raising the compute budget while it runs out of CUs.
+    ///
+    /// Starts from the configured compute budget and doubles its
+    /// `compute_unit_limit` each time the result has `exceeded_compute_budget`
+    /// set, up to `RollUpChannelConfig::auto_budget_ceiling`. Returns the last
+    /// result together with the budget it was simulated with.
+    pub fn simulate_with_auto_budget(
+        &self,
+        transaction: &Transaction,
+    ) -> (RawSimulationResult, ComputeBudget) {
+        let ceiling = self
+            .config
+            .auto_budget_ceiling
+            .unwrap_or(MAX_COMPUTE_UNIT_LIMIT as u64);
+        let mut budget = self.config.compute_budget;
+        loop {
+            let result = self.simulate_with_budget(transaction, &budget);
+            if !result.exceeded_compute_budget || budget.compute_unit_limit >= ceiling {
+                return (result, budget);
+            }
+            budget.compute_unit_limit = budget
+                .compute_unit_limit
+                .max(1)
+                .saturating_mul(2)
+                .min(ceiling);
+        }
+    }
+
+    /// Simulates a single transaction with its compute unit limit set to the
+    /// budget's `compute_unit_limit`.
+    fn simulate_with_budget(
+        &self,
+        transaction: &Transaction,
+        budget: &ComputeBudget,
+    ) -> RawSimulationResult {
+        let mut tx = transaction.clone();
+        let limit = budget.compute_unit_limit.min(u32::MAX as u64) as u32;
+        set_compute_unit_limit(&mut tx.message, limit);
+        let processor = self.new_processor(&self.account_loader, budget);
+        self.simulate_with_callbacks(
+            &self.account_loader,
+            &[tx],
+            &AnalysisConfig::default(),
+            &processor,
+        )
+        .pop()
+        .unwrap_or_else(RawSimulationResult::base_no_results)
+    }
+
+    /// Runs the SVM simulation against the given account source, using `processor`.
+    fn simulate_with_callbacks<CB: TransactionProcessingCallback>(
+        &self,
+        account_loader: &CB,
+        transactions: &[Transaction],
+        analysis_config: &AnalysisConfig,
+        processor: &BatchProcessor,
+    ) -> Vec<RawSimulationResult> {
+        // Only the loads of this simulation may fail its transactions.
+        self.account_loader.take_load_errors();
         let sanitized = transactions
             .iter()
             .map(|tx| SolanaSanitizedTransaction::from_transaction_for_tests(tx.clone()))
             .collect::<Vec<SolanaSanitizedTransaction>>();
 
-        // Default configuration for SVM transaction simulation.
-        // Can be overridden if custom behavior is needed.
-        let compute_budget = ComputeBudget::default();
-        let feature_set = Arc::new(FeatureSet::all_enabled());
-        let fee_structure = FeeStructure::default();
-        let _rent_collector = RentCollector::default();
-
-        // Custom account loader for fetching account data via RPC.
-        let account_loader = RollUpAccountLoader::new(&self.rpc_client);
+        // Transactions passing the `Clock` sysvar see the configured epoch too,
+        // and load the same fallbacks as the processor's sysvar cache.
+        let fallbacks = self.fallback_accounts(account_loader);
+        let account_loader = &FallbackAccountLoader::new(account_loader, &fallbacks);
+        let clock_override = self.clock_override(account_loader);
+        let account_loader = &OverriddenAccountLoader::new(account_loader, &clock_override);
 
-        // Creates an SVM-compatible transaction batch processor.
-        // Entry point for executing transactions against Solana runtime logic.
-        let fork_graph = Arc::new(RwLock::new(ForkRollUpGraph {}));
-        let processor = create_transaction_batch_processor(
-            &account_loader,
-            &feature_set,
-            &compute_budget,
-            Arc::clone(&fork_graph),
-        );
-        println!("transaction batch processor created ");
+        let lamports_per_signature = self.fee_structure().lamports_per_signature;
+        // Collects rent for the configured epoch, matching the processor's. The
+        // SVM's default collector would collect for epoch 0.
+        let rent_collector = match self.config.epoch {
+            Some(epoch) => Some(RentCollector {
+                epoch,
+                ..self.config.rent_collector.clone().unwrap_or_else(|| RentCollector {
+                    rent: self.rent(),
+                    ..RentCollector::default()
+                })
+            }),
+            None => self.config.rent_collector.clone(),
+        };
 
         // Creates a simulation environment, similar to a Solana runtime slot.
         let processing_environment = TransactionProcessingEnvironment {
             blockhash: Hash::default(),
-            blockhash_lamports_per_signature: fee_structure.lamports_per_signature,
-            epoch_total_stake: 0,
-            feature_set,
-            fee_lamports_per_signature: 5000,
-            rent_collector: None,
+            blockhash_lamports_per_signature: lamports_per_signature,
+            epoch_total_stake: self.config.epoch_total_stake,
+            feature_set: Arc::clone(&processor.feature_set),
+            fee_lamports_per_signature: lamports_per_signature,
+            rent_collector: rent_collector
+                .as_ref()
+                .map(|rent_collector| rent_collector as &dyn SVMRentCollector),
         };
 
-        // Uses the default transaction processing config.
-        // Can be extended for more fine-grained control.
-        let processing_config = TransactionProcessingConfig::default();
-
-        println!("transaction processing_config created ");
-
         // Executes sanitized transactions using the simulated runtime.
-        let results = processor.load_and_execute_sanitized_transactions(
-            &account_loader,
+        let results = processor.processor.load_and_execute_sanitized_transactions(
+            account_loader,
             &sanitized,
-            get_transaction_check_results(transactions.len()),
+            get_transaction_check_results(account_loader, &sanitized, lamports_per_signature),
             &processing_environment,
-            &processing_config,
+            &self.processing_config,
         );
 
+        // Each account as the transaction being processed sees it: read once from
+        // the account source, then replaced by the writes of earlier successful
+        // transactions in the batch.
+        let mut batch_accounts: HashMap<Pubkey, Option<AccountSharedData>> = HashMap::new();
         let mut return_results = Vec::new();
         for (i, transaction_result) in results.processing_results.iter().enumerate() {
             let mut fee_details: Option<PrioritizationFeeDetails> = None;
@@ -119,7 +1136,21 @@ impl<'a> RollUpChannel<'a> {
 
             if analysis_config.calculate_priority_fee && executed_cu > 0 {
                 let accounts_for_fee_estimation: Vec<Pubkey> = transactions[i].message.account_keys.iter().cloned().collect();
-                match self.rpc_client.estimate_priority_fee_for_cu_sync(Some(&accounts_for_fee_estimation), executed_cu) {
+                let estimate = match (analysis_config.fixed_fee_per_cu, &self.fee_model) {
+                    (Some(price), _) => Ok(EstimatedPrioritizationFee::from_price(price, executed_cu)),
+                    (None, Some(fee_model)) => fee_model.estimate_priority_fee(&accounts_for_fee_estimation, executed_cu),
+                    (None, None) => {
+                        self.account_loader.record_rpc_call(
+                            "getRecentPrioritizationFees",
+                            &accounts_for_fee_estimation,
+                        );
+                        self.rpc_client.estimate_priority_fee_for_cu_sync(
+                            Some(&accounts_for_fee_estimation),
+                            executed_cu,
+                        )
+                    }
+                };
+                match estimate {
                     Ok(estimated_fee) => {
                         fee_details = Some(PrioritizationFeeDetails {
                             fee_per_cu_micro_lamports: estimated_fee.fee_per_cu_micro_lamports,
@@ -136,30 +1167,171 @@ impl<'a> RollUpChannel<'a> {
                 }
             }
 
-            let tx_result: RawSimulationResult = match transaction_result {
+            let mut tx_result: RawSimulationResult = match transaction_result {
                 Ok(processed_tx) => match processed_tx {
                     ProcessedTransaction::Executed(executed_tx) => {
                         let cu = executed_tx.execution_details.executed_units;
-                        let logs = executed_tx.execution_details.log_messages.clone();
+                        let logs = executed_tx
+                            .execution_details
+                            .log_messages
+                            .clone()
+                            .unwrap_or_default();
                         let status = executed_tx.execution_details.status.clone();
-                        if status.is_ok() {
-                            let mut res = RawSimulationResult::base_success(cu);
-                            res.prioritization_fee_details = fee_details;
-                            res
-                        } else {
-                            let error_msg = format!(
-                                "Transaction {} failed with error: {}",
-                                i,
-                                status.unwrap_err()
+                        let mut res = match &status {
+                            Ok(()) => RawSimulationResult::base_success(cu),
+                            Err(err) => {
+                                let error_msg =
+                                    format!("Transaction {} failed with error: {}", i, err);
+                                RawSimulationResult::base_failure(format!(
+                                    "{}\nLogs:\n{}",
+                                    error_msg,
+                                    logs.join("\n")
+                                ))
+                            }
+                        };
+                        // Soft failures: the program returned Ok but logged a failure pattern.
+                        let log_match =
+                            analysis_config.fail_on_log_patterns.iter().find_map(|pattern| {
+                                logs.iter()
+                                    .find(|line| line.contains(pattern.as_str()))
+                                    .map(|line| (pattern, line))
+                            });
+                        if let (true, Some((pattern, line))) = (res.success, log_match) {
+                            res.success = false;
+                            res.result = format!(
+                                "Transaction {} logged {:?}, matching failure pattern {:?}",
+                                i, line, pattern
                             );
-                            let log_msg = logs.map(|l| l.join("\n")).unwrap_or_default();
-                            let mut res = RawSimulationResult::base_failure(format!(
-                                "{}\nLogs:\n{}",
-                                error_msg, log_msg
-                            ));
-                            res.prioritization_fee_details = fee_details; // Also add here for context if needed
-                            res
                         }
+                        res.prioritization_fee_details = fee_details;
+                        res.implied_priority_fee_lamports =
+                            parse_compute_budget(&transactions[i]).unit_price.map(|price| {
+                                (price as u128 * cu as u128).div_ceil(1_000_000) as u64
+                            });
+                        res.exceeded_compute_budget = matches!(
+                            status,
+                            Err(TransactionError::InstructionError(
+                                _,
+                                InstructionError::ComputationalBudgetExceeded
+                            ))
+                        );
+                        res.error = status.err();
+                        res.max_cpi_depth = max_invoke_depth(&logs);
+                        res.cpi_count = cpi_count(&logs);
+                        res.invoked_programs = invoked_programs(&logs);
+                        // The SVM's log collector ends truncated logs with this line.
+                        res.logs_truncated = logs.iter().any(|line| line == "Log truncated");
+                        res.logs = logs;
+                        res.return_data = executed_tx.execution_details.return_data.clone();
+                        res.post_accounts = executed_tx.loaded_transaction.accounts.clone();
+                        let pre_accounts: HashMap<Pubkey, Option<AccountSharedData>> = res
+                            .post_accounts
+                            .iter()
+                            .map(|(pubkey, _)| {
+                                let before = batch_accounts.entry(*pubkey).or_insert_with(|| {
+                                    account_loader.get_account_shared_data(pubkey)
+                                });
+                                (*pubkey, before.clone())
+                            })
+                            .collect();
+                        let account_before =
+                            |pubkey: &Pubkey| pre_accounts.get(pubkey).and_then(Option::as_ref);
+                        let message = &transactions[i].message;
+                        // The recorded CPIs carry no CU data. Builtins charge a
+                        // fixed amount; BPF programs log what they consumed, in
+                        // the order the CPIs were recorded.
+                        let logged_units = inner_invocation_units(&res.logs);
+                        res.inner_instructions = executed_tx
+                            .execution_details
+                            .inner_instructions
+                            .iter()
+                            .flatten()
+                            .enumerate()
+                            .flat_map(|(instruction_index, inner_instructions)| {
+                                let logged = logged_units.get(instruction_index);
+                                inner_instructions.iter().enumerate().map(move |(j, inner)| {
+                                    let program_id = message
+                                        .account_keys
+                                        .get(inner.instruction.program_id_index as usize)
+                                        .copied()
+                                        .unwrap_or_default();
+                                    let logged_cu =
+                                        logged.and_then(|units| units.get(j).copied().flatten());
+                                    InnerInstructionCu {
+                                        instruction_index,
+                                        stack_height: inner.stack_height,
+                                        program_id,
+                                        consumed_cu: builtin_compute_units(&program_id)
+                                            .or(logged_cu),
+                                    }
+                                })
+                            })
+                            .collect();
+                        if res.success {
+                            res.created_accounts = res
+                                .post_accounts
+                                .iter()
+                                .enumerate()
+                                .filter(|(index, (pubkey, account))| {
+                                    message.is_maybe_writable(*index, None)
+                                        && account.lamports() > 0
+                                        && !account_before(pubkey)
+                                            .is_some_and(|before| before.lamports() > 0)
+                                })
+                                .map(|(_, (pubkey, _))| *pubkey)
+                                .collect();
+                            res.closed_accounts = res
+                                .post_accounts
+                                .iter()
+                                .enumerate()
+                                .filter(|(index, (pubkey, account))| {
+                                    message.is_maybe_writable(*index, None)
+                                        && account.lamports() == 0
+                                        && account_before(pubkey)
+                                            .is_some_and(|before| before.lamports() > 0)
+                                })
+                                .map(|(_, (pubkey, _))| *pubkey)
+                                .collect();
+                            res.advanced_nonce = sanitized[i]
+                                .message()
+                                .get_durable_nonce()
+                                .and_then(|nonce_address| {
+                                    let before =
+                                        account_before(nonce_address).and_then(nonce_data)?;
+                                    let after = res
+                                        .post_accounts
+                                        .iter()
+                                        .find(|(pubkey, _)| pubkey == nonce_address)
+                                        .and_then(|(_, account)| nonce_data(account))?;
+                                    (after.durable_nonce != before.durable_nonce).then_some(after)
+                                });
+                        }
+                        res.account_data_sizes = res
+                            .post_accounts
+                            .iter()
+                            .enumerate()
+                            .filter(|(index, _)| message.is_maybe_writable(*index, None))
+                            .map(|(_, (pubkey, account))| AccountDataSizeChange {
+                                pubkey: *pubkey,
+                                data_len_before: account_before(pubkey)
+                                    .map_or(0, |account| account.data().len()),
+                                data_len_after: account.data().len(),
+                            })
+                            .collect();
+                        res.loaded_accounts_data_size =
+                            executed_tx.loaded_transaction.loaded_accounts_data_size as u64;
+                        res.fee_lamports =
+                            Some(executed_tx.loaded_transaction.fee_details.total_fee());
+                        if res.error.is_none() {
+                            batch_accounts.extend(
+                                res.post_accounts
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|(index, _)| message.is_maybe_writable(*index, None))
+                                    .map(|(_, (pubkey, account))| (*pubkey, Some(account.clone()))),
+                            );
+                        }
+                        res
                     }
                     ProcessedTransaction::FeesOnly(fees_only) => {
                         let mut res = RawSimulationResult::base_failure(format!(
@@ -167,17 +1339,45 @@ impl<'a> RollUpChannel<'a> {
                             i, fees_only.load_error
                         ));
                         res.prioritization_fee_details = fee_details;
+                        res.error = Some(fees_only.load_error.clone());
+                        res.fees_only = Some(FeesOnlyDetails {
+                            load_error: Some(fees_only.load_error.clone()),
+                            fee_lamports: fees_only.fee_details.total_fee(),
+                        });
                         res
                     }
                 },
                 Err(err) => {
                     let mut res = RawSimulationResult::base_failure(format!("Transaction {} failed: {}", i, err));
                     res.prioritization_fee_details = fee_details;
+                    res.error = Some(err.clone());
                     res
                 }
             };
+            tx_result.custom_error_code = match &tx_result.error {
+                Some(TransactionError::InstructionError(_, InstructionError::Custom(code))) => {
+                    Some(*code)
+                }
+                _ => None,
+            };
             return_results.push(tx_result);
         }
+        // The SVM ran accounts that failed to load as missing; report the load
+        // error for the transactions referencing them instead of its outcome.
+        let load_errors = self.account_loader.take_load_errors();
+        for (i, (tx_result, transaction)) in return_results.iter_mut().zip(transactions).enumerate()
+        {
+            if let Some((pubkey, err)) = load_errors
+                .iter()
+                .find(|(pubkey, _)| transaction.message.account_keys.contains(pubkey))
+            {
+                tx_result.success = false;
+                tx_result.result = format!(
+                    "Transaction {} failed: account {} couldn't be loaded: {}",
+                    i, pubkey, err
+                );
+            }
+        }
         if return_results.is_empty() && !transactions.is_empty() {
             return_results.push(RawSimulationResult::base_no_results());
         }
@@ -188,83 +1388,149 @@ impl<'a> RollUpChannel<'a> {
     ///
     /// Stores results if a tag is provided in the `AnalysisConfig`.
     pub fn process_transactions_with_analysis(
-        &mut self,
+        &self,
         transactions: &[Transaction],
         config: &AnalysisConfig,
     ) -> Vec<SimulationAnalysisResult> {
         let raw_simulation_results = self.simulate_transactions_raw(transactions, config);
 
+        let analysis_results: Vec<SimulationAnalysisResult> = raw_simulation_results
+            .iter()
+            .flat_map(|raw_res| Self::analyze(raw_res, config))
+            .collect();
+
+        if let Some(tag_str) = &config.tag {
+            self.store_tagged(tag_str, &analysis_results);
+        }
+
+        analysis_results
+    }
+
+    /// Like `process_transactions_with_analysis`, but stores each transaction's
+    /// results under its own tag.
+    ///
+    /// The transactions are simulated as one batch. A transaction tagged `None`
+    /// falls back to `config.tag`, and isn't stored if that is `None` too.
+    /// Results are returned in transaction order.
+    pub fn process_with_per_tx_tags(
+        &self,
+        transactions: &[(Transaction, Option<String>)],
+        config: &AnalysisConfig,
+    ) -> Vec<SimulationAnalysisResult> {
+        let batch: Vec<Transaction> = transactions.iter().map(|(tx, _)| tx.clone()).collect();
+        let raw_simulation_results = self.simulate_transactions_raw(&batch, config);
+
+        let mut analysis_results = Vec::new();
+        for (raw_res, (_, tag)) in raw_simulation_results.iter().zip(transactions) {
+            let results = Self::analyze(raw_res, config);
+            if let Some(tag_str) = tag.as_ref().or(config.tag.as_ref()) {
+                self.store_tagged(tag_str, &results);
+            }
+            analysis_results.extend(results);
+        }
+        analysis_results
+    }
+
+    /// Runs the analyses enabled in `config` on one simulation result.
+    fn analyze(
+        raw_res: &RawSimulationResult,
+        config: &AnalysisConfig,
+    ) -> Vec<SimulationAnalysisResult> {
         let mut analysis_results: Vec<SimulationAnalysisResult> = Vec::new();
+        if config.estimate_compute_units {
+            let logs_for_cu_details = if raw_res.logs.is_empty() {
+                None
+            } else {
+                Some(raw_res.logs.clone())
+            };
 
-        for raw_res in raw_simulation_results.iter() {
-            if config.estimate_compute_units {
-                let logs_for_cu_details = None;
-
-                let cu_details = ComputeUnitsDetails {
-                    cu_consumed: raw_res.cu,
-                    logs: logs_for_cu_details,
-                    error_message: if raw_res.success {
-                        None
-                    } else {
-                        Some(raw_res.result.clone())
-                    },
-                };
+            let cu_details = ComputeUnitsDetails {
+                cu_consumed: raw_res.cu,
+                logs: logs_for_cu_details,
+                error_message: if raw_res.success {
+                    None
+                } else {
+                    Some(raw_res.result.clone())
+                },
+            };
+            analysis_results.push(SimulationAnalysisResult {
+                base_simulation_success: raw_res.success,
+                analysis_type: "compute_units".to_string(),
+                details: AnalysisResultDetail::ComputeUnits(cu_details),
+                top_level_error_message: if raw_res.success {
+                    None
+                } else {
+                    Some(raw_res.result.clone())
+                },
+            });
+        }
+        // New block for priority fee analysis
+        if config.calculate_priority_fee {
+            if let Some(details) = &raw_res.prioritization_fee_details {
+                analysis_results.push(SimulationAnalysisResult {
+                    base_simulation_success: raw_res.success, // Base success is relevant here too
+                    analysis_type: "priority_fee".to_string(),
+                    details: AnalysisResultDetail::PriorityFee(details.clone()),
+                    top_level_error_message: details.error_message.clone().or_else(|| {
+                        if !raw_res.success {
+                            Some(raw_res.result.clone())
+                        } else {
+                            None
+                        }
+                    }),
+                });
+            } else {
+                // This case might occur if fee calculation was skipped due to cu=0 or other reasons
+                // Or if it failed and RawSimulationResult wasn't populated (though current logic tries to populate with error)
                 analysis_results.push(SimulationAnalysisResult {
                     base_simulation_success: raw_res.success,
-                    analysis_type: "compute_units".to_string(),
-                    details: AnalysisResultDetail::ComputeUnits(cu_details),
-                    top_level_error_message: if raw_res.success {
-                        None
-                    } else {
-                        Some(raw_res.result.clone())
-                    },
+                    analysis_type: "priority_fee".to_string(),
+                    details: AnalysisResultDetail::PriorityFee(PrioritizationFeeDetails {
+                        error_message: Some("Priority fee details not available or calculation skipped.".to_string()),
+                        ..Default::default()
+                    }),
+                    top_level_error_message: Some("Priority fee details not available or calculation skipped.".to_string()),
                 });
             }
-            // New block for priority fee analysis
-            if config.calculate_priority_fee {
-                if let Some(details) = &raw_res.prioritization_fee_details {
-                    analysis_results.push(SimulationAnalysisResult {
-                        base_simulation_success: raw_res.success, // Base success is relevant here too
-                        analysis_type: "priority_fee".to_string(),
-                        details: AnalysisResultDetail::PriorityFee(details.clone()),
-                        top_level_error_message: details.error_message.clone().or_else(|| {
-                            if !raw_res.success {
-                                Some(raw_res.result.clone())
-                            } else {
-                                None
-                            }
-                        }),
-                    });
-                } else {
-                    // This case might occur if fee calculation was skipped due to cu=0 or other reasons
-                    // Or if it failed and RawSimulationResult wasn't populated (though current logic tries to populate with error)
-                    analysis_results.push(SimulationAnalysisResult {
-                        base_simulation_success: raw_res.success,
-                        analysis_type: "priority_fee".to_string(),
-                        details: AnalysisResultDetail::PriorityFee(PrioritizationFeeDetails {
-                            error_message: Some("Priority fee details not available or calculation skipped.".to_string()),
-                            ..Default::default()
-                        }),
-                        top_level_error_message: Some("Priority fee details not available or calculation skipped.".to_string()),
-                    });
-                }
-            }
         }
+        analysis_results
+    }
 
-        if let Some(tag_str) = &config.tag {
-            if !analysis_results.is_empty() {
-                self.tagged_results
-                    .entry(tag_str.clone())
-                    .or_default()
-                    .extend(analysis_results.clone());
-            }
+    /// Appends analysis results to the tagged result store.
+    fn store_tagged(&self, tag: &str, results: &[SimulationAnalysisResult]) {
+        if !results.is_empty() {
+            self.tagged_results
+                .write()
+                .unwrap()
+                .entry(tag.to_string())
+                .or_default()
+                .extend(results.iter().cloned());
         }
+    }
 
-        analysis_results
+    /// Retrieves a copy of the stored `SimulationAnalysisResult` for a given tag.
+    pub fn get_tagged_results(&self, tag: &str) -> Option<Vec<SimulationAnalysisResult>> {
+        self.tagged_results.read().unwrap().get(tag).cloned()
+    }
+
+    /// Borrows every stored `SimulationAnalysisResult`, keyed by tag.
+    ///
+    /// The guard derefs to the map and holds the store's read lock, so tagging
+    /// on this channel blocks until it's dropped. Use `tagged_results_snapshot`
+    /// to keep the results while simulating further.
+    pub fn all_tagged_results(
+        &self,
+    ) -> RwLockReadGuard<'_, HashMap<String, Vec<SimulationAnalysisResult>>> {
+        self.tagged_results.read().unwrap()
+    }
+
+    /// Returns a copy of every stored `SimulationAnalysisResult`, keyed by tag.
+    pub fn tagged_results_snapshot(&self) -> HashMap<String, Vec<SimulationAnalysisResult>> {
+        self.tagged_results.read().unwrap().clone()
     }
 
-    /// Retrieves stored `SimulationAnalysisResult` for a given tag.
-    pub fn get_tagged_results(&self, tag: &str) -> Option<&Vec<SimulationAnalysisResult>> {
-        self.tagged_results.get(tag)
+    /// Consumes the channel and returns the whole tagged result store.
+    pub fn into_tagged_results(self) -> HashMap<String, Vec<SimulationAnalysisResult>> {
+        std::mem::take(&mut *self.tagged_results.write().unwrap())
     }
 }
@@ -0,0 +1,33 @@
+use std::ops::Deref;
+use std::sync::Arc;
+
+use solana_client::rpc_client::RpcClient;
+
+/// An `RpcClient` that is either borrowed or shared through an `Arc`.
+///
+/// Lets channels and loaders keep accepting a plain `&RpcClient`, while also
+/// supporting `'static` instances that can be moved across threads.
+pub(crate) enum RpcClientHandle<'a> {
+    Borrowed(&'a RpcClient),
+    Shared(Arc<RpcClient>),
+}
+
+impl Clone for RpcClientHandle<'_> {
+    fn clone(&self) -> Self {
+        match self {
+            RpcClientHandle::Borrowed(client) => RpcClientHandle::Borrowed(client),
+            RpcClientHandle::Shared(client) => RpcClientHandle::Shared(Arc::clone(client)),
+        }
+    }
+}
+
+impl Deref for RpcClientHandle<'_> {
+    type Target = RpcClient;
+
+    fn deref(&self) -> &RpcClient {
+        match self {
+            RpcClientHandle::Borrowed(client) => client,
+            RpcClientHandle::Shared(client) => client,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction::SystemInstruction;
+use solana_sdk::{compute_budget, system_program};
+
+use crate::utils::helpers::decode_compute_budget_instruction;
+
+/// SPL Token program id.
+const TOKEN_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// SPL Token instruction names, indexed by their tag (the first data byte).
+const TOKEN_INSTRUCTIONS: [&str; 25] = [
+    "InitializeMint",
+    "InitializeAccount",
+    "InitializeMultisig",
+    "Transfer",
+    "Approve",
+    "Revoke",
+    "SetAuthority",
+    "MintTo",
+    "Burn",
+    "CloseAccount",
+    "FreezeAccount",
+    "ThawAccount",
+    "TransferChecked",
+    "ApproveChecked",
+    "MintToChecked",
+    "BurnChecked",
+    "InitializeAccount2",
+    "SyncNative",
+    "InitializeAccount3",
+    "InitializeMultisig2",
+    "InitializeMint2",
+    "GetAccountDataSize",
+    "InitializeImmutableOwner",
+    "AmountToUiAmount",
+    "UiAmountToAmount",
+];
+
+/// Names the program and decodes the instruction type for the System, Compute
+/// Budget and SPL Token programs.
+///
+/// Returns `None` for any other program. The instruction type is left out if
+/// `data` can't be decoded.
+pub(crate) fn describe_known_instruction(program_id: &Pubkey, data: &[u8]) -> Option<String> {
+    let (program, instruction) = if *program_id == system_program::id() {
+        let instruction = bincode::deserialize::<SystemInstruction>(data)
+            .ok()
+            .map(|ix| format!("{:?}", ix));
+        ("System Program", instruction)
+    } else if *program_id == compute_budget::id() {
+        let instruction = decode_compute_budget_instruction(data).map(|ix| format!("{:?}", ix));
+        ("Compute Budget", instruction)
+    } else if *program_id == TOKEN_PROGRAM_ID {
+        let instruction = data
+            .first()
+            .and_then(|&tag| TOKEN_INSTRUCTIONS.get(tag as usize))
+            .map(|name| name.to_string());
+        ("Token", instruction)
+    } else {
+        return None;
+    };
+    Some(match instruction {
+        Some(instruction) => format!("{}: {}", program, instruction),
+        None => program.to_string(),
+    })
+}
@@ -1,32 +1,106 @@
+use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
+use base64::{prelude::BASE64_STANDARD, Engine};
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_client::rpc_response::RpcSimulateTransactionResult;
 use solana_bpf_loader_program::syscalls::create_program_runtime_environment_v1;
-use solana_compute_budget::{
-    compute_budget::ComputeBudget, compute_budget_limits::ComputeBudgetLimits,
+use solana_compute_budget::compute_budget::ComputeBudget;
+use solana_compute_budget::compute_budget_limits::{
+    MAX_COMPUTE_UNIT_LIMIT, MAX_HEAP_FRAME_BYTES, MIN_HEAP_FRAME_BYTES,
 };
 use solana_program_runtime::loaded_programs::ProgramCacheEntry;
-use solana_sdk::transaction;
+use solana_sdk::account::{
+    create_account_shared_data_for_test, Account, AccountSharedData, ReadableAccount,
+    WritableAccount,
+};
+use solana_sdk::clock::{Clock, Epoch, Slot};
+use solana_sdk::hash::Hash;
+use solana_sdk::rent::Rent;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::{AccountMeta, Instruction, InstructionError};
+use solana_sdk::message::v0::{self, LoadedAddresses};
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signers::Signers;
+use solana_sdk::stake_history::StakeHistory;
+use solana_sdk::sysvar;
+use solana_sdk::nonce::state::{Data, State};
+use solana_sdk::nonce::versions::Versions;
+use solana_sdk::nonce_account::verify_nonce_account;
+use solana_sdk::transaction::{
+    self, SanitizedTransaction, Transaction, TransactionError, VersionedTransaction,
+};
 use solana_svm::account_loader::CheckedTransactionDetails;
+use solana_svm::nonce_info::NonceInfo;
 use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
 use solana_svm::transaction_processor::TransactionBatchProcessor;
 use solana_system_program::system_processor;
 
+use crate::error::SolanaClientExtError;
 use crate::ForkRollUpGraph;
 use agave_feature_set::FeatureSet;
 
-/// Generates a vector of placeholder "checked" transactions to simulate what a
+/// Generates the "checked" transaction details the SVM expects, mimicking what a
 /// validator would normally do before execution (signature check, account ownership, etc).
 ///
 /// In a real validator, this step ensures transactions are structurally valid
-/// before passing them to the runtime. Here, we mock that behavior so that
-/// we can run fully in-memory simulations without real pre-validation.
+/// before passing them to the runtime. Here, we mock most of that behavior so
+/// that we can run fully in-memory simulations without real pre-validation.
 ///
-/// `len` defines how many mock results to return, used for simulating batches.
-pub(crate) fn get_transaction_check_results(
-    len: usize,
+/// Durable nonce transactions are the exception: their nonce account is loaded
+/// through `callbacks` and must hold the transaction's `recent_blockhash`,
+/// otherwise the transaction is rejected with `BlockhashNotFound`, as a validator would.
+/// `lamports_per_signature` must match the processing environment's, so the fee
+/// checked here is the one charged.
+pub(crate) fn get_transaction_check_results<CB: TransactionProcessingCallback>(
+    callbacks: &CB,
+    transactions: &[SanitizedTransaction],
+    lamports_per_signature: u64,
 ) -> Vec<transaction::Result<CheckedTransactionDetails>> {
-    let _compute_budget_limit = ComputeBudgetLimits::default();
-    vec![transaction::Result::Ok(CheckedTransactionDetails::new(None, 5000,)); len]
+    transactions
+        .iter()
+        .map(|tx| {
+            let Some(nonce_address) = tx.message().get_durable_nonce() else {
+                return Ok(CheckedTransactionDetails::new(None, lamports_per_signature));
+            };
+            let nonce_account = callbacks
+                .get_account_shared_data(nonce_address)
+                .ok_or(TransactionError::BlockhashNotFound)?;
+            verify_nonce_account(&nonce_account, tx.message().recent_blockhash())
+                .ok_or(TransactionError::BlockhashNotFound)?;
+            Ok(CheckedTransactionDetails::new(
+                Some(NonceInfo::new(*nonce_address, nonce_account)),
+                lamports_per_signature,
+            ))
+        })
+        .collect()
+}
+
+/// Parses the data of an initialized nonce account, or `None` if `account`
+/// isn't one.
+pub(crate) fn nonce_data(account: &AccountSharedData) -> Option<Data> {
+    match bincode::deserialize::<Versions>(account.data()).ok()?.state() {
+        State::Initialized(data) => Some(data.clone()),
+        State::Uninitialized => None,
+    }
+}
+
+/// Returns the `Clock` sysvar `callbacks` load, if there is one.
+pub(crate) fn clock_sysvar<CB: TransactionProcessingCallback>(callbacks: &CB) -> Option<Clock> {
+    callbacks
+        .get_account_shared_data(&solana_sdk::sysvar::clock::id())
+        .and_then(|account| bincode::deserialize(account.data()).ok())
+}
+
+/// Returns the `Rent` sysvar `callbacks` load, if there is one.
+pub(crate) fn rent_sysvar<CB: TransactionProcessingCallback>(callbacks: &CB) -> Option<Rent> {
+    callbacks
+        .get_account_shared_data(&solana_sdk::sysvar::rent::id())
+        .and_then(|account| bincode::deserialize(account.data()).ok())
 }
 
 /// Creates a local, in-memory transaction processor capable of simulating
@@ -40,19 +114,22 @@ pub(crate) fn get_transaction_check_results(
 ///
 /// `fork_graph` is the mocked ledger state.
 /// `feature_set` and `compute_budget` customize runtime behavior (e.g., instruction limits).
+/// `slot` is the slot transactions execute at (raised to 1 if 0), in `epoch`.
 pub(crate) fn create_transaction_batch_processor<CB: TransactionProcessingCallback>(
     callbacks: &CB,
     feature_set: &FeatureSet,
     compute_budget: &ComputeBudget,
     fork_graph: Arc<RwLock<ForkRollUpGraph>>,
+    slot: Slot,
+    epoch: Epoch,
 ) -> TransactionBatchProcessor<ForkRollUpGraph> {
-    // Create a new transaction batch processor for slot 1.
+    // Create a new transaction batch processor for the requested slot.
     //
-    // We choose slot 1 deliberately: Solana treats programs deployed in slot 0
+    // The slot is at least 1 deliberately: Solana treats programs deployed in slot 0
     // as not visible until slot 1. This ensures deployed programs are active during simulation.
     let processor = TransactionBatchProcessor::<ForkRollUpGraph>::new(
-        /* slot */ 1,
-        /* epoch */ 1,
+        /* slot */ slot.max(1),
+        /* epoch */ epoch,
         Arc::downgrade(&fork_graph),
         Some(Arc::new(
             create_program_runtime_environment_v1(feature_set, compute_budget, false, false)
@@ -61,6 +138,12 @@ pub(crate) fn create_transaction_batch_processor<CB: TransactionProcessingCallba
         None,
     );
 
+    // Populate the sysvar cache from the callbacks.
+    //
+    // Programs and builtins read sysvars (clock, rent, recent blockhashes used by
+    // durable nonce advances, ...) from this cache rather than from accounts.
+    processor.fill_missing_sysvar_cache_entries(callbacks);
+
     // Register the System Program as a built-in.
     //
     // This enables simulation of basic SOL instructions like transfers and account creation.
@@ -75,20 +158,506 @@ pub(crate) fn create_transaction_batch_processor<CB: TransactionProcessingCallba
         ),
     );
 
-    // Register the BPF Loader v2 as a built-in.
+    // Register the BPF loaders as built-ins.
     //
-    // This is needed to simulate execution of BPF-based programs,
-    // including programs like SPL Token, associated token accounts, etc.
+    // Programs run through their owner's builtin: the BPF Loader v2 runs
+    // programs like SPL Token, and most deployed programs are upgradeable, so
+    // they fail with `UnsupportedProgramId` without the upgradeable loader.
+    // That one reads program bytes from the ProgramData account, which the
+    // program cache loads through the callbacks.
+    for (loader_id, name) in BPF_LOADERS {
+        processor.add_builtin(
+            callbacks,
+            loader_id,
+            name,
+            ProgramCacheEntry::new_builtin(
+                0,
+                name.len(),
+                solana_bpf_loader_program::Entrypoint::vm,
+            ),
+        );
+    }
+
+    // Register the Address Lookup Table program as a built-in.
+    //
+    // This enables simulation of lookup table management transactions
+    // (create, extend, freeze, deactivate and close).
     processor.add_builtin(
         callbacks,
-        solana_sdk::bpf_loader::id(),
-        "solana_bpf_loader_program",
+        solana_sdk::address_lookup_table::program::id(),
+        "address_lookup_table_program",
         ProgramCacheEntry::new_builtin(
             0,
-            b"solana_bpf_loader_program".len(),
-            solana_bpf_loader_program::Entrypoint::vm,
+            b"address_lookup_table_program".len(),
+            solana_address_lookup_table_program::processor::Entrypoint::vm,
+        ),
+    );
+
+    // Register the Compute Budget program as a built-in.
+    //
+    // Its instructions are read before execution, but still invoke the program,
+    // so transactions carrying a `SetComputeUnitLimit` need it to be present.
+    processor.add_builtin(
+        callbacks,
+        solana_sdk::compute_budget::id(),
+        "compute_budget_program",
+        ProgramCacheEntry::new_builtin(
+            0,
+            b"compute_budget_program".len(),
+            solana_compute_budget_program::Entrypoint::vm,
+        ),
+    );
+
+    // Register the Stake program as a built-in.
+    //
+    // This enables simulation of staking flows (initialize, delegate, deactivate,
+    // withdraw, ...). The sysvars they read are in the sysvar cache above and the
+    // stake config account is loaded like any other instruction account, so all
+    // must be available from the callbacks; see `stake_accounts`.
+    processor.add_builtin(
+        callbacks,
+        solana_sdk::stake::program::id(),
+        "stake_program",
+        ProgramCacheEntry::new_builtin(
+            0,
+            b"stake_program".len(),
+            solana_stake_program::stake_instruction::Entrypoint::vm,
         ),
     );
 
     processor
 }
+
+/// BPF loaders `create_transaction_batch_processor` registers, with their
+/// builtin names. Only programs owned by one of them can run locally.
+pub(crate) const BPF_LOADERS: [(Pubkey, &str); 3] = [
+    (solana_sdk::bpf_loader::ID, "solana_bpf_loader_program"),
+    (
+        solana_sdk::bpf_loader_deprecated::ID,
+        "solana_bpf_loader_deprecated_program",
+    ),
+    (
+        solana_sdk::bpf_loader_upgradeable::ID,
+        "solana_bpf_loader_upgradeable_program",
+    ),
+];
+
+/// Programs `create_transaction_batch_processor` registers as builtins.
+pub(crate) fn builtin_program_ids() -> Vec<Pubkey> {
+    let mut ids = vec![
+        solana_system_program::id(),
+        solana_sdk::address_lookup_table::program::id(),
+        solana_sdk::compute_budget::id(),
+        solana_sdk::stake::program::id(),
+    ];
+    ids.extend(BPF_LOADERS.map(|(loader_id, _)| loader_id));
+    ids
+}
+
+/// Compute units a builtin registered by `create_transaction_batch_processor`
+/// charges per invocation, or `None` if `program_id` isn't one.
+///
+/// Builtins charge a fixed amount up front and don't log it, so this is what
+/// prices a recorded CPI into one.
+pub(crate) fn builtin_compute_units(program_id: &Pubkey) -> Option<u64> {
+    let units = [
+        (
+            solana_system_program::id(),
+            solana_system_program::system_processor::DEFAULT_COMPUTE_UNITS,
+        ),
+        (
+            solana_sdk::address_lookup_table::program::id(),
+            solana_address_lookup_table_program::processor::DEFAULT_COMPUTE_UNITS,
+        ),
+        (
+            solana_sdk::compute_budget::id(),
+            solana_compute_budget_program::DEFAULT_COMPUTE_UNITS,
+        ),
+        (
+            solana_sdk::stake::program::id(),
+            solana_stake_program::stake_instruction::DEFAULT_COMPUTE_UNITS,
+        ),
+        (
+            solana_sdk::bpf_loader::ID,
+            solana_bpf_loader_program::DEFAULT_LOADER_COMPUTE_UNITS,
+        ),
+        (
+            solana_sdk::bpf_loader_deprecated::ID,
+            solana_bpf_loader_program::DEPRECATED_LOADER_COMPUTE_UNITS,
+        ),
+        (
+            solana_sdk::bpf_loader_upgradeable::ID,
+            solana_bpf_loader_program::UPGRADEABLE_LOADER_COMPUTE_UNITS,
+        ),
+    ];
+    units
+        .into_iter()
+        .find(|(builtin_id, _)| builtin_id == program_id)
+        .map(|(_, units)| units)
+}
+
+/// Builds an executable program account from a compiled ELF.
+///
+/// The account is owned by the BPF Loader v2 (non-upgradeable), which keeps the
+/// bytecode in the program account itself, and holds the rent-exempt minimum.
+pub(crate) fn program_account_from_elf(elf: &[u8]) -> AccountSharedData {
+    AccountSharedData::from(Account {
+        lamports: Rent::default().minimum_balance(elf.len()),
+        data: elf.to_vec(),
+        owner: solana_sdk::bpf_loader::id(),
+        executable: true,
+        rent_epoch: 0,
+    })
+}
+
+/// Accounts staking instructions need besides the stake and vote accounts,
+/// keyed by address: the Stake program, the `Clock` and `Rent` sysvars, an
+/// empty `StakeHistory` sysvar and the stake config account, with its default
+/// values.
+///
+/// Clusters have all of them, but other account sources, like a
+/// `MockAccountSource`, may not.
+// The stake config is deprecated, but the Stake program still loads it.
+#[allow(deprecated)]
+pub(crate) fn stake_accounts(clock: &Clock, rent: &Rent) -> HashMap<Pubkey, AccountSharedData> {
+    let mut config = solana_stake_program::config::create_account(
+        0,
+        &solana_sdk::stake::config::Config::default(),
+    );
+    config.set_lamports(rent.minimum_balance(config.data().len()));
+    HashMap::from([
+        (
+            solana_sdk::stake::program::id(),
+            solana_sdk::native_loader::create_loadable_account_for_test("stake_program"),
+        ),
+        (sysvar::clock::id(), create_account_shared_data_for_test(clock)),
+        (sysvar::rent::id(), create_account_shared_data_for_test(rent)),
+        (
+            sysvar::stake_history::id(),
+            create_account_shared_data_for_test(&StakeHistory::default()),
+        ),
+        (solana_sdk::stake::config::id(), config),
+    ])
+}
+
+/// Decodes the data of a Compute Budget program instruction.
+///
+/// Returns `None` for unknown discriminators or malformed data.
+pub(crate) fn decode_compute_budget_instruction(data: &[u8]) -> Option<ComputeBudgetInstruction> {
+    let (discriminator, rest) = data.split_first()?;
+    match discriminator {
+        1 => Some(ComputeBudgetInstruction::RequestHeapFrame(
+            u32::from_le_bytes(rest.try_into().ok()?),
+        )),
+        2 => Some(ComputeBudgetInstruction::SetComputeUnitLimit(
+            u32::from_le_bytes(rest.try_into().ok()?),
+        )),
+        3 => Some(ComputeBudgetInstruction::SetComputeUnitPrice(
+            u64::from_le_bytes(rest.try_into().ok()?),
+        )),
+        4 => Some(ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(
+            u32::from_le_bytes(rest.try_into().ok()?),
+        )),
+        _ => None,
+    }
+}
+
+/// Rebuilds the instructions of a legacy message.
+///
+/// Each account's signer and writable flags are taken from the message header.
+pub(crate) fn decompile_instructions(message: &Message) -> Vec<Instruction> {
+    message
+        .instructions
+        .iter()
+        .map(|ix| Instruction {
+            program_id: message.account_keys[ix.program_id_index as usize],
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|&index| {
+                    let index = index as usize;
+                    AccountMeta {
+                        pubkey: message.account_keys[index],
+                        is_signer: message.is_signer(index),
+                        is_writable: message.is_maybe_writable(index, None),
+                    }
+                })
+                .collect(),
+            data: ix.data.clone(),
+        })
+        .collect()
+}
+
+/// Rewrites a `v0` message as an equivalent legacy message, given the
+/// addresses its lookup tables resolve to.
+///
+/// Account order may differ from the original, but every account keeps its
+/// signer and writable flags. The message must be sanitized.
+pub(crate) fn v0_to_legacy_message(message: &v0::Message, loaded: &LoadedAddresses) -> Message {
+    let account_keys: Vec<Pubkey> = message
+        .account_keys
+        .iter()
+        .chain(&loaded.writable)
+        .chain(&loaded.readonly)
+        .copied()
+        .collect();
+    let num_signers = message.header.num_required_signatures as usize;
+    let instructions: Vec<Instruction> = message
+        .instructions
+        .iter()
+        .map(|ix| Instruction {
+            program_id: account_keys[ix.program_id_index as usize],
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|&index| {
+                    let index = index as usize;
+                    AccountMeta {
+                        pubkey: account_keys[index],
+                        is_signer: index < num_signers,
+                        is_writable: message.is_maybe_writable(index, None),
+                    }
+                })
+                .collect(),
+            data: ix.data.clone(),
+        })
+        .collect();
+    Message::new_with_blockhash(
+        &instructions,
+        message.account_keys.first(),
+        &message.recent_blockhash,
+    )
+}
+
+/// Decodes a base64 or base58 wire-format transaction into a legacy `Transaction`.
+///
+/// Base64 is tried first; base58 is used if base64 decoding or deserialization fails.
+pub(crate) fn decode_transaction(encoded: &str) -> Result<Transaction, String> {
+    let versioned = BASE64_STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| bincode::deserialize::<VersionedTransaction>(&bytes).ok())
+        .or_else(|| {
+            bs58::decode(encoded)
+                .into_vec()
+                .ok()
+                .and_then(|bytes| bincode::deserialize::<VersionedTransaction>(&bytes).ok())
+        })
+        .ok_or_else(|| "not a valid base64 or base58 encoded transaction".to_string())?;
+    versioned
+        .into_legacy_transaction()
+        .ok_or_else(|| "v0 transactions are not supported".to_string())
+}
+
+/// Sets the compute unit limit of a message in place.
+///
+/// Rewrites an existing `SetComputeUnitLimit` instruction, or inserts one as
+/// the first instruction, adding the Compute Budget program key only if the
+/// message doesn't already reference it. Invalidates existing signatures.
+pub(crate) fn set_compute_unit_limit(message: &mut Message, limit: u32) {
+    set_compute_budget_instruction(
+        message,
+        ComputeBudgetInstruction::set_compute_unit_limit(limit),
+        |ix| matches!(ix, ComputeBudgetInstruction::SetComputeUnitLimit(_)),
+    );
+}
+
+/// Sets the compute unit price of a message, in micro-lamports, in place.
+///
+/// Like `set_compute_unit_limit`, rewrites an existing `SetComputeUnitPrice`
+/// instruction or inserts one first.
+pub(crate) fn set_compute_unit_price(message: &mut Message, micro_lamports: u64) {
+    set_compute_budget_instruction(
+        message,
+        ComputeBudgetInstruction::set_compute_unit_price(micro_lamports),
+        |ix| matches!(ix, ComputeBudgetInstruction::SetComputeUnitPrice(_)),
+    );
+}
+
+/// Sets the heap frame size a message requests, in bytes, in place.
+///
+/// Like `set_compute_unit_limit`, rewrites an existing `RequestHeapFrame`
+/// instruction or inserts one first. The runtime rejects sizes that aren't a
+/// multiple of 1024 between 32 KiB and 256 KiB, so those fail with
+/// `SolanaClientExtError::InvalidHeapSize` and leave the message unchanged.
+pub(crate) fn set_heap_frame(
+    message: &mut Message,
+    bytes: u32,
+) -> Result<(), SolanaClientExtError> {
+    if bytes % 1024 != 0 || !(MIN_HEAP_FRAME_BYTES..=MAX_HEAP_FRAME_BYTES).contains(&bytes) {
+        return Err(SolanaClientExtError::InvalidHeapSize { bytes });
+    }
+    set_compute_budget_instruction(
+        message,
+        ComputeBudgetInstruction::request_heap_frame(bytes),
+        |ix| matches!(ix, ComputeBudgetInstruction::RequestHeapFrame(_)),
+    );
+    Ok(())
+}
+
+/// Sets the loaded accounts data size limit of a message, in bytes, in place.
+///
+/// Like `set_compute_unit_limit`, rewrites an existing
+/// `SetLoadedAccountsDataSizeLimit` instruction or inserts one first.
+pub(crate) fn set_loaded_accounts_data_size_limit(message: &mut Message, bytes: u32) {
+    set_compute_budget_instruction(
+        message,
+        ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(bytes),
+        |ix| matches!(ix, ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(_)),
+    );
+}
+
+/// Replaces the message's Compute Budget instruction of the same kind as
+/// `instruction`, or inserts `instruction` first if there is none.
+fn set_compute_budget_instruction(
+    message: &mut Message,
+    instruction: Instruction,
+    same_kind: fn(&ComputeBudgetInstruction) -> bool,
+) {
+    let account_keys = &message.account_keys;
+    let existing = message.instructions.iter_mut().find(|ix| {
+        account_keys.get(ix.program_id_index as usize) == Some(&solana_sdk::compute_budget::id())
+            && decode_compute_budget_instruction(&ix.data).is_some_and(|ix| same_kind(&ix))
+    });
+    if let Some(ix) = existing {
+        ix.data = instruction.data;
+        return;
+    }
+
+    if !message
+        .account_keys
+        .contains(&solana_sdk::compute_budget::id())
+    {
+        message.account_keys.push(solana_sdk::compute_budget::id());
+        message.header.num_readonly_unsigned_accounts += 1;
+    }
+    let compiled_ix = message.compile_instruction(&instruction);
+    message.instructions.insert(0, compiled_ix);
+}
+
+/// Converts a failed `RpcClient::get_fee_for_message` call for `message`.
+///
+/// The client reports the `None` fee the cluster returns for a blockhash it
+/// doesn't know (e.g. an expired one) as a custom error; it becomes a
+/// `BlockhashError` naming the blockhash.
+pub(crate) fn fee_for_message_error(message: &Message, err: ClientError) -> SolanaClientExtError {
+    match err.kind() {
+        ClientErrorKind::Custom(_) => SolanaClientExtError::BlockhashError(format!(
+            "blockhash {} of the message has expired or is unknown to the cluster",
+            message.recent_blockhash
+        )),
+        _ => err.into(),
+    }
+}
+
+/// Returns the first signer `transaction` requires that hasn't signed it yet
+/// and isn't among `signers`, if any.
+///
+/// Local simulation skips signature verification, so this is what catches a
+/// transaction that would be rejected for a missing signature. Partially signed
+/// transactions only need `signers` for the remaining signatures.
+pub(crate) fn missing_signer<I: Signers + ?Sized>(
+    transaction: &Transaction,
+    signers: &I,
+) -> Option<Pubkey> {
+    let signer_pubkeys = signers.pubkeys();
+    transaction
+        .message
+        .signer_keys()
+        .into_iter()
+        .enumerate()
+        .find(|(i, pubkey)| {
+            let signed = transaction
+                .signatures
+                .get(*i)
+                .is_some_and(|signature| *signature != Signature::default());
+            !signed && !signer_pubkeys.contains(pubkey)
+        })
+        .map(|(_, pubkey)| *pubkey)
+}
+
+/// Returns whether a simulation failed because a program couldn't be found or
+/// executed locally, rather than because of the transaction itself.
+pub(crate) fn is_program_load_error(err: &TransactionError) -> bool {
+    matches!(
+        err,
+        TransactionError::ProgramAccountNotFound
+            | TransactionError::InvalidProgramForExecution
+            | TransactionError::InstructionError(_, InstructionError::UnsupportedProgramId)
+    )
+}
+
+/// Wraps a failed `getRecentPrioritizationFees` call with the accounts it was made for.
+pub(crate) fn priority_fee_fetch_error(
+    accounts: &[Pubkey],
+    err: ClientError,
+) -> SolanaClientExtError {
+    SolanaClientExtError::PriorityFeeFetchError(format!(
+        "getRecentPrioritizationFees failed for {} account(s): {}",
+        accounts.len(),
+        err
+    ))
+}
+
+/// Extracts the consumed compute units from an RPC simulation response.
+///
+/// Fails if the response carries no CU data, or if the simulation errored
+/// before consuming any compute units.
+pub(crate) fn units_consumed_from_simulation(
+    result: &RpcSimulateTransactionResult,
+) -> Result<u64, SolanaClientExtError> {
+    let consumed_cu = result.units_consumed.ok_or_else(|| {
+        SolanaClientExtError::ComputeUnitsError(
+            "Missing Compute Units from transaction simulation.".into(),
+        )
+    })?;
+    if let (0, Some(err)) = (consumed_cu, &result.err) {
+        return Err(SolanaClientExtError::RpcError(format!(
+            "Transaction simulation failed: {:?}",
+            err
+        )));
+    }
+    Ok(consumed_cu)
+}
+
+/// Signs `instructions` behind a `MAX_COMPUTE_UNIT_LIMIT` limit over `blockhash`
+/// and simulates them over RPC.
+///
+/// Returns the units consumed along with the simulation error, if any. A
+/// transaction over the cap fails with `ComputationalBudgetExceeded` and reports
+/// the cap itself as consumed, so check the result with `fits_cu_cap`.
+pub(crate) fn simulate_at_max_cu_limit<I: Signers + ?Sized>(
+    rpc_client: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    signers: &I,
+    blockhash: Hash,
+) -> Result<(u64, Option<TransactionError>), SolanaClientExtError> {
+    let mut with_limit = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+        MAX_COMPUTE_UNIT_LIMIT,
+    )];
+    with_limit.extend_from_slice(instructions);
+    let mut tx = Transaction::new_unsigned(Message::new(&with_limit, Some(payer)));
+    tx.try_sign(signers, blockhash).map_err(|err| {
+        SolanaClientExtError::TransactionBuildError(format!(
+            "failed to sign transaction for CU estimation: {}",
+            err
+        ))
+    })?;
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: true,
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let result = rpc_client.simulate_transaction_with_config(&tx, config)?.value;
+    let consumed_cu = result.units_consumed.ok_or_else(|| {
+        SolanaClientExtError::ComputeUnitsError(
+            "Missing Compute Units from transaction simulation.".into(),
+        )
+    })?;
+    Ok((consumed_cu, result.err))
+}
+
+/// Whether a `simulate_at_max_cu_limit` result ran to completion under the cap.
+pub(crate) fn fits_cu_cap(consumed_cu: u64, err: &Option<TransactionError>) -> bool {
+    err.is_none() && consumed_cu < MAX_COMPUTE_UNIT_LIMIT as u64
+}
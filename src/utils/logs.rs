@@ -0,0 +1,128 @@
+use std::str::FromStr;
+
+use solana_sdk::pubkey::Pubkey;
+
+/// Parses a `"Program <id> invoke [<depth>]"` log line into its program id and depth.
+///
+/// Returns `None` for any other kind of log line.
+pub(crate) fn parse_invoke_line(line: &str) -> Option<(&str, u32)> {
+    let mut parts = line.strip_prefix("Program ")?.split_whitespace();
+    let program_id = parts.next()?;
+    if parts.next()? != "invoke" {
+        return None;
+    }
+    let depth = parts
+        .next()?
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .parse()
+        .ok()?;
+    Some((program_id, depth))
+}
+
+/// Returns the deepest invocation level found in the execution logs.
+///
+/// Top-level instructions are invoked at depth 1, and every nested CPI adds one.
+/// Returns 0 if no invocations were logged.
+pub(crate) fn max_invoke_depth(logs: &[String]) -> u32 {
+    logs.iter()
+        .filter_map(|line| parse_invoke_line(line))
+        .map(|(_, depth)| depth)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Counts the CPIs in the execution logs: every invocation below depth 1.
+pub(crate) fn cpi_count(logs: &[String]) -> u32 {
+    logs.iter()
+        .filter_map(|line| parse_invoke_line(line))
+        .filter(|(_, depth)| *depth > 1)
+        .count() as u32
+}
+
+/// Returns every program invoked in the execution logs, including through CPI.
+///
+/// Programs are listed once each, in the order they were first invoked.
+pub(crate) fn invoked_programs(logs: &[String]) -> Vec<Pubkey> {
+    let mut programs = Vec::new();
+    for (program_id, _) in logs.iter().filter_map(|line| parse_invoke_line(line)) {
+        if let Ok(program_id) = Pubkey::from_str(program_id) {
+            if !programs.contains(&program_id) {
+                programs.push(program_id);
+            }
+        }
+    }
+    programs
+}
+
+/// Parses a `"Program <id> consumed <n> of <m> compute units"` log line into
+/// its program id and consumed units.
+pub(crate) fn parse_consumed_line(line: &str) -> Option<(&str, u64)> {
+    let mut parts = line.strip_prefix("Program ")?.split_whitespace();
+    let program_id = parts.next()?;
+    if parts.next()? != "consumed" {
+        return None;
+    }
+    let consumed = parts.next()?.parse().ok()?;
+    Some((program_id, consumed))
+}
+
+/// Returns whether the line closes a program invocation (`success` or `failed`).
+fn is_invoke_result_line(line: &str) -> bool {
+    line.strip_prefix("Program ")
+        .and_then(|rest| rest.split_whitespace().nth(1))
+        .is_some_and(|word| word == "success" || word == "failed:")
+}
+
+/// Returns the compute units consumed by each nested (CPI) invocation, grouped
+/// by top-level instruction.
+///
+/// Invocations are listed in the order they were logged, which matches the
+/// order of the SVM's inner instruction records. Builtins don't log a
+/// `consumed` line, so their entries are `None`.
+pub(crate) fn inner_invocation_units(logs: &[String]) -> Vec<Vec<Option<u64>>> {
+    let mut units: Vec<Vec<Option<u64>>> = Vec::new();
+    // One frame per open invocation: the index of its entry in the current
+    // top-level instruction's list, or `None` for the top-level frame itself.
+    let mut frames: Vec<Option<usize>> = Vec::new();
+    for line in logs {
+        if let Some((_, depth)) = parse_invoke_line(line) {
+            if depth <= 1 {
+                units.push(Vec::new());
+                frames.clear();
+                frames.push(None);
+            } else if let Some(invocations) = units.last_mut() {
+                invocations.push(None);
+                frames.push(Some(invocations.len() - 1));
+            }
+        } else if let Some((_, consumed)) = parse_consumed_line(line) {
+            if let (Some(Some(index)), Some(invocations)) = (frames.last(), units.last_mut()) {
+                invocations[*index] = Some(consumed);
+            }
+        } else if is_invoke_result_line(line) {
+            frames.pop();
+        }
+    }
+    units
+}
+
+/// Sums the compute units reported by top-level BPF program invocations.
+///
+/// Only BPF programs log a `consumed` line, and a program's total already
+/// includes its CPIs, so only lines at invocation depth 1 are counted.
+pub(crate) fn top_level_bpf_units(logs: &[String]) -> u64 {
+    let mut depth = 0u32;
+    let mut units = 0u64;
+    for line in logs {
+        if let Some((_, invoke_depth)) = parse_invoke_line(line) {
+            depth = invoke_depth;
+        } else if let Some((_, consumed)) = parse_consumed_line(line) {
+            if depth == 1 {
+                units = units.saturating_add(consumed);
+            }
+        } else if is_invoke_result_line(line) {
+            depth = depth.saturating_sub(1);
+        }
+    }
+    units
+}
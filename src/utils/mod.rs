@@ -1 +1,4 @@
+pub mod client;
+pub mod describe;
 pub mod helpers;
+pub mod logs;
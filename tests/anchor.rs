@@ -0,0 +1,56 @@
+#![cfg(feature = "anchor")]
+
+use borsh::BorshSerialize;
+use solana_client_ext::anchor::{anchor_discriminator, anchor_instruction};
+use solana_client_ext::{MockAccountSource, RollUpAccountLoader};
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey, transaction::TransactionError};
+
+mod common;
+
+#[derive(BorshSerialize)]
+struct InitializeArgs {
+    amount: u64,
+}
+
+#[test]
+fn test_anchor_discriminator() {
+    // sha256("global:initialize")[..8]
+    assert_eq!(
+        anchor_discriminator("initialize"),
+        [175, 175, 109, 31, 13, 152, 155, 237]
+    );
+}
+
+#[test]
+fn test_anchor_instruction_data() {
+    let program_id = Pubkey::new_unique();
+    let ix = anchor_instruction(
+        program_id,
+        vec![AccountMeta::new(Pubkey::new_unique(), false)],
+        "initialize",
+        &InitializeArgs { amount: 42 },
+    )
+    .unwrap();
+
+    assert_eq!(ix.program_id, program_id);
+    assert_eq!(ix.data[..8], anchor_discriminator("initialize"));
+    assert_eq!(ix.data[8..], 42u64.to_le_bytes());
+}
+
+#[test]
+fn test_simulate_anchor_ix_missing_program() {
+    let loader = RollUpAccountLoader::with_source(MockAccountSource::new());
+    let channel = common::mock_channel_with_loader(loader);
+
+    let result = channel
+        .simulate_anchor_ix(
+            Pubkey::new_unique(),
+            vec![],
+            "initialize",
+            InitializeArgs { amount: 42 },
+        )
+        .unwrap();
+    // The funded payer covers fees, so the transaction gets as far as the program.
+    assert!(!result.success);
+    assert_eq!(result.error, Some(TransactionError::ProgramAccountNotFound));
+}
@@ -0,0 +1,216 @@
+//! Fixtures shared by the integration tests.
+//!
+//! Each test crate uses a different subset of them.
+#![allow(dead_code)]
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde_json::{json, Value};
+use solana_client::client_error::{ClientErrorKind, Result as ClientResult};
+use solana_client::nonblocking::rpc_client::RpcClient as NonblockingRpcClient;
+use solana_client::rpc_client::{RpcClient, RpcClientConfig};
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_client_ext::rpc_compat::to_rpc_simulate_response;
+use solana_client_ext::{
+    AccountSource, EstimatedPrioritizationFee, FeeModel, MockAccountSource, RawSimulationResult,
+    RollUpAccountLoader, RollUpChannel,
+};
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount},
+    compute_budget,
+    hash::Hash,
+    native_loader,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair},
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+use std::sync::{Arc, Mutex};
+
+/// Environment variable holding the path of the keypair file devnet tests pay
+/// fees from, as written by `solana-keygen new`.
+pub const DEVNET_PAYER_ENV: &str = "DEVNET_PAYER_KEYPAIR";
+
+/// Returns the funded devnet account the tests pay fees from, read from the
+/// file at `DEVNET_PAYER_KEYPAIR`.
+///
+/// Returns `None` if the variable isn't set, so tests needing a funded payer
+/// can skip themselves.
+///
+/// # Panics
+/// If the variable is set but the file can't be read as a keypair.
+pub fn devnet_payer() -> Option<Keypair> {
+    let Ok(path) = std::env::var(DEVNET_PAYER_ENV) else {
+        eprintln!("skipping: {} is not set", DEVNET_PAYER_ENV);
+        return None;
+    };
+    let payer = read_keypair_file(&path)
+        .unwrap_or_else(|err| panic!("failed to read devnet payer from {}: {}", path, err));
+    Some(payer)
+}
+
+/// Fee model pricing every CU at 1,000 micro-lamports, so fee estimates don't
+/// depend on RPC or recent network fees.
+pub struct FixedFeeModel;
+
+impl FeeModel for FixedFeeModel {
+    fn estimate_priority_fee(
+        &self,
+        _accounts: &[Pubkey],
+        cu: u64,
+    ) -> anyhow::Result<EstimatedPrioritizationFee> {
+        Ok(EstimatedPrioritizationFee::from_price(1_000, cu))
+    }
+}
+
+/// Seeds a funded payer and the builtin programs used here into a mock source.
+pub fn seeded_source(payer: &Keypair) -> MockAccountSource {
+    let source = MockAccountSource::new();
+    source.insert(
+        payer.pubkey(),
+        AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+    );
+    source.insert(
+        system_program::id(),
+        native_loader::create_loadable_account_for_test("system_program"),
+    );
+    source.insert(
+        compute_budget::id(),
+        native_loader::create_loadable_account_for_test("compute_budget_program"),
+    );
+    source
+}
+
+/// Builds a channel over `loader` that never contacts RPC: all accounts come
+/// from the loader's source, and the client points at an address nothing
+/// listens on.
+pub fn mock_channel_with_loader(loader: RollUpAccountLoader<'static>) -> RollUpChannel<'static> {
+    let rpc_client = Arc::new(RpcClient::new("http://127.0.0.1:1".to_string()));
+    RollUpChannel::new_shared(vec![], rpc_client).with_account_loader(loader)
+}
+
+/// Builds an offline channel over `seeded_source(payer)`.
+pub fn mock_channel(payer: &Keypair) -> RollUpChannel<'static> {
+    mock_channel_with_loader(RollUpAccountLoader::with_source(seeded_source(payer)))
+}
+
+/// Transfers `lamports` from `payer` to a new account.
+pub fn transfer_tx(payer: &Keypair, lamports: u64) -> Transaction {
+    let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), lamports);
+    Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[payer], Hash::default())
+}
+
+/// Stands in for an RPC node, so `RpcClientExt` methods can run offline.
+///
+/// Serves `getAccountInfo` and `getMultipleAccounts` from `accounts` (failing
+/// while the source is unavailable), and answers the fee and simulation
+/// requests with the configured values.
+pub struct MockRpc {
+    pub accounts: Arc<MockAccountSource>,
+    /// CUs every `simulateTransaction` consumes; `None` fails the request.
+    pub simulated_cu: Option<u64>,
+    /// `getFeeForMessage` result; `None` reports an expired blockhash.
+    pub base_fee: Option<u64>,
+    /// Recent prioritization fee (micro-lamports per CU) of every slot.
+    pub priority_fee: u64,
+    /// Every request received, with its params, in order.
+    pub requests: Arc<Mutex<Vec<(RpcRequest, Value)>>>,
+}
+
+impl MockRpc {
+    /// Serves `seeded_source(payer)`, simulating every transaction at 150 CUs
+    /// (a System Program transfer) and charging 5,000 lamports per signature.
+    pub fn new(payer: &Keypair) -> Self {
+        Self {
+            accounts: Arc::new(seeded_source(payer)),
+            simulated_cu: Some(150),
+            base_fee: Some(5_000),
+            priority_fee: 0,
+            requests: Arc::default(),
+        }
+    }
+
+    pub fn into_client(self) -> RpcClient {
+        RpcClient::new_sender(self, RpcClientConfig::default())
+    }
+
+    pub fn into_nonblocking_client(self) -> NonblockingRpcClient {
+        NonblockingRpcClient::new_sender(self, RpcClientConfig::default())
+    }
+
+    fn account_json(&self, pubkey: &Value) -> ClientResult<Value> {
+        let pubkey: Pubkey = pubkey.as_str().unwrap().parse().unwrap();
+        let account = self
+            .accounts
+            .get_account(&pubkey)
+            .map_err(|err| ClientErrorKind::Custom(err.to_string()))?;
+        Ok(account.map_or(Value::Null, |account| {
+            json!({
+                "lamports": account.lamports(),
+                "data": [BASE64_STANDARD.encode(account.data()), "base64"],
+                "owner": account.owner().to_string(),
+                "executable": account.executable(),
+                "rentEpoch": account.rent_epoch(),
+                "space": account.data().len(),
+            })
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl RpcSender for MockRpc {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        self.requests
+            .lock()
+            .unwrap()
+            .push((request, params.clone()));
+        let context = json!({ "slot": 1 });
+        Ok(match request {
+            RpcRequest::GetVersion => json!({ "solana-core": "2.2.2", "feature-set": 0 }),
+            RpcRequest::GetLatestBlockhash => json!({
+                "context": context,
+                "value": {
+                    "blockhash": Hash::default().to_string(),
+                    "lastValidBlockHeight": 150,
+                },
+            }),
+            RpcRequest::GetAccountInfo => json!({
+                "context": context,
+                "value": self.account_json(&params[0])?,
+            }),
+            RpcRequest::GetMultipleAccounts => {
+                let accounts = params[0]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|pubkey| self.account_json(pubkey))
+                    .collect::<ClientResult<Vec<Value>>>()?;
+                json!({ "context": context, "value": accounts })
+            }
+            RpcRequest::SimulateTransaction => match self.simulated_cu {
+                Some(cu) => {
+                    to_rpc_simulate_response(&RawSimulationResult::base_success(cu), 1, None)
+                }
+                None => {
+                    return Err(
+                        ClientErrorKind::Custom("simulation unavailable".to_string()).into(),
+                    )
+                }
+            },
+            RpcRequest::GetFeeForMessage => json!({ "context": context, "value": self.base_fee }),
+            RpcRequest::GetRecentPrioritizationFees => {
+                json!([{ "slot": 1, "prioritizationFee": self.priority_fee }])
+            }
+            _ => panic!("unexpected request: {:?}", request),
+        })
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "mock-rpc".to_string()
+    }
+}
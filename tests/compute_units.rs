@@ -0,0 +1,607 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde_json::{json, Value};
+use solana_client::client_error::Result as ClientResult;
+use solana_client::rpc_client::{RpcClient, RpcClientConfig};
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_client_ext::rpc_compat::to_rpc_simulate_response;
+use solana_client_ext::{
+    parse_compute_budget, total_compute_units, transaction_metrics, try_total_compute_units,
+    CuBreakdown, CuEstimate, CuEstimateSource, CuMarginConfig, CuSource, HybridEstimateConfig,
+    InstructionGroup, RawSimulationResult, RpcClientExt, SolanaClientExtError,
+};
+use solana_sdk::{
+    account::{AccountSharedData, WritableAccount},
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{Instruction, InstructionError},
+    loader_v4,
+    message::Message,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+
+mod common;
+use common::MockRpc;
+
+/// Program whose instructions `BurnSimulator` charges the CUs in their data.
+const BURN_PROGRAM: Pubkey = Pubkey::new_from_array([7; 32]);
+
+fn burn(units: u32) -> Instruction {
+    Instruction::new_with_bytes(BURN_PROGRAM, &units.to_le_bytes(), vec![])
+}
+
+/// Stands in for an RPC node whose simulations charge each `BURN_PROGRAM`
+/// instruction the little-endian `u32` in its data, and 150 CUs for any other
+/// instruction. Like the runtime, a transaction that runs past its limit fails
+/// with `ComputationalBudgetExceeded` and reports the limit as consumed.
+struct BurnSimulator;
+
+#[async_trait::async_trait]
+impl RpcSender for BurnSimulator {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        Ok(match request {
+            RpcRequest::GetVersion => json!({ "solana-core": "2.2.2", "feature-set": 0 }),
+            RpcRequest::GetLatestBlockhash => json!({
+                "context": { "slot": 1 },
+                "value": {
+                    "blockhash": Hash::default().to_string(),
+                    "lastValidBlockHeight": 150,
+                },
+            }),
+            RpcRequest::SimulateTransaction => simulate_burns(params[0].as_str().unwrap()),
+            _ => panic!("unexpected request: {:?}", request),
+        })
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "burn-simulator".to_string()
+    }
+}
+
+fn simulate_burns(encoded: &str) -> Value {
+    let tx: Transaction = bincode::deserialize(&BASE64_STANDARD.decode(encoded).unwrap()).unwrap();
+    let limit = parse_compute_budget(&tx).unit_limit.unwrap_or(200_000) as u64;
+    let mut result = RawSimulationResult::base_success(0);
+    for (i, ix) in tx.message.instructions.iter().enumerate() {
+        let program_id = tx.message.account_keys[ix.program_id_index as usize];
+        result.cu += if program_id == BURN_PROGRAM {
+            u32::from_le_bytes(ix.data[..4].try_into().unwrap()) as u64
+        } else {
+            150
+        };
+        if result.cu > limit {
+            result.cu = limit;
+            result.error = Some(TransactionError::InstructionError(
+                i as u8,
+                InstructionError::ComputationalBudgetExceeded,
+            ));
+            break;
+        }
+    }
+    to_rpc_simulate_response(&result, 1, None)
+}
+
+fn burn_client() -> RpcClient {
+    RpcClient::new_sender(BurnSimulator, RpcClientConfig::default())
+}
+
+#[test]
+fn test_cu_margin_apply() {
+    let margin = CuMarginConfig {
+        recommended_multiplier: 1.1,
+        p95_multiplier: 1.5,
+    };
+    let estimate = margin.apply(1_000);
+    assert_eq!(
+        estimate,
+        CuEstimate {
+            point: 1_000,
+            recommended: 1_100,
+            p95: 1_500,
+        }
+    );
+
+    // Derived limits never exceed the per-transaction CU cap.
+    let capped = margin.apply(1_200_000);
+    assert_eq!(capped.point, 1_200_000);
+    assert_eq!(capped.p95, 1_400_000);
+}
+
+#[test]
+fn test_total_compute_units() {
+    let results = vec![
+        RawSimulationResult::base_success(1_000),
+        RawSimulationResult::base_failure("Transaction 1 failed"),
+        RawSimulationResult::base_success(2_500),
+    ];
+
+    assert_eq!(total_compute_units(&results), 3_500);
+    assert!(try_total_compute_units(&results).is_err());
+    assert_eq!(
+        try_total_compute_units(&[results[0].clone()]).unwrap(),
+        1_000
+    );
+}
+
+#[test]
+fn test_cu_breakdown_from_logs() {
+    let mut result = RawSimulationResult::base_success(3_150);
+    result.logs = vec![
+        "Program 11111111111111111111111111111111 invoke [1]".to_string(),
+        "Program 11111111111111111111111111111111 success".to_string(),
+        "Program MyProgram111111111111111111111111111111111 invoke [1]".to_string(),
+        "Program log: hello".to_string(),
+        "Program 11111111111111111111111111111111 invoke [2]".to_string(),
+        "Program 11111111111111111111111111111111 success".to_string(),
+        "Program MyProgram111111111111111111111111111111111 consumed 3000 of 200000 compute units"
+            .to_string(),
+        "Program MyProgram111111111111111111111111111111111 success".to_string(),
+    ];
+
+    assert_eq!(
+        result.cu_breakdown(),
+        CuBreakdown {
+            builtin: 150,
+            bpf: 3_000,
+            total: 3_150,
+        }
+    );
+}
+
+#[test]
+fn test_suggest_instruction_split_over_cap() {
+    let rpc_client = burn_client();
+    let payer = Keypair::new();
+    let instructions: Vec<_> = [600_000, 600_000, 300_000, 900_000]
+        .into_iter()
+        .map(burn)
+        .collect();
+
+    // Adding the third instruction would take the first group to 1.5M CUs.
+    let groups = rpc_client
+        .suggest_instruction_split(&instructions, &payer.pubkey(), &[&payer])
+        .unwrap();
+    assert_eq!(
+        groups,
+        vec![
+            InstructionGroup {
+                indices: vec![0, 1],
+                cu: 1_200_150,
+                cu_deltas: vec![600_150, 600_000],
+            },
+            InstructionGroup {
+                indices: vec![2, 3],
+                cu: 1_200_150,
+                cu_deltas: vec![300_150, 900_000],
+            },
+        ]
+    );
+
+    let err = rpc_client
+        .suggest_instruction_split(&[burn(1_500_000)], &payer.pubkey(), &[&payer])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::ComputeUnitsError(_))
+    ));
+}
+
+#[test]
+fn test_pack_into_transactions_over_cap() {
+    let rpc_client = burn_client();
+    let payer = Keypair::new();
+    // 2.5M CUs of work, far more than a single transaction can hold.
+    let instructions: Vec<_> = (0..5).map(|_| burn(500_000)).collect();
+
+    let transactions = rpc_client
+        .pack_into_transactions(&instructions, &payer.pubkey(), &[&payer], Hash::default())
+        .unwrap();
+    let packed: Vec<usize> = transactions
+        .iter()
+        .map(|tx| tx.message.instructions.len() - 1)
+        .collect();
+    assert_eq!(packed, vec![2, 2, 1]);
+
+    let margin = CuMarginConfig::default();
+    let limits: Vec<u64> = transactions
+        .iter()
+        .map(|tx| parse_compute_budget(tx).unit_limit.unwrap() as u64)
+        .collect();
+    assert_eq!(
+        limits,
+        vec![
+            margin.apply(1_000_150).recommended,
+            margin.apply(1_000_150).recommended,
+            margin.apply(500_150).recommended,
+        ]
+    );
+    for tx in &transactions {
+        assert!(tx.verify().is_ok());
+    }
+}
+
+#[test]
+fn test_pack_into_transactions_over_packet_limit() {
+    let payer = Keypair::new();
+    let rpc_client = MockRpc::new(&payer).into_client();
+    // Far too many recipients for one packet, though well within the CU cap:
+    // each transfer adds 49 bytes to a 206-byte transaction, so 20 fit in 1232.
+    let instructions: Vec<_> = (0..60)
+        .map(|_| system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000))
+        .collect();
+    let blockhash = Hash::new_unique();
+
+    let transactions = rpc_client
+        .pack_into_transactions(&instructions, &payer.pubkey(), &[&payer], blockhash)
+        .unwrap();
+    let packed: Vec<usize> = transactions
+        .iter()
+        .map(|tx| tx.message.instructions.len() - 1)
+        .collect();
+    assert_eq!(packed, vec![20, 20, 20]);
+
+    let cu_limit = CuMarginConfig::default().apply(150).recommended as u32;
+    for tx in &transactions {
+        assert!(!transaction_metrics(tx).exceeds_packet_limit());
+        assert!(tx.verify().is_ok());
+        assert_eq!(tx.message.recent_blockhash, blockhash);
+        assert_eq!(parse_compute_budget(tx).unit_limit, Some(cu_limit));
+    }
+    // Instruction order is preserved across transactions.
+    let recipients: Vec<Pubkey> = transactions
+        .iter()
+        .flat_map(|tx| {
+            tx.message.instructions[1..]
+                .iter()
+                .map(|ix| tx.message.account_keys[ix.accounts[1] as usize])
+        })
+        .collect();
+    let expected: Vec<Pubkey> = instructions
+        .iter()
+        .map(|ix| ix.accounts[1].pubkey)
+        .collect();
+    assert_eq!(recipients, expected);
+}
+
+#[test]
+fn test_compare_local_vs_rpc_offline() {
+    let payer = Keypair::new();
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&payer.pubkey())));
+
+    let rpc_client = MockRpc {
+        simulated_cu: Some(450),
+        ..MockRpc::new(&payer)
+    }
+    .into_client();
+    let comparison = rpc_client.compare_local_vs_rpc(&tx, &[&payer]).unwrap();
+    assert_eq!(comparison.local, 150);
+    assert_eq!(comparison.rpc, Ok(450));
+    assert_eq!(comparison.difference, 300);
+
+    // A failed RPC simulation is reported, not dropped.
+    let rpc_client = MockRpc {
+        simulated_cu: None,
+        ..MockRpc::new(&payer)
+    }
+    .into_client();
+    let comparison = rpc_client.compare_local_vs_rpc(&tx, &[&payer]).unwrap();
+    assert_eq!(comparison.local, 150);
+    let err = comparison.rpc.unwrap_err();
+    assert!(err.contains("simulation unavailable"), "{}", err);
+    assert_eq!(comparison.difference, 0);
+}
+
+#[test]
+fn test_try_estimate_compute_units_mixed_batch() {
+    let payer = Keypair::new();
+    let stranger = Keypair::new();
+    let transfer = |from: &Keypair, lamports| {
+        let ix = system_instruction::transfer(&from.pubkey(), &Pubkey::new_unique(), lamports);
+        Transaction::new_unsigned(Message::new(&[ix], Some(&payer.pubkey())))
+    };
+    let rpc_client = MockRpc::new(&payer).into_client();
+
+    let results = rpc_client.try_estimate_compute_units(
+        &[
+            transfer(&payer, 1_000),
+            transfer(&payer, u64::MAX),
+            transfer(&stranger, 1_000),
+        ],
+        &[&payer],
+    );
+    assert_eq!(results[0], Ok(150));
+    let failure = results[1].as_ref().unwrap_err();
+    assert_eq!(
+        failure.error,
+        Some(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(1)
+        ))
+    );
+    assert!(!failure.logs.is_empty());
+    // Nobody can sign for the stranger, so its transfer isn't simulated.
+    let failure = results[2].as_ref().unwrap_err();
+    assert_eq!(failure.error, Some(TransactionError::SignatureFailure));
+    assert!(failure.message.contains(&stranger.pubkey().to_string()));
+}
+
+#[test]
+fn test_optimize_loaded_accounts_data_size() {
+    let payer = Keypair::new();
+    let transfer = |lamports| {
+        let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), lamports);
+        Transaction::new_unsigned(Message::new(&[ix], Some(&payer.pubkey())))
+    };
+    let rpc_client = MockRpc::new(&payer).into_client();
+
+    // A transfer loads almost no account data, so the smallest limit is used.
+    let mut tx = transfer(1_000);
+    let limit = rpc_client
+        .optimize_loaded_accounts_data_size_unsigned_tx(&mut tx, &[&payer])
+        .unwrap();
+    assert_eq!(limit, 1024);
+    assert_eq!(
+        parse_compute_budget(&tx).loaded_accounts_data_size_limit,
+        Some(1024)
+    );
+
+    let err = rpc_client
+        .optimize_loaded_accounts_data_size_unsigned_tx(&mut transfer(u64::MAX), &[&payer])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::SimulationFailed(_))
+    ));
+
+    let err = rpc_client
+        .optimize_loaded_accounts_data_size_unsigned_tx(&mut transfer(1_000), &[] as &[&Keypair])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::MissingSigner { pubkey }) if *pubkey == payer.pubkey()
+    ));
+}
+
+#[test]
+fn test_estimate_compute_units_with_pubkeys() {
+    // Only the payer's public key is needed, as with a hardware wallet.
+    let payer = Keypair::new();
+    let rpc_client = MockRpc::new(&payer).into_client();
+    let payer = payer.pubkey();
+    let transfer = |lamports| {
+        let ix = system_instruction::transfer(&payer, &Pubkey::new_unique(), lamports);
+        Message::new(&[ix], Some(&payer))
+    };
+
+    let cu = rpc_client
+        .estimate_compute_units_with_pubkeys(&transfer(1_000), &[payer])
+        .unwrap();
+    assert_eq!(cu, 150);
+
+    let err = rpc_client
+        .estimate_compute_units_with_pubkeys(&transfer(1_000), &[])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::MissingSigner { pubkey }) if *pubkey == payer
+    ));
+
+    let err = rpc_client
+        .estimate_compute_units_with_pubkeys(&transfer(u64::MAX), &[payer])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::SimulationFailed(_))
+    ));
+}
+
+#[test]
+fn test_optimize_compute_units_with_heap() {
+    let payer = Keypair::new();
+    let rpc_client = MockRpc::new(&payer).into_client();
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let unsigned = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&payer.pubkey())));
+
+    let mut tx = unsigned.clone();
+    let cu = rpc_client
+        .optimize_compute_units_unsigned_tx_with_heap(&mut tx, &[&payer], Some(64 * 1024))
+        .unwrap();
+    let budget = parse_compute_budget(&tx);
+    assert_eq!(budget.heap_size, Some(64 * 1024));
+    let expected_limit = CuMarginConfig::default().apply(cu as u64).recommended;
+    assert_eq!(budget.unit_limit, Some(expected_limit as u32));
+    // Limit, heap request and transfer, with the Compute Budget program listed once.
+    assert_eq!(tx.message.instructions.len(), 3);
+    let compute_budget_keys = tx
+        .message
+        .account_keys
+        .iter()
+        .filter(|key| **key == solana_sdk::compute_budget::id())
+        .count();
+    assert_eq!(compute_budget_keys, 1);
+
+    // Sizes the runtime would reject fail before anything is simulated.
+    for heap_bytes in [16 * 1024, 64 * 1024 + 1, 512 * 1024] {
+        let mut tx = unsigned.clone();
+        let err = rpc_client
+            .optimize_compute_units_unsigned_tx_with_heap(&mut tx, &[&payer], Some(heap_bytes))
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SolanaClientExtError>(),
+            Some(SolanaClientExtError::InvalidHeapSize { bytes }) if *bytes == heap_bytes
+        ));
+        assert_eq!(tx, unsigned);
+    }
+    let mut message = unsigned.message.clone();
+    let err = rpc_client
+        .optimize_compute_units_msg_with_heap(&mut message, &[&payer], Some(1_000))
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::InvalidHeapSize { bytes: 1_000 })
+    ));
+    assert_eq!(message, unsigned.message);
+}
+
+#[test]
+fn test_estimate_compute_units_hybrid_max_of_both() {
+    let payer = Keypair::new();
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&payer.pubkey())));
+    let config = HybridEstimateConfig {
+        cu_source: CuSource::MaxOfBoth,
+    };
+
+    // The local SVM charges the transfer 150 CUs; RPC reports more or fewer.
+    for (rpc_cu, expected) in [
+        (400, (400, CuEstimateSource::Rpc)),
+        (100, (150, CuEstimateSource::Local)),
+        (150, (150, CuEstimateSource::Local)),
+    ] {
+        let rpc_client = MockRpc {
+            simulated_cu: Some(rpc_cu),
+            ..MockRpc::new(&payer)
+        }
+        .into_client();
+        let estimate = rpc_client
+            .estimate_compute_units_hybrid_with_config(&tx, &[&payer], &config)
+            .unwrap();
+        assert_eq!(estimate, expected, "RPC reported {} CUs", rpc_cu);
+    }
+}
+
+#[test]
+fn test_estimate_compute_units_hybrid_rpc_fallback() {
+    let payer = Keypair::new();
+    let rpc = MockRpc {
+        simulated_cu: Some(2_000),
+        ..MockRpc::new(&payer)
+    };
+    // Deployed with a loader the local SVM doesn't run.
+    let unsupported_program = Pubkey::new_unique();
+    let mut program = AccountSharedData::new(1_000_000, 0, &loader_v4::id());
+    program.set_executable(true);
+    rpc.accounts.insert(unsupported_program, program);
+    let rpc_client = rpc.into_client();
+    let local_only = HybridEstimateConfig {
+        cu_source: CuSource::LocalOnly,
+    };
+
+    // Programs that are missing or can't run locally are simulated with RPC.
+    for program_id in [Pubkey::new_unique(), unsupported_program] {
+        let ix = Instruction::new_with_bytes(program_id, &[], vec![]);
+        let tx = Transaction::new_unsigned(Message::new(&[ix], Some(&payer.pubkey())));
+        let estimate = rpc_client
+            .estimate_compute_units_hybrid(&tx, &[&payer])
+            .unwrap();
+        assert_eq!(estimate, (2_000, CuEstimateSource::Rpc));
+        let estimate = rpc_client
+            .estimate_compute_units_hybrid_with_config(
+                &tx,
+                &[&payer],
+                &HybridEstimateConfig::default(),
+            )
+            .unwrap();
+        assert_eq!(estimate, (2_000, CuEstimateSource::Rpc));
+        assert!(rpc_client
+            .estimate_compute_units_hybrid_with_config(&tx, &[&payer], &local_only)
+            .is_err());
+    }
+
+    // Other failures aren't retried with RPC.
+    let overdraft = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), u64::MAX);
+    let tx = Transaction::new_unsigned(Message::new(&[overdraft], Some(&payer.pubkey())));
+    let err = rpc_client
+        .estimate_compute_units_hybrid(&tx, &[&payer])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::ComputeUnitsError(_))
+    ));
+}
+
+#[test]
+fn test_cu_curve() {
+    let rpc_client = burn_client();
+    let payer = Keypair::new();
+    let instructions = vec![
+        burn(1_000),
+        burn(2_000),
+        system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000),
+        burn(3_000),
+    ];
+
+    // One point per prefix, each simulated on its own.
+    let curve = rpc_client
+        .cu_curve(&instructions, &payer.pubkey(), &[&payer])
+        .unwrap();
+    assert_eq!(curve, vec![1_000, 3_000, 3_150, 6_150]);
+
+    // Past the default 200,000 CU limit, the curve flattens at the limit.
+    let over_budget = [burn(150_000), burn(100_000)];
+    let curve = rpc_client
+        .cu_curve(&over_budget, &payer.pubkey(), &[&payer])
+        .unwrap();
+    assert_eq!(curve, vec![150_000, 200_000]);
+}
+
+#[test]
+fn test_plan_compute_budget_offline() {
+    let payer = Keypair::new();
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[transfer_ix.clone()], Some(&payer.pubkey())));
+    // The transfer and both Compute Budget instructions cost 150 CUs each.
+    let cu_limit = CuMarginConfig::default().apply(450).recommended as u32;
+
+    let rpc = MockRpc {
+        priority_fee: 2_000,
+        ..MockRpc::new(&payer)
+    };
+    let requests = rpc.requests.clone();
+    let rpc_client = rpc.into_client();
+    let plan = rpc_client.plan_compute_budget(&tx, &[&payer]).unwrap();
+    assert_eq!(
+        plan,
+        vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(cu_limit),
+            ComputeBudgetInstruction::set_compute_unit_price(2_000),
+        ]
+    );
+
+    // An existing price is kept, so recent fees aren't fetched.
+    let priced = Transaction::new_unsigned(Message::new(
+        &[
+            ComputeBudgetInstruction::set_compute_unit_price(5),
+            transfer_ix,
+        ],
+        Some(&payer.pubkey()),
+    ));
+    requests.lock().unwrap().clear();
+    let plan = rpc_client.plan_compute_budget(&priced, &[&payer]).unwrap();
+    assert_eq!(
+        plan,
+        vec![ComputeBudgetInstruction::set_compute_unit_limit(cu_limit)]
+    );
+    assert!(!requests
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|(request, _)| *request == RpcRequest::GetRecentPrioritizationFees));
+
+    // Without recent fees there is no price worth setting.
+    let rpc_client = MockRpc::new(&payer).into_client();
+    let plan = rpc_client.plan_compute_budget(&tx, &[&payer]).unwrap();
+    assert_eq!(
+        plan,
+        vec![ComputeBudgetInstruction::set_compute_unit_limit(cu_limit)]
+    );
+}
@@ -0,0 +1,49 @@
+use solana_client_ext::describe_transaction;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+
+#[test]
+fn test_describe_transaction() {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let program_id = Pubkey::new_unique();
+    let readonly = Pubkey::new_unique();
+    let instructions = [
+        ComputeBudgetInstruction::set_compute_unit_limit(300),
+        system_instruction::transfer(&payer.pubkey(), &recipient, 1_000),
+        Instruction::new_with_bytes(
+            program_id,
+            &[1, 2, 3],
+            vec![AccountMeta::new_readonly(readonly, false)],
+        ),
+    ];
+    let tx = Transaction::new_unsigned(Message::new(&instructions, Some(&payer.pubkey())));
+
+    let description = describe_transaction(&tx);
+    let expected = [
+        format!(
+            "Instruction 0: {} (Compute Budget: SetComputeUnitLimit(300))",
+            solana_sdk::compute_budget::id()
+        ),
+        "  data: 5 bytes".to_string(),
+        format!(
+            "Instruction 1: {} (System Program: Transfer {{ lamports: 1000 }})",
+            system_program::id()
+        ),
+        "  data: 12 bytes".to_string(),
+        format!("  account {} [signer, writable]", payer.pubkey()),
+        format!("  account {} [writable]", recipient),
+        format!("Instruction 2: {}", program_id),
+        "  data: 3 bytes".to_string(),
+        format!("  account {}", readonly),
+    ];
+    assert_eq!(description.lines().collect::<Vec<_>>(), expected);
+}
@@ -0,0 +1,89 @@
+use std::error::Error;
+
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_client::RpcClient;
+use solana_client_ext::{RpcClientExt, SolanaClientExtError};
+use solana_sdk::pubkey::Pubkey;
+
+#[test]
+fn test_client_error_conversion_keeps_source() {
+    fn fetch() -> Result<(), SolanaClientExtError> {
+        let rpc_result: Result<(), ClientError> =
+            Err(ClientErrorKind::Custom("boom".to_string()).into());
+        rpc_result?;
+        Ok(())
+    }
+
+    let err = fetch().unwrap_err();
+    assert!(matches!(err, SolanaClientExtError::ClientError(_)));
+    assert!(err.to_string().contains("boom"));
+    assert!(err.source().is_some());
+
+    // Works with `anyhow` and can be recovered from it.
+    let err = anyhow::Error::from(err);
+    assert!(err.downcast_ref::<SolanaClientExtError>().is_some());
+}
+
+#[test]
+fn test_error_messages() {
+    let err = SolanaClientExtError::CuOutOfRange {
+        actual: 300,
+        min: 100,
+        max: 200,
+    };
+    assert_eq!(
+        err.to_string(),
+        "Compute units out of range: consumed 300 CUs, expected 100..=200"
+    );
+    assert_eq!(
+        SolanaClientExtError::RpcError("timeout".to_string()).to_string(),
+        "RPC error: timeout"
+    );
+    assert_eq!(
+        SolanaClientExtError::FeeCapExceeded {
+            base_fee: 5_000,
+            max_fee: 4_000,
+        }
+        .to_string(),
+        "Fee cap exceeded: base fee of 5000 lamports is above the 4000 lamport cap"
+    );
+    let pubkey = Pubkey::new_unique();
+    assert_eq!(
+        SolanaClientExtError::MissingSigner { pubkey }.to_string(),
+        format!("Missing signer: {} must sign the transaction", pubkey)
+    );
+    assert_eq!(
+        SolanaClientExtError::AccountNotFound { pubkey }.to_string(),
+        format!("Account not found: {}", pubkey)
+    );
+    assert_eq!(
+        SolanaClientExtError::SimulationFailed("custom program error: 0x1".to_string()).to_string(),
+        "Simulation failed: custom program error: 0x1"
+    );
+    assert_eq!(
+        SolanaClientExtError::InvalidHeapSize { bytes: 1_000 }.to_string(),
+        "Invalid heap size: 1000 bytes isn't a multiple of 1024 between 32 KiB and 256 KiB"
+    );
+    assert_eq!(
+        SolanaClientExtError::FeeModelError("oracle unavailable".to_string()).to_string(),
+        "Fee model error: oracle unavailable"
+    );
+}
+
+#[test]
+fn test_priority_fee_fetch_error_and_fallback() {
+    // Nothing listens on this port, so every fee request fails.
+    let rpc_client = RpcClient::new("http://127.0.0.1:1".to_string());
+
+    let err = rpc_client
+        .estimate_priority_fee_for_cu_sync(None, 200_000)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::PriorityFeeFetchError(_))
+    ));
+
+    let fee = rpc_client.estimate_priority_fee_for_cu_sync_with_fallback(None, 200_000, 5_000);
+    assert_eq!(fee.fee_per_cu_micro_lamports, 5_000);
+    assert_eq!(fee.total_fee_lamports, 1_000);
+}
@@ -0,0 +1,468 @@
+use serde_json::{json, Value};
+use solana_client::rpc_request::RpcRequest;
+use solana_client_ext::{
+    bucket_fees, compute_priority_fee_from_tx, estimate_base_fee, estimate_write_lock_fee,
+    format_lamports, min_price_to_outbid, parse_compute_budget, transaction_metrics,
+    ComputeBudgetSettings, CuMarginConfig, EstimatedPrioritizationFee, FeeModel, RpcClientExt,
+    RpcClientExtAsync, SolanaClientExtError,
+};
+use solana_sdk::{
+    account::AccountSharedData,
+    compute_budget::ComputeBudgetInstruction,
+    fee::FeeStructure,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+
+mod common;
+use common::{FixedFeeModel, MockRpc};
+
+#[test]
+fn test_write_lock_fee_counts_writable_non_signers() {
+    let payer = Pubkey::new_unique();
+    let ix = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[ix], Some(&payer)));
+
+    let fee_structure = FeeStructure {
+        lamports_per_write_lock: 10,
+        ..FeeStructure::default()
+    };
+
+    // Only the recipient is writable without signing; the payer and the
+    // System Program are excluded.
+    assert_eq!(estimate_write_lock_fee(&tx, &fee_structure), 10);
+    assert_eq!(estimate_write_lock_fee(&tx, &FeeStructure::default()), 0);
+}
+
+#[test]
+fn test_write_lock_fee_skips_read_only_accounts() {
+    let payer = Pubkey::new_unique();
+    let co_signer = Pubkey::new_unique();
+    let ix = Instruction::new_with_bytes(
+        Pubkey::new_unique(),
+        &[],
+        vec![
+            AccountMeta::new(co_signer, true),
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new_readonly(Pubkey::new_unique(), false),
+        ],
+    );
+    let tx = Transaction::new_unsigned(Message::new(&[ix], Some(&payer)));
+    let fee_structure = FeeStructure {
+        lamports_per_write_lock: 10,
+        ..FeeStructure::default()
+    };
+
+    // Two writable non-signers; the read-only account, the program and the
+    // writable signers aren't charged.
+    assert_eq!(estimate_write_lock_fee(&tx, &fee_structure), 20);
+    // The base fee is the signature fee plus the write-lock fee.
+    assert_eq!(
+        estimate_base_fee(&tx.message, &fee_structure, None),
+        2 * fee_structure.lamports_per_signature + 20
+    );
+}
+
+#[test]
+fn test_base_fee_signature_override() {
+    let payer = Pubkey::new_unique();
+    let ix = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1_000);
+    let message = Message::new(&[ix], Some(&payer));
+    let fee_structure = FeeStructure::default();
+
+    // Defaults to the header's single required signature.
+    assert_eq!(estimate_base_fee(&message, &fee_structure, None), 5_000);
+    // A relayer adding its own fee payer signature.
+    assert_eq!(estimate_base_fee(&message, &fee_structure, Some(2)), 10_000);
+}
+
+#[test]
+fn test_bucket_fees() {
+    assert!(bucket_fees(&[], 4).is_empty());
+    assert!(bucket_fees(&[1, 2, 3], 0).is_empty());
+
+    assert_eq!(bucket_fees(&[0, 10, 20, 30, 40], 2), vec![(0, 3), (21, 2)]);
+    // A single distinct value collapses into one bucket.
+    assert_eq!(bucket_fees(&[7, 7, 7], 5), vec![(7, 3)]);
+}
+
+#[test]
+fn test_estimated_fee_to_instructions() {
+    let fee = EstimatedPrioritizationFee {
+        fee_per_cu_micro_lamports: 5_000,
+        total_fee_lamports: 1,
+    };
+    let [limit_ix, price_ix] = fee.to_instructions(200);
+    assert_eq!(
+        limit_ix,
+        ComputeBudgetInstruction::set_compute_unit_limit(200)
+    );
+    assert_eq!(
+        price_ix,
+        ComputeBudgetInstruction::set_compute_unit_price(5_000)
+    );
+}
+
+#[test]
+fn test_estimated_fee_from_price_rounds_up() {
+    let fee = EstimatedPrioritizationFee::from_price(5_000, 200_000);
+    assert_eq!(fee.fee_per_cu_micro_lamports, 5_000);
+    assert_eq!(fee.total_fee_lamports, 1_000);
+
+    // 150 CUs at 1_000 micro-lamports is 0.15 lamports, charged as 1.
+    assert_eq!(
+        EstimatedPrioritizationFee::from_price(1_000, 150).total_fee_lamports,
+        1
+    );
+    assert_eq!(
+        EstimatedPrioritizationFee::from_price(0, 150).total_fee_lamports,
+        0
+    );
+}
+
+#[test]
+fn test_fee_sol_formatting() {
+    let fee = EstimatedPrioritizationFee {
+        fee_per_cu_micro_lamports: 0,
+        total_fee_lamports: 12_000,
+    };
+    assert_eq!(fee.total_fee_sol(), 0.000012);
+    assert_eq!(fee.format_fee(), "0.000012 SOL (12000 lamports)");
+
+    assert_eq!(format_lamports(0), "0 SOL (0 lamports)");
+    assert_eq!(
+        format_lamports(1_500_000_000),
+        "1.5 SOL (1500000000 lamports)"
+    );
+}
+
+#[test]
+fn test_min_price_to_outbid() {
+    // Target paid 1_000 lamports; at 200_000 CU that's 5_000 micro-lamports/CU.
+    let price = min_price_to_outbid(200_000, 1_000);
+    assert_eq!(price, 5_001);
+    let fee = |price: u64, cu: u64| (price as u128 * cu as u128).div_ceil(1_000_000) as u64;
+    assert!(fee(price, 200_000) > 1_000);
+    assert!(fee(price - 1, 200_000) <= 1_000);
+
+    // A much larger CU still needs a non-zero price.
+    assert_eq!(min_price_to_outbid(10_000_000_000, 0), 1);
+    assert_eq!(min_price_to_outbid(0, 1_000), u64::MAX);
+}
+
+#[test]
+fn test_transaction_metrics() {
+    let payer = Pubkey::new_unique();
+
+    let ix = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[ix], Some(&payer)));
+    let metrics = transaction_metrics(&tx);
+    assert_eq!(metrics.serialized_size, bincode::serialized_size(&tx).unwrap() as usize);
+    assert_eq!(metrics.num_signatures, 1);
+    assert_eq!(metrics.num_accounts, 3);
+    assert_eq!(metrics.num_instructions, 1);
+    assert!(!metrics.exceeds_packet_limit());
+
+    // 40 transfers to distinct recipients push the message well past 1232 bytes.
+    let ixs: Vec<_> = (0..40)
+        .map(|_| system_instruction::transfer(&payer, &Pubkey::new_unique(), 1_000))
+        .collect();
+    let tx = Transaction::new_unsigned(Message::new(&ixs, Some(&payer)));
+    let metrics = transaction_metrics(&tx);
+    assert_eq!(metrics.num_instructions, 40);
+    assert!(metrics.exceeds_packet_limit());
+}
+
+#[test]
+fn test_parse_compute_budget() {
+    let payer = Pubkey::new_unique();
+    let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1_000);
+
+    let tx = Transaction::new_unsigned(Message::new(&[transfer.clone()], Some(&payer)));
+    assert_eq!(parse_compute_budget(&tx), ComputeBudgetSettings::default());
+
+    let ixs = [
+        ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+        ComputeBudgetInstruction::set_compute_unit_price(5_000),
+        ComputeBudgetInstruction::request_heap_frame(64 * 1024),
+        ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(32 * 1024),
+        transfer,
+    ];
+    let tx = Transaction::new_unsigned(Message::new(&ixs, Some(&payer)));
+    assert_eq!(
+        parse_compute_budget(&tx),
+        ComputeBudgetSettings {
+            unit_limit: Some(200_000),
+            unit_price: Some(5_000),
+            heap_size: Some(64 * 1024),
+            loaded_accounts_data_size_limit: Some(32 * 1024),
+        }
+    );
+}
+
+#[test]
+fn test_compute_priority_fee_from_tx() {
+    let payer = Pubkey::new_unique();
+    let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1_000);
+
+    let ixs = [
+        ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+        ComputeBudgetInstruction::set_compute_unit_price(10_000),
+        transfer.clone(),
+    ];
+    let tx = Transaction::new_unsigned(Message::new(&ixs, Some(&payer)));
+    assert_eq!(compute_priority_fee_from_tx(&tx), 3_000);
+
+    // Limits above the runtime's maximum are charged at 1,400,000 CUs.
+    let ixs = [
+        ComputeBudgetInstruction::set_compute_unit_limit(2_000_000),
+        ComputeBudgetInstruction::set_compute_unit_price(10_000),
+        transfer.clone(),
+    ];
+    let tx = Transaction::new_unsigned(Message::new(&ixs, Some(&payer)));
+    assert_eq!(compute_priority_fee_from_tx(&tx), 14_000);
+
+    // Without a limit, the runtime default of 200,000 CUs per instruction
+    // applies; the fee is rounded up.
+    let ixs = [
+        ComputeBudgetInstruction::set_compute_unit_price(3),
+        transfer.clone(),
+    ];
+    let tx = Transaction::new_unsigned(Message::new(&ixs, Some(&payer)));
+    assert_eq!(compute_priority_fee_from_tx(&tx), 1);
+
+    // Without a price there is no priority fee.
+    let tx = Transaction::new_unsigned(Message::new(&[transfer], Some(&payer)));
+    assert_eq!(compute_priority_fee_from_tx(&tx), 0);
+}
+
+#[test]
+fn test_get_fee_for_message() {
+    let payer = Keypair::new();
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let message = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+
+    let rpc_client = MockRpc::new(&payer).into_client();
+    let fee = RpcClientExt::get_fee_for_message(&rpc_client, &message).unwrap();
+    assert_eq!(fee, 5_000);
+
+    // A blockhash the cluster doesn't know is reported clearly.
+    let rpc_client = MockRpc {
+        base_fee: None,
+        ..MockRpc::new(&payer)
+    }
+    .into_client();
+    let err = RpcClientExt::get_fee_for_message(&rpc_client, &message).unwrap_err();
+    match err.downcast_ref::<SolanaClientExtError>() {
+        Some(SolanaClientExtError::BlockhashError(message)) => {
+            assert!(
+                message.contains(&Hash::default().to_string()),
+                "{}",
+                message
+            )
+        }
+        _ => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_simulate_fee_payer_balance() {
+    let payer = Keypair::new();
+    let transfer = |lamports| {
+        let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), lamports);
+        Transaction::new_unsigned(Message::new(&[ix], Some(&payer.pubkey())))
+    };
+    let rpc = MockRpc::new(&payer);
+    let accounts = rpc.accounts.clone();
+    let rpc_client = rpc.into_client();
+
+    // 1 SOL, minus the transfer and the 5,000 lamport signature fee.
+    let balance = rpc_client
+        .simulate_fee_payer_balance(&transfer(1_000_000), &[&payer])
+        .unwrap();
+    assert_eq!(balance, 1_000_000_000 - 1_000_000 - 5_000);
+
+    let missing_signer = rpc_client
+        .simulate_fee_payer_balance(&transfer(1_000_000), &[] as &[&Keypair])
+        .unwrap_err();
+    assert!(matches!(
+        missing_signer.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::MissingSigner { pubkey }) if *pubkey == payer.pubkey()
+    ));
+
+    // A balance that would go negative paying the fee.
+    accounts.insert(
+        payer.pubkey(),
+        AccountSharedData::new(4_000, 0, &system_program::id()),
+    );
+    let err = rpc_client
+        .simulate_fee_payer_balance(&transfer(1_000), &[&payer])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::InsufficientFunds { fee_payer }) if *fee_payer == payer.pubkey()
+    ));
+
+    // A payer that doesn't exist isn't reported as merely underfunded.
+    let unknown = Keypair::new();
+    let ix = system_instruction::transfer(&unknown.pubkey(), &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[ix], Some(&unknown.pubkey())));
+    let err = rpc_client
+        .simulate_fee_payer_balance(&tx, &[&unknown])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::AccountNotFound { pubkey }) if *pubkey == unknown.pubkey()
+    ));
+}
+
+#[tokio::test]
+async fn test_estimate_priority_fees_for_cus() {
+    let rpc = MockRpc {
+        priority_fee: 2_000,
+        ..MockRpc::new(&Keypair::new())
+    };
+    let requests = rpc.requests.clone();
+    let rpc_client = rpc.into_nonblocking_client();
+    let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let estimates = rpc_client
+        .estimate_priority_fees_for_cus(&[
+            (Some(vec![a]), 100_000),
+            (None, 1_000),
+            (Some(vec![a]), 250),
+            (Some(vec![b]), 300_000),
+            (None, 50_000),
+        ])
+        .await
+        .unwrap();
+    let totals: Vec<u64> = estimates
+        .iter()
+        .map(|estimate| estimate.total_fee_lamports)
+        .collect();
+    // 2,000 micro-lamports per CU, rounded up to whole lamports.
+    assert_eq!(totals, vec![200, 2, 1, 600, 100]);
+    assert!(estimates
+        .iter()
+        .all(|estimate| estimate.fee_per_cu_micro_lamports == 2_000));
+
+    // Recent fees are fetched once per distinct account list.
+    let fee_requests: Vec<Value> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(request, _)| *request == RpcRequest::GetRecentPrioritizationFees)
+        .map(|(_, params)| params.clone())
+        .collect();
+    assert_eq!(
+        fee_requests,
+        vec![
+            json!([[a.to_string()]]),
+            json!([[]]),
+            json!([[b.to_string()]])
+        ]
+    );
+}
+
+/// Fee model whose price source is down.
+struct UnavailableFeeModel;
+
+impl FeeModel for UnavailableFeeModel {
+    fn estimate_priority_fee(
+        &self,
+        _accounts: &[Pubkey],
+        _cu: u64,
+    ) -> anyhow::Result<EstimatedPrioritizationFee> {
+        anyhow::bail!("oracle unavailable")
+    }
+}
+
+#[test]
+fn test_estimate_full_cost_fee_model() {
+    let payer = Keypair::new();
+    let rpc_client = MockRpc::new(&payer).into_client();
+    let tx = Transaction::new_unsigned(Message::new(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &Pubkey::new_unique(),
+            1_000,
+        )],
+        Some(&payer.pubkey()),
+    ));
+
+    // 150 CUs at 1,000 micro-lamports each, rounded up to a lamport.
+    let cost = rpc_client.estimate_full_cost(&tx, &FixedFeeModel).unwrap();
+    assert_eq!(cost.base_fee, 5_000);
+    assert_eq!(cost.priority_fee, 1);
+
+    // The fee model's own error comes through, not an RPC fetch error.
+    let err = rpc_client
+        .estimate_full_cost(&tx, &UnavailableFeeModel)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::FeeModelError(message)) if message == "oracle unavailable"
+    ));
+}
+
+#[test]
+fn test_optimize_with_fee_cap_offline() {
+    let payer = Keypair::new();
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&payer.pubkey())));
+    let rpc_client = MockRpc {
+        priority_fee: 1_000_000,
+        ..MockRpc::new(&payer)
+    }
+    .into_client();
+    // The transfer and both Compute Budget instructions cost 150 CUs each.
+    let cu_limit = CuMarginConfig::default().apply(450).recommended;
+
+    // A generous cap leaves the recent price of one lamport per CU alone.
+    let mut uncapped = tx.clone();
+    let fee = rpc_client
+        .optimize_with_fee_cap(&mut uncapped, &[&payer], 1_000_000)
+        .unwrap();
+    assert_eq!(fee.cu, cu_limit);
+    assert_eq!(fee.base_fee_lamports, 5_000);
+    assert_eq!(fee.priority_fee_lamports, cu_limit);
+    assert_eq!(fee.total_lamports, 5_000 + cu_limit);
+    let settings = parse_compute_budget(&uncapped);
+    assert_eq!(settings.unit_limit, Some(cu_limit as u32));
+    assert_eq!(settings.unit_price, Some(1_000_000));
+
+    // One lamport above the signature fee forces the price down.
+    let mut capped = tx.clone();
+    let fee = rpc_client
+        .optimize_with_fee_cap(&mut capped, &[&payer], 5_001)
+        .unwrap();
+    assert!(fee.total_lamports <= 5_001);
+    assert_eq!(
+        compute_priority_fee_from_tx(&capped),
+        fee.priority_fee_lamports
+    );
+    let settings = parse_compute_budget(&capped);
+    assert_eq!(settings.unit_limit, Some(cu_limit as u32));
+    assert!(settings.unit_price.unwrap() < 1_000_000);
+
+    // The signature fee alone is over this cap, so nothing changes.
+    let mut rejected = tx.clone();
+    let err = rpc_client
+        .optimize_with_fee_cap(&mut rejected, &[&payer], 4_999)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::FeeCapExceeded {
+            base_fee: 5_000,
+            max_fee: 4_999
+        })
+    ));
+    assert_eq!(rejected, tx);
+}
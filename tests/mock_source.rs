@@ -0,0 +1,2134 @@
+use agave_feature_set::FeatureSet;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use solana_client_ext::{
+    AccountSource, AnalysisConfig, AnalysisResultDetail, Diagnostic, FailoverAccountSource,
+    MockAccountSource, OversizedAccountPolicy, RollUpAccountLoader, RollUpChannel,
+    RollUpChannelConfig, RpcCall, SolanaClientExtError,
+};
+use solana_compute_budget::compute_budget::ComputeBudget;
+use solana_sdk::{
+    account::{
+        create_account_shared_data_for_test, AccountSharedData, ReadableAccount, WritableAccount,
+    },
+    address_lookup_table::{self, state::AddressLookupTable},
+    bpf_loader,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::Clock,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    message::{v0, AddressLookupTableAccount, VersionedMessage},
+    native_loader,
+    nonce::{
+        state::{Data, DurableNonce, State},
+        versions::Versions,
+    },
+    pubkey::Pubkey,
+    rent::Rent,
+    rent_collector::{RentCollector, RENT_EXEMPT_RENT_EPOCH},
+    signature::Keypair,
+    signer::Signer,
+    stake::{
+        self,
+        state::{Authorized, Lockup, StakeStateV2},
+    },
+    system_instruction, system_program,
+    sysvar::{
+        self,
+        recent_blockhashes::{IterItem, RecentBlockhashes},
+        slot_hashes::SlotHashes,
+    },
+    transaction::{Transaction, TransactionError, VersionedTransaction},
+    vote::{
+        self,
+        state::{VoteState, VoteStateVersions},
+    },
+};
+use solana_svm::transaction_processor::TransactionProcessingConfig;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+mod common;
+use common::{mock_channel, mock_channel_with_loader, seeded_source, transfer_tx, FixedFeeModel};
+
+/// Builds an offline channel that can run the Address Lookup Table program,
+/// with `slot_hashes` as the `SlotHashes` sysvar.
+fn lookup_table_channel(payer: &Keypair, slot_hashes: &SlotHashes) -> RollUpChannel<'static> {
+    let source = seeded_source(payer);
+    source.insert(
+        address_lookup_table::program::id(),
+        native_loader::create_loadable_account_for_test("address_lookup_table_program"),
+    );
+    source.insert(
+        sysvar::rent::id(),
+        create_account_shared_data_for_test(&Rent::default()),
+    );
+    let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source));
+    channel.set_sysvar(slot_hashes);
+    channel
+}
+
+/// Creates a lookup table owned by `payer` for `recent_slot`.
+fn create_lookup_table_tx(payer: &Keypair, recent_slot: u64) -> Transaction {
+    let (ix, _) = address_lookup_table::instruction::create_lookup_table(
+        payer.pubkey(),
+        payer.pubkey(),
+        recent_slot,
+    );
+    Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[payer], Hash::default())
+}
+
+/// Builds a minimal SBF program that exits with the epoch's total stake, read
+/// through the `sol_get_epoch_stake` syscall. A non-zero exit code fails the
+/// instruction with `InstructionError::Custom(code)`.
+fn epoch_stake_program_elf() -> Vec<u8> {
+    // File layout; every section is loaded at its file offset.
+    const TEXT: u64 = 0x40;
+    const DYNSYM: u64 = 0x58;
+    const DYNSTR: u64 = 0x88;
+    const REL_DYN: u64 = 0xa0;
+    const DYNAMIC: u64 = 0xb0;
+    const SHSTRTAB: u64 = 0x100;
+    const SECTION_HEADERS: u64 = 0x138;
+    const SHSTRTAB_NAMES: &[u8] = b"\0.text\0.dynsym\0.dynstr\0.rel.dyn\0.dynamic\0.shstrtab\0";
+
+    let mut elf = Vec::new();
+    // ELF header: 64-bit little-endian eBPF shared object, entry point at the
+    // start of .text, no program headers.
+    elf.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    elf.extend_from_slice(&3u16.to_le_bytes());
+    elf.extend_from_slice(&247u16.to_le_bytes());
+    elf.extend_from_slice(&1u32.to_le_bytes());
+    elf.extend_from_slice(&TEXT.to_le_bytes());
+    elf.extend_from_slice(&0u64.to_le_bytes());
+    elf.extend_from_slice(&SECTION_HEADERS.to_le_bytes());
+    elf.extend_from_slice(&0u32.to_le_bytes());
+    for half in [64u16, 56, 0, 64, 7, 6] {
+        elf.extend_from_slice(&half.to_le_bytes());
+    }
+    // .text: `mov64 r1, 0`, `call sol_get_epoch_stake` (relocated), `exit`.
+    elf.extend_from_slice(&[0xb7, 0x01, 0, 0, 0, 0, 0, 0]);
+    elf.extend_from_slice(&[0x85, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]);
+    elf.extend_from_slice(&[0x95, 0, 0, 0, 0, 0, 0, 0]);
+    // .dynsym: the null symbol, then the undefined syscall.
+    elf.extend_from_slice(&[0; 24]);
+    elf.extend_from_slice(&1u32.to_le_bytes());
+    elf.extend_from_slice(&[0x10, 0, 0, 0]);
+    elf.extend_from_slice(&[0; 16]);
+    // .dynstr
+    elf.extend_from_slice(b"\0sol_get_epoch_stake\0");
+    elf.resize(REL_DYN as usize, 0);
+    // .rel.dyn: an `R_BPF_64_32` relocation of the call against symbol 1.
+    elf.extend_from_slice(&(TEXT + 8).to_le_bytes());
+    elf.extend_from_slice(&((1u64 << 32) | 10).to_le_bytes());
+    // .dynamic: DT_REL, DT_RELSZ, DT_RELENT, DT_SYMTAB, DT_NULL.
+    for (tag, value) in [(17u64, REL_DYN), (18, 16), (19, 16), (6, DYNSYM), (0, 0)] {
+        elf.extend_from_slice(&tag.to_le_bytes());
+        elf.extend_from_slice(&value.to_le_bytes());
+    }
+    elf.extend_from_slice(SHSTRTAB_NAMES);
+    elf.resize(SECTION_HEADERS as usize, 0);
+    // Section headers: name, type, flags, address, size, link and entry size.
+    let sections = [
+        (0u32, 0u32, 0u64, 0u64, 0u64, 0u32, 0u64),
+        (1, 1, 6, TEXT, 24, 0, 0),
+        (7, 11, 2, DYNSYM, 48, 3, 24),
+        (15, 3, 2, DYNSTR, 21, 0, 0),
+        (23, 9, 2, REL_DYN, 16, 2, 16),
+        (32, 6, 3, DYNAMIC, 80, 3, 16),
+        (41, 3, 0, SHSTRTAB, SHSTRTAB_NAMES.len() as u64, 0, 0),
+    ];
+    for (name, kind, flags, address, size, link, entry_size) in sections {
+        elf.extend_from_slice(&name.to_le_bytes());
+        elf.extend_from_slice(&kind.to_le_bytes());
+        elf.extend_from_slice(&flags.to_le_bytes());
+        elf.extend_from_slice(&address.to_le_bytes());
+        elf.extend_from_slice(&address.to_le_bytes());
+        elf.extend_from_slice(&size.to_le_bytes());
+        elf.extend_from_slice(&link.to_le_bytes());
+        elf.extend_from_slice(&0u32.to_le_bytes());
+        elf.extend_from_slice(&8u64.to_le_bytes());
+        elf.extend_from_slice(&entry_size.to_le_bytes());
+    }
+    elf
+}
+
+#[test]
+fn test_mock_source_transfer_offline() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    let results = channel.simulate_transactions_raw(
+        &[
+            transfer_tx(&payer, 10_000),
+            transfer_tx(&payer, 10_000_000_000),
+        ],
+        &AnalysisConfig::default(),
+    );
+
+    assert!(results[0].success, "transfer failed: {}", results[0].result);
+    assert_eq!(results[0].cu, 150);
+    assert_eq!(results[0].invoked_programs, vec![system_program::id()]);
+    // A plain transfer makes no CPI.
+    assert!(results[0].inner_instructions.is_empty());
+    assert_eq!(results[0].cpi_count, 0);
+    // Only the System Program account holds data; the recipient doesn't exist yet.
+    assert_eq!(
+        results[0].loaded_accounts_data_size,
+        "system_program".len() as u64
+    );
+    assert!(!results[1].success, "overdraft should fail");
+    // SystemError::ResultWithNegativeLamports
+    assert_eq!(results[1].custom_error_code, Some(1));
+}
+
+#[test]
+fn test_mock_source_tagging_offline() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer).with_fee_model(FixedFeeModel);
+
+    let config = AnalysisConfig {
+        estimate_compute_units: true,
+        calculate_priority_fee: true,
+        tag: Some("mock".to_string()),
+        ..AnalysisConfig::default()
+    };
+    channel.process_transactions_with_analysis(&[transfer_tx(&payer, 10_000)], &config);
+
+    let tagged = channel.get_tagged_results("mock").expect("tag missing");
+    assert!(tagged.iter().all(|result| result.base_simulation_success));
+    assert!(tagged.iter().any(|result| matches!(
+        &result.details,
+        AnalysisResultDetail::ComputeUnits(cu) if cu.cu_consumed == 150
+    )));
+    assert!(tagged.iter().any(|result| matches!(
+        &result.details,
+        AnalysisResultDetail::PriorityFee(fee) if fee.fee_per_cu_micro_lamports == 1_000
+    )));
+}
+
+#[test]
+fn test_mock_source_fees_only() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    // The invoked program doesn't exist, so loading fails after fee validation.
+    let ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+
+    assert!(!results[0].success);
+    let fees_only = results[0]
+        .fees_only
+        .as_ref()
+        .expect("expected fees-only outcome");
+    assert_eq!(
+        fees_only.load_error,
+        Some(TransactionError::ProgramAccountNotFound)
+    );
+    assert_eq!(fees_only.fee_lamports, 5_000);
+}
+
+#[test]
+fn test_mock_source_rent_collector() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer).with_config(RollUpChannelConfig {
+        rent_collector: Some(RentCollector::default()),
+        ..RollUpChannelConfig::default()
+    });
+
+    // Funding a new account below the rent-exempt minimum is rejected once
+    // rent checks are enabled.
+    let results = channel
+        .simulate_transactions_raw(&[transfer_tx(&payer, 10_000)], &AnalysisConfig::default());
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::InsufficientFundsForRent { account_index: 1 })
+    );
+}
+
+#[test]
+fn test_mock_source_rent_collector_epoch() {
+    // Rent is collected on load only while fee collection isn't disabled.
+    let mut feature_set = FeatureSet::all_enabled();
+    feature_set.deactivate(&agave_feature_set::disable_rent_fees_collection::id());
+    let feature_set = Arc::new(feature_set);
+
+    // A rent-exempt account whose `rent_epoch` is 5 is only marked exempt
+    // once the collector's epoch reaches it, whether the collector is
+    // configured or the default one.
+    for (epoch, rent_collector, expected_rent_epoch) in [
+        (None, Some(RentCollector::default()), 5),
+        (
+            Some(10),
+            Some(RentCollector::default()),
+            RENT_EXEMPT_RENT_EPOCH,
+        ),
+        (Some(10), None, RENT_EXEMPT_RENT_EPOCH),
+    ] {
+        let payer = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let source = seeded_source(&payer);
+        let mut account = AccountSharedData::new(1_000_000_000, 0, &system_program::id());
+        account.set_rent_epoch(5);
+        source.insert(recipient, account);
+        let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source))
+            .with_config(RollUpChannelConfig {
+                feature_set: Some(Arc::clone(&feature_set)),
+                rent_collector,
+                epoch,
+                ..RollUpChannelConfig::default()
+            });
+
+        let ix = system_instruction::transfer(&payer.pubkey(), &recipient, 10_000);
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+        assert!(results[0].success, "transfer failed: {}", results[0].result);
+        let (_, account) = results[0]
+            .post_accounts
+            .iter()
+            .find(|(pubkey, _)| *pubkey == recipient)
+            .expect("recipient should be loaded");
+        assert_eq!(account.rent_epoch(), expected_rent_epoch);
+    }
+}
+
+#[test]
+fn test_mock_source_clock_epoch() {
+    for (epoch, expected_epoch) in [(None, 2), (Some(7), 7)] {
+        let payer = Keypair::new();
+        let channel = mock_channel(&payer).with_config(RollUpChannelConfig {
+            epoch,
+            ..RollUpChannelConfig::default()
+        });
+        channel.set_sysvar(&Clock {
+            slot: 43,
+            epoch: 2,
+            ..Clock::default()
+        });
+
+        // The System Program ignores the extra `Clock` account, so the
+        // transaction loads it without changing it.
+        let mut ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 10_000);
+        ix.accounts
+            .push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+        assert!(results[0].success, "transfer failed: {}", results[0].result);
+        let (_, account) = results[0]
+            .post_accounts
+            .iter()
+            .find(|(pubkey, _)| *pubkey == sysvar::clock::id())
+            .expect("clock should be loaded");
+        let clock: Clock = bincode::deserialize(account.data()).unwrap();
+        assert_eq!(clock.slot, 43);
+        assert_eq!(clock.epoch, expected_epoch);
+    }
+}
+
+#[test]
+fn test_mock_source_auto_budget() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer).with_config(RollUpChannelConfig {
+        compute_budget: ComputeBudget {
+            compute_unit_limit: 100,
+            ..ComputeBudget::default()
+        },
+        ..RollUpChannelConfig::default()
+    });
+
+    // 100 CUs can't cover a transfer; the limit is doubled until it fits.
+    let (result, budget) = channel.simulate_with_auto_budget(&transfer_tx(&payer, 10_000));
+    assert!(result.success, "transfer failed: {}", result.result);
+    assert!(!result.exceeded_compute_budget);
+    assert!(budget.compute_unit_limit > 100);
+    assert!(result.cu <= budget.compute_unit_limit);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_mock_source_durable_nonce() {
+    let payer = Keypair::new();
+    let source = seeded_source(&payer);
+
+    let nonce_address = Pubkey::new_unique();
+    let durable_nonce = DurableNonce::from_blockhash(&Hash::new_unique());
+    let nonce_state = Versions::new(State::Initialized(Data::new(
+        payer.pubkey(),
+        durable_nonce,
+        5_000,
+    )));
+    source.insert(
+        nonce_address,
+        AccountSharedData::new_data(1_500_000, &nonce_state, &system_program::id()).unwrap(),
+    );
+    let recent_blockhashes =
+        RecentBlockhashes::from_iter([IterItem(0, &Hash::new_unique(), 5_000)]);
+    source.insert(
+        sysvar::recent_blockhashes::id(),
+        create_account_shared_data_for_test(&recent_blockhashes),
+    );
+
+    let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source));
+
+    let instructions = [
+        system_instruction::advance_nonce_account(&nonce_address, &payer.pubkey()),
+        system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 10_000),
+    ];
+    let nonce_tx = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer],
+        *durable_nonce.as_hash(),
+    );
+    let stale_tx = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::new_unique(),
+    );
+
+    let results =
+        channel.simulate_transactions_raw(&[nonce_tx, stale_tx], &AnalysisConfig::default());
+    assert!(results[0].success, "nonce tx failed: {}", results[0].result);
+    assert_eq!(results[1].error, Some(TransactionError::BlockhashNotFound));
+
+    // The nonce advances to the simulation's blockhash.
+    let advanced = results[0].advanced_nonce.as_ref().unwrap();
+    assert_eq!(advanced.authority, payer.pubkey());
+    assert_ne!(advanced.durable_nonce, durable_nonce);
+    assert_eq!(
+        advanced.durable_nonce,
+        DurableNonce::from_blockhash(&Hash::default())
+    );
+}
+
+#[test]
+fn test_mock_source_simulate_encoded() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    let wire =
+        bincode::serialize(&VersionedTransaction::from(transfer_tx(&payer, 10_000))).unwrap();
+    let encoded = vec![
+        BASE64_STANDARD.encode(&wire),
+        bs58::encode(&wire).into_string(),
+    ];
+    let results = channel
+        .simulate_encoded(&encoded, &AnalysisConfig::default())
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.success));
+
+    let err = channel
+        .simulate_encoded(
+            &["not a transaction".to_string()],
+            &AnalysisConfig::default(),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        SolanaClientExtError::TransactionDecodeError(_)
+    ));
+}
+
+#[test]
+fn test_mock_source_account_data_sizes() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    let new_account = Keypair::new();
+    let rent = Rent::default();
+    let ix = system_instruction::create_account(
+        &payer.pubkey(),
+        &new_account.pubkey(),
+        rent.minimum_balance(100),
+        100,
+        &system_program::id(),
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer, &new_account],
+        Hash::default(),
+    );
+    let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+    assert!(results[0].success, "create failed: {}", results[0].result);
+
+    let change = results[0]
+        .account_data_sizes
+        .iter()
+        .find(|change| change.pubkey == new_account.pubkey())
+        .expect("new account should be tracked");
+    assert_eq!(change.data_len_before, 0);
+    assert_eq!(change.data_len_after, 100);
+    assert_eq!(change.growth(), 100);
+    assert_eq!(
+        change.additional_rent_lamports(&rent),
+        rent.minimum_balance(100) - rent.minimum_balance(0)
+    );
+    // Read-only accounts such as the System Program aren't tracked.
+    assert!(results[0]
+        .account_data_sizes
+        .iter()
+        .all(|change| change.pubkey != system_program::id()));
+}
+
+#[test]
+fn test_mock_source_processing_config() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer).with_processing_config(TransactionProcessingConfig {
+        log_messages_bytes_limit: Some(10),
+        ..RollUpChannel::default_processing_config()
+    });
+
+    let results = channel
+        .simulate_transactions_raw(&[transfer_tx(&payer, 10_000)], &AnalysisConfig::default());
+    assert!(results[0].success, "transfer failed: {}", results[0].result);
+    assert!(results[0].logs.iter().any(|line| line == "Log truncated"));
+}
+
+#[test]
+fn test_mock_source_batch_fees() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    let estimate = channel
+        .estimate_batch_fees(
+            &[transfer_tx(&payer, 10_000), transfer_tx(&payer, 20_000)],
+            &FixedFeeModel,
+        )
+        .unwrap();
+    assert_eq!(estimate.per_tx.len(), 2);
+    for fee in &estimate.per_tx {
+        assert_eq!(fee.cu, 150);
+        assert_eq!(fee.base_fee_lamports, 5_000);
+        assert_eq!(
+            fee.total_lamports,
+            fee.base_fee_lamports + fee.priority_fee_lamports
+        );
+    }
+    assert_eq!(
+        estimate.total_lamports,
+        estimate
+            .per_tx
+            .iter()
+            .map(|fee| fee.total_lamports)
+            .sum::<u64>()
+    );
+}
+
+#[test]
+fn test_mock_source_simulate_at_slots() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    let results = channel.simulate_at_slots(&transfer_tx(&payer, 10_000), &[1, 1_000, 250_000]);
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|result| result.success));
+}
+
+#[test]
+fn test_mock_source_concurrent_tagging() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+    let config = AnalysisConfig {
+        estimate_compute_units: true,
+        calculate_priority_fee: false,
+        tag: Some("concurrent".to_string()),
+        ..AnalysisConfig::default()
+    };
+
+    std::thread::scope(|scope| {
+        for lamports in [10_000, 20_000, 30_000, 40_000] {
+            let (channel, config, payer) = (&channel, &config, &payer);
+            scope.spawn(move || {
+                channel.process_transactions_with_analysis(&[transfer_tx(payer, lamports)], config);
+            });
+        }
+    });
+
+    let tagged = channel
+        .get_tagged_results("concurrent")
+        .expect("tag missing");
+    assert_eq!(tagged.len(), 4);
+    assert!(tagged.iter().all(|result| result.base_simulation_success));
+}
+
+#[test]
+fn test_mock_source_simulate_transactions_iter() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer).with_config(RollUpChannelConfig {
+        iter_chunk_size: Some(2),
+        ..RollUpChannelConfig::default()
+    });
+
+    let pulled = Cell::new(0);
+    let transactions = (1..=5).map(|i| {
+        pulled.set(pulled.get() + 1);
+        transfer_tx(&payer, i * 10_000)
+    });
+    let mut results = channel.simulate_transactions_iter(transactions, AnalysisConfig::default());
+    // Nothing is pulled until the first result is asked for...
+    assert_eq!(pulled.get(), 0);
+    // ...and then only the first chunk.
+    assert!(results.next().unwrap().success);
+    assert_eq!(pulled.get(), 2);
+    assert!(results.next().unwrap().success);
+    assert_eq!(pulled.get(), 2);
+    assert!(results.next().unwrap().success);
+    assert_eq!(pulled.get(), 4);
+    assert_eq!(results.count(), 2);
+    assert_eq!(pulled.get(), 5);
+}
+
+#[test]
+fn test_mock_source_upgradeable_loader() {
+    let payer = Keypair::new();
+    let buffer = Keypair::new();
+    let source = seeded_source(&payer);
+    source.insert(
+        bpf_loader_upgradeable::id(),
+        native_loader::create_loadable_account_for_test("solana_bpf_loader_upgradeable_program"),
+    );
+    let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source));
+
+    // Creating a program buffer runs the upgradeable loader itself.
+    let instructions = bpf_loader_upgradeable::create_buffer(
+        &payer.pubkey(),
+        &buffer.pubkey(),
+        &payer.pubkey(),
+        Rent::default().minimum_balance(UpgradeableLoaderState::size_of_buffer(64)),
+        64,
+    )
+    .unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer, &buffer],
+        Hash::default(),
+    );
+    let result = &channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default())[0];
+    assert!(result.success, "{}", result.result);
+
+    let (_, account) = result
+        .post_accounts
+        .iter()
+        .find(|(pubkey, _)| *pubkey == buffer.pubkey())
+        .unwrap();
+    assert_eq!(
+        bincode::deserialize::<UpgradeableLoaderState>(account.data()).unwrap(),
+        UpgradeableLoaderState::Buffer {
+            authority_address: Some(payer.pubkey()),
+        }
+    );
+}
+
+#[test]
+fn test_mock_source_reuse_processor() {
+    let payer = Keypair::new();
+    let source = seeded_source(&payer);
+    source.insert(
+        bpf_loader_upgradeable::id(),
+        native_loader::create_loadable_account_for_test("solana_bpf_loader_upgradeable_program"),
+    );
+    let program_id = Pubkey::new_unique();
+    let programdata_address = Pubkey::new_unique();
+    let mut program_account = AccountSharedData::new_data(
+        1_000_000,
+        &UpgradeableLoaderState::Program {
+            programdata_address,
+        },
+        &bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    program_account.set_executable(true);
+    source.insert(program_id, program_account);
+    let mut programdata = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: None,
+    })
+    .unwrap();
+    programdata.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+    programdata.extend_from_slice(&epoch_stake_program_elf());
+    let mut programdata_account =
+        AccountSharedData::new(1_000_000, programdata.len(), &bpf_loader_upgradeable::id());
+    programdata_account.set_data_from_slice(&programdata);
+    source.insert(programdata_address, programdata_account);
+
+    let channel =
+        mock_channel_with_loader(RollUpAccountLoader::with_source(source).with_rpc_call_log())
+            .with_config(RollUpChannelConfig {
+                reuse_processor: true,
+                ..RollUpChannelConfig::default()
+            });
+    let tx = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bytes(program_id, &[], vec![])],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    // Only the program cache reads the ProgramData account, so the loader is
+    // asked for it each time the program is loaded. It's invalidated before
+    // every simulation, so cached account data can't hide a load.
+    let programdata_loads = |channel: &RollUpChannel<'static>| {
+        channel.account_loader().invalidate(&programdata_address);
+        channel.account_loader().clear_rpc_call_log();
+        let results = channel.simulate_transactions_raw(&[tx.clone()], &AnalysisConfig::default());
+        assert!(results[0].success, "{}", results[0].result);
+        channel
+            .rpc_call_log()
+            .iter()
+            .filter(|call| call.args.contains(&programdata_address))
+            .count()
+    };
+
+    // The first call builds the processor and loads the program; the next
+    // ones run it from the kept processor's cache.
+    assert_eq!(programdata_loads(&channel), 1);
+    assert_eq!(programdata_loads(&channel), 0);
+    assert_eq!(programdata_loads(&channel), 0);
+
+    // A reset processor loads the program again, once.
+    channel.reset_processor();
+    assert_eq!(programdata_loads(&channel), 1);
+    assert_eq!(programdata_loads(&channel), 0);
+
+    // Without reuse, every call loads it.
+    let channel = channel.with_config(RollUpChannelConfig::default());
+    assert_eq!(programdata_loads(&channel), 1);
+    assert_eq!(programdata_loads(&channel), 1);
+}
+
+#[test]
+fn test_mock_source_fail_on_log_patterns() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+    let tx = transfer_tx(&payer, 10_000);
+
+    let config = AnalysisConfig {
+        estimate_compute_units: true,
+        fail_on_log_patterns: vec!["error".to_string()],
+        ..AnalysisConfig::default()
+    };
+    let results = channel.process_transactions_with_analysis(std::slice::from_ref(&tx), &config);
+    assert!(results[0].base_simulation_success);
+
+    // The System Program logs "Program 1111... success" for the transfer.
+    let config = AnalysisConfig {
+        estimate_compute_units: true,
+        fail_on_log_patterns: vec!["success".to_string()],
+        ..AnalysisConfig::default()
+    };
+    let results = channel.process_transactions_with_analysis(&[tx], &config);
+    assert!(!results[0].base_simulation_success);
+    let message = results[0].top_level_error_message.as_deref().unwrap();
+    assert!(
+        message.contains("matching failure pattern \"success\""),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn test_mock_source_stake_account_creation() {
+    // The source has neither the Stake program nor the `Rent` sysvar it reads.
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    let stake_account = Keypair::new();
+    let ixs = stake::instruction::create_account(
+        &payer.pubkey(),
+        &stake_account.pubkey(),
+        &Authorized::auto(&payer.pubkey()),
+        &Lockup::default(),
+        10_000_000,
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &ixs,
+        Some(&payer.pubkey()),
+        &[&payer, &stake_account],
+        Hash::default(),
+    );
+
+    let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+    assert!(
+        results[0].success,
+        "stake account creation failed: {}",
+        results[0].result
+    );
+    assert!(results[0].invoked_programs.contains(&stake::program::id()));
+    // 150 CUs to create the account and 750 to initialize it.
+    assert_eq!(results[0].cu, 900);
+}
+
+#[test]
+fn test_mock_source_stake_lifecycle() {
+    // Delegations need at least 1 SOL with all features enabled.
+    let payer = Keypair::new();
+    let source = seeded_source(&payer);
+    source.insert(
+        payer.pubkey(),
+        AccountSharedData::new(10_000_000_000, 0, &system_program::id()),
+    );
+    let vote_account = Pubkey::new_unique();
+    source.insert(
+        vote_account,
+        AccountSharedData::new_data_with_space(
+            1_000_000_000,
+            &VoteStateVersions::new_current(VoteState::default()),
+            VoteState::size_of(),
+            &vote::program::id(),
+        )
+        .unwrap(),
+    );
+    let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source));
+    let stake_account = Keypair::new();
+
+    // Simulates `ixs` and keeps the stake account they leave behind for the
+    // next step. Returns the CUs consumed and the stake account.
+    let run = |ixs: &[Instruction], signers: &[&Keypair]| {
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&payer.pubkey()),
+            signers,
+            Hash::default(),
+        );
+        let mut results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+        let result = results.remove(0);
+        assert!(result.success, "{}", result.result);
+        let (_, account) = result
+            .post_accounts
+            .iter()
+            .find(|(pubkey, _)| *pubkey == stake_account.pubkey())
+            .unwrap();
+        channel
+            .account_loader()
+            .insert_account(stake_account.pubkey(), account.clone());
+        (result.cu, account.clone())
+    };
+    let stake_state = |account: &AccountSharedData| -> StakeStateV2 {
+        bincode::deserialize(account.data()).unwrap()
+    };
+
+    run(
+        &stake::instruction::create_account(
+            &payer.pubkey(),
+            &stake_account.pubkey(),
+            &Authorized::auto(&payer.pubkey()),
+            &Lockup::default(),
+            2_000_000_000,
+        ),
+        &[&payer, &stake_account],
+    );
+
+    // Each step is a single Stake program instruction, at 750 CUs.
+    let (cu, account) = run(
+        &[stake::instruction::delegate_stake(
+            &stake_account.pubkey(),
+            &payer.pubkey(),
+            &vote_account,
+        )],
+        &[&payer],
+    );
+    assert_eq!(cu, 750);
+    let delegation = stake_state(&account).delegation().unwrap();
+    assert_eq!(delegation.voter_pubkey, vote_account);
+    assert_eq!(delegation.activation_epoch, 1);
+
+    let (cu, account) = run(
+        &[stake::instruction::deactivate_stake(
+            &stake_account.pubkey(),
+            &payer.pubkey(),
+        )],
+        &[&payer],
+    );
+    assert_eq!(cu, 750);
+    assert_eq!(
+        stake_state(&account)
+            .delegation()
+            .unwrap()
+            .deactivation_epoch,
+        1
+    );
+
+    // Deactivated in the epoch it was activated in, so none of it is staked.
+    let (cu, account) = run(
+        &[stake::instruction::withdraw(
+            &stake_account.pubkey(),
+            &payer.pubkey(),
+            &payer.pubkey(),
+            1_000_000_000,
+            None,
+        )],
+        &[&payer],
+    );
+    assert_eq!(cu, 750);
+    assert_eq!(account.lamports(), 1_000_000_000);
+}
+
+#[test]
+fn test_mock_source_detects_account_creation() {
+    let payer = Keypair::new();
+    let existing = Pubkey::new_unique();
+    let source = seeded_source(&payer);
+    source.insert(
+        existing,
+        AccountSharedData::new(1_000_000, 0, &system_program::id()),
+    );
+    let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source));
+
+    let new_recipient = Pubkey::new_unique();
+    let ix = system_instruction::transfer(&payer.pubkey(), &new_recipient, 1_000_000);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    assert_eq!(channel.detects_account_creation(&tx), vec![new_recipient]);
+
+    let ix = system_instruction::transfer(&payer.pubkey(), &existing, 1_000_000);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    assert!(channel.detects_account_creation(&tx).is_empty());
+}
+
+#[test]
+fn test_mock_source_created_accounts_in_batch() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+    let new_recipient = Pubkey::new_unique();
+    let transfer = |lamports| {
+        Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &new_recipient,
+                lamports,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        )
+    };
+
+    // The second transfer funds the account the first one created.
+    let results = channel.simulate_transactions_raw(
+        &[transfer(1_000_000), transfer(2_000_000)],
+        &AnalysisConfig::default(),
+    );
+    assert!(results.iter().all(|result| result.success));
+    assert_eq!(results[0].created_accounts, vec![new_recipient]);
+    assert!(results[1].created_accounts.is_empty());
+}
+
+/// Account source recording the size of every batch request it serves.
+#[derive(Default)]
+struct BatchRecordingSource {
+    inner: MockAccountSource,
+    batch_sizes: Mutex<Vec<usize>>,
+}
+
+impl AccountSource for BatchRecordingSource {
+    fn get_account(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<Option<AccountSharedData>, SolanaClientExtError> {
+        self.inner.get_account(pubkey)
+    }
+
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<AccountSharedData>>, SolanaClientExtError> {
+        self.batch_sizes.lock().unwrap().push(pubkeys.len());
+        self.inner.get_multiple_accounts(pubkeys)
+    }
+}
+
+#[test]
+fn test_mock_source_prefetch_batches() {
+    let source = Arc::new(BatchRecordingSource::default());
+    let pubkeys: Vec<Pubkey> = (0..250).map(|_| Pubkey::new_unique()).collect();
+    for pubkey in &pubkeys {
+        source.inner.insert(
+            *pubkey,
+            AccountSharedData::new(1_000, 0, &system_program::id()),
+        );
+    }
+
+    let loader = RollUpAccountLoader::with_source(source.clone()).with_prefetch_concurrency(2);
+    loader.prefetch(&pubkeys).unwrap();
+
+    let mut batch_sizes = source.batch_sizes.lock().unwrap().clone();
+    batch_sizes.sort_unstable();
+    assert_eq!(batch_sizes, vec![50, 100, 100]);
+
+    // Everything is cached now, so loading doesn't reach the source.
+    source.inner.remove(&pubkeys[0]);
+    assert!(loader.load_account(&pubkeys[0]).unwrap().is_some());
+    loader.prefetch(&pubkeys).unwrap();
+    assert_eq!(source.batch_sizes.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn test_mock_source_custom_feature_set() {
+    let payer = Keypair::new();
+    let mut feature_set = FeatureSet::all_enabled();
+    feature_set.deactivate(&agave_feature_set::enable_transaction_loading_failure_fees::id());
+    let channel = mock_channel(&payer).with_config(RollUpChannelConfig {
+        feature_set: Some(Arc::new(feature_set)),
+        ..RollUpChannelConfig::default()
+    });
+
+    let results = channel
+        .simulate_transactions_raw(&[transfer_tx(&payer, 10_000)], &AnalysisConfig::default());
+    assert!(results[0].success, "transfer failed: {}", results[0].result);
+    assert_eq!(results[0].cu, 150);
+
+    // Without the feature, a transaction that fails to load isn't charged fees.
+    let ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+    assert!(!results[0].success);
+    assert!(results[0].fees_only.is_none());
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::ProgramAccountNotFound)
+    );
+}
+
+#[test]
+fn test_mock_source_versioned_lookup_tables() {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let table = AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: vec![recipient],
+    };
+    let channel =
+        mock_channel(&payer).with_lookup_tables(HashMap::from([(table.key, table.clone())]));
+
+    let ix = system_instruction::transfer(&payer.pubkey(), &recipient, 10_000);
+    let message =
+        v0::Message::try_compile(&payer.pubkey(), &[ix.clone()], &[table], Hash::default())
+            .unwrap();
+    assert_eq!(message.address_table_lookups.len(), 1);
+    let versioned =
+        VersionedTransaction::try_new(VersionedMessage::V0(message), &[&payer]).unwrap();
+
+    // Same transfer through a table the channel doesn't know about.
+    let unknown_table = AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: vec![recipient],
+    };
+    let message =
+        v0::Message::try_compile(&payer.pubkey(), &[ix], &[unknown_table], Hash::default())
+            .unwrap();
+    let unresolved =
+        VersionedTransaction::try_new(VersionedMessage::V0(message), &[&payer]).unwrap();
+
+    let results = channel
+        .simulate_versioned_transactions(&[unresolved, versioned], &AnalysisConfig::default());
+    assert!(!results[0].success);
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::AddressLookupTableNotFound)
+    );
+    assert!(results[1].success, "transfer failed: {}", results[1].result);
+    assert_eq!(results[1].cu, 150);
+}
+
+#[test]
+fn test_mock_source_max_log_bytes() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+    let results = channel
+        .simulate_transactions_raw(&[transfer_tx(&payer, 10_000)], &AnalysisConfig::default());
+    assert!(!results[0].logs_truncated);
+
+    let channel = channel.with_config(RollUpChannelConfig {
+        max_log_bytes: Some(10),
+        ..RollUpChannelConfig::default()
+    });
+    let results = channel
+        .simulate_transactions_raw(&[transfer_tx(&payer, 10_000)], &AnalysisConfig::default());
+    assert!(results[0].success, "transfer failed: {}", results[0].result);
+    assert!(results[0].logs_truncated);
+    assert_eq!(results[0].logs, vec!["Log truncated".to_string()]);
+
+    // Unsetting the limit restores the processing config's.
+    let channel = channel.with_config(RollUpChannelConfig::default());
+    let results = channel
+        .simulate_transactions_raw(&[transfer_tx(&payer, 10_000)], &AnalysisConfig::default());
+    assert!(!results[0].logs_truncated);
+
+    let channel = channel
+        .with_processing_config(TransactionProcessingConfig {
+            log_messages_bytes_limit: Some(10),
+            ..RollUpChannel::default_processing_config()
+        })
+        .with_config(RollUpChannelConfig {
+            max_log_bytes: Some(10_000),
+            ..RollUpChannelConfig::default()
+        });
+    let results = channel
+        .simulate_transactions_raw(&[transfer_tx(&payer, 10_000)], &AnalysisConfig::default());
+    assert!(!results[0].logs_truncated);
+    let channel = channel.with_config(RollUpChannelConfig::default());
+    let results = channel
+        .simulate_transactions_raw(&[transfer_tx(&payer, 10_000)], &AnalysisConfig::default());
+    assert!(results[0].logs_truncated);
+}
+
+#[test]
+fn test_mock_source_resimulate_failures() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+    let transactions = [
+        transfer_tx(&payer, 10_000),
+        transfer_tx(&payer, 10_000_000_000),
+    ];
+    let prior = channel.simulate_transactions_raw(&transactions, &AnalysisConfig::default());
+    assert!(prior[0].success);
+    assert!(!prior[1].success);
+
+    // Fund the payer enough for the overdraft, then retry only the failure.
+    channel.account_loader().insert_account(
+        payer.pubkey(),
+        AccountSharedData::new(20_000_000_000, 0, &system_program::id()),
+    );
+    let results = channel.resimulate_failures(&transactions, &prior, &AnalysisConfig::default());
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].post_accounts, prior[0].post_accounts);
+    assert!(results[1].success, "retry failed: {}", results[1].result);
+}
+
+#[test]
+fn test_mock_source_per_tx_tags() {
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let source = seeded_source(&alice);
+    source.insert(
+        bob.pubkey(),
+        AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+    );
+    let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source));
+
+    let config = AnalysisConfig {
+        estimate_compute_units: true,
+        tag: Some("default".to_string()),
+        ..AnalysisConfig::default()
+    };
+    let results = channel.process_with_per_tx_tags(
+        &[
+            (transfer_tx(&alice, 10_000), Some("alice".to_string())),
+            (transfer_tx(&bob, 10_000), Some("bob".to_string())),
+            (transfer_tx(&bob, 20_000), None),
+        ],
+        &config,
+    );
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(channel.get_tagged_results("alice").unwrap().len(), 1);
+    assert_eq!(channel.get_tagged_results("bob").unwrap().len(), 1);
+    assert_eq!(channel.get_tagged_results("default").unwrap().len(), 1);
+}
+
+#[test]
+fn test_mock_source_all_tagged_results() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+    let tagged = |tag: &str| AnalysisConfig {
+        estimate_compute_units: true,
+        tag: Some(tag.to_string()),
+        ..AnalysisConfig::default()
+    };
+
+    channel.process_transactions_with_analysis(&[transfer_tx(&payer, 10_000)], &tagged("first"));
+    let snapshot = channel.tagged_results_snapshot();
+    {
+        let all = channel.all_tagged_results();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all["first"].len(), snapshot["first"].len());
+    }
+
+    // The snapshot doesn't see later results; the store does.
+    channel.process_transactions_with_analysis(&[transfer_tx(&payer, 20_000)], &tagged("second"));
+    assert_eq!(snapshot.len(), 1);
+    let all = channel.all_tagged_results();
+    assert_eq!(all.len(), 2);
+    assert_eq!(all["second"].len(), 1);
+    drop(all);
+
+    let owned = channel.into_tagged_results();
+    assert_eq!(owned.len(), 2);
+}
+
+#[test]
+fn test_mock_source_lru_cache() {
+    let source = Arc::new(MockAccountSource::new());
+    let keys: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    for key in &keys {
+        source.insert(*key, AccountSharedData::new(1, 0, &system_program::id()));
+    }
+    let pinned = Pubkey::new_unique();
+    let loader = RollUpAccountLoader::with_source(Arc::clone(&source)).with_max_cache_entries(2);
+    loader.insert_account(pinned, AccountSharedData::new(1, 0, &system_program::id()));
+
+    loader.load_account(&keys[0]).unwrap();
+    loader.load_account(&keys[1]).unwrap();
+    // Touch the first account, so the second is now the least recently used.
+    loader.load_account(&keys[0]).unwrap();
+    loader.load_account(&keys[2]).unwrap();
+
+    // Only cached accounts survive removal from the source.
+    for key in &keys {
+        source.remove(key);
+    }
+    assert!(loader.load_account(&keys[0]).unwrap().is_some());
+    assert!(loader.load_account(&keys[2]).unwrap().is_some());
+    assert!(loader.load_account(&keys[1]).unwrap().is_none());
+    // Inserted accounts don't count towards the limit.
+    assert!(loader.load_account(&pinned).unwrap().is_some());
+}
+
+#[test]
+fn test_mock_source_detects_account_closure() {
+    let payer = Keypair::new();
+    let owned = Keypair::new();
+    let source = seeded_source(&payer);
+    source.insert(
+        owned.pubkey(),
+        AccountSharedData::new(1_000_000, 0, &system_program::id()),
+    );
+    let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source));
+
+    // Draining every lamport closes the account.
+    let ix = system_instruction::transfer(&owned.pubkey(), &payer.pubkey(), 1_000_000);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer, &owned],
+        Hash::default(),
+    );
+    assert_eq!(channel.detects_account_closure(&tx), vec![owned.pubkey()]);
+
+    let ix = system_instruction::transfer(&owned.pubkey(), &payer.pubkey(), 1_000);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer, &owned],
+        Hash::default(),
+    );
+    assert!(channel.detects_account_closure(&tx).is_empty());
+}
+
+#[test]
+fn test_mock_source_rpc_call_log() {
+    let payer = Keypair::new();
+    let channel = mock_channel_with_loader(
+        RollUpAccountLoader::with_source(seeded_source(&payer)).with_rpc_call_log(),
+    );
+
+    let tx = transfer_tx(&payer, 10_000);
+    channel.simulate_transactions_raw(&[tx.clone()], &AnalysisConfig::default());
+
+    // The whole batch is prefetched in one request.
+    let log = channel.rpc_call_log();
+    assert_eq!(log[0].method, "getMultipleAccounts");
+    assert_eq!(log[0].args.len(), tx.message.account_keys.len());
+    // The recipient doesn't exist, so it's looked up again on its own.
+    assert!(log[1..].iter().all(|call| call.method == "getAccountInfo"));
+
+    channel.account_loader().clear_rpc_call_log();
+    assert!(channel.rpc_call_log().is_empty());
+}
+
+#[test]
+fn test_mock_source_caches_missing_sysvars() {
+    let payer = Keypair::new();
+    let channel = mock_channel_with_loader(
+        RollUpAccountLoader::with_source(seeded_source(&payer))
+            .with_cache_ttl(Duration::from_secs(1))
+            .with_rpc_call_log(),
+    );
+    let tx = transfer_tx(&payer, 10_000);
+    let clock_lookup = RpcCall {
+        method: "getAccountInfo",
+        args: vec![sysvar::clock::id()],
+    };
+
+    // The source has no sysvars, so the first simulation asks for each of them.
+    channel.simulate_transactions_raw(&[tx.clone()], &AnalysisConfig::default());
+    assert!(channel.rpc_call_log().contains(&clock_lookup));
+
+    // They are cached as missing, so the next simulation makes no requests...
+    channel.account_loader().clear_rpc_call_log();
+    channel.simulate_transactions_raw(&[tx.clone()], &AnalysisConfig::default());
+    assert!(channel.rpc_call_log().is_empty());
+
+    // ...until the cache TTL runs out.
+    std::thread::sleep(Duration::from_millis(1_100));
+    channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+    assert!(channel.rpc_call_log().contains(&clock_lookup));
+}
+
+#[test]
+fn test_mock_source_spoofed_owner_override() {
+    let payer = Keypair::new();
+    let sender = Keypair::new();
+    let source = seeded_source(&payer);
+    source.insert(
+        sender.pubkey(),
+        AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+    );
+    let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source));
+
+    let ix = system_instruction::transfer(&sender.pubkey(), &Pubkey::new_unique(), 10_000);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer, &sender],
+        Hash::default(),
+    );
+    let config = AnalysisConfig::default();
+    assert!(channel.simulate_transactions_raw(&[tx.clone()], &config)[0].success);
+
+    // The System Program can't debit an account another program owns.
+    let spoofed = channel
+        .account_with_owner(&sender.pubkey(), &Pubkey::new_unique())
+        .unwrap();
+    let overrides = HashMap::from([(sender.pubkey(), spoofed)]);
+    let result = channel
+        .simulate_transactions_raw_with_overrides(&[tx.clone()], &config, &overrides)
+        .remove(0);
+    assert!(!result.success);
+    assert_eq!(
+        result.error,
+        Some(TransactionError::InstructionError(
+            0,
+            InstructionError::ExternalAccountLamportSpend
+        ))
+    );
+    // The override doesn't reach the cache.
+    assert!(channel.simulate_transactions_raw(&[tx], &config)[0].success);
+}
+
+#[test]
+fn test_mock_source_diagnose() {
+    let payer = Keypair::new();
+    let source = seeded_source(&payer);
+
+    let missing_program = Pubkey::new_unique();
+    let not_executable = Pubkey::new_unique();
+    source.insert(
+        not_executable,
+        AccountSharedData::new(1_000_000, 0, &system_program::id()),
+    );
+    let vote_program = solana_sdk::vote::program::id();
+    source.insert(
+        vote_program,
+        native_loader::create_loadable_account_for_test("vote_program"),
+    );
+    let foreign_program = Pubkey::new_unique();
+    let foreign_loader = Pubkey::new_unique();
+    let mut foreign_account = AccountSharedData::new(1_000_000, 64, &foreign_loader);
+    foreign_account.set_executable(true);
+    source.insert(foreign_program, foreign_account);
+    let upgradeable_program = Pubkey::new_unique();
+    let programdata_address = Pubkey::new_unique();
+    let mut upgradeable_account = AccountSharedData::new_data(
+        1_000_000,
+        &UpgradeableLoaderState::Program {
+            programdata_address,
+        },
+        &bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    upgradeable_account.set_executable(true);
+    source.insert(upgradeable_program, upgradeable_account);
+
+    let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source));
+
+    let recipient = Pubkey::new_unique();
+    let mut instructions = vec![system_instruction::transfer(
+        &payer.pubkey(),
+        &recipient,
+        10_000,
+    )];
+    instructions.extend(
+        [
+            missing_program,
+            not_executable,
+            vote_program,
+            foreign_program,
+            upgradeable_program,
+        ]
+        .iter()
+        .map(|&program_id| Instruction::new_with_bytes(program_id, &[], vec![])),
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+
+    // A plain transfer only misses the account it creates.
+    assert!(matches!(
+        channel.diagnose(&transfer_tx(&payer, 10_000))[..],
+        [Diagnostic::MissingAccount { .. }]
+    ));
+    assert_eq!(
+        channel.diagnose(&tx),
+        vec![
+            Diagnostic::MissingProgram {
+                program_id: missing_program
+            },
+            Diagnostic::NotExecutable {
+                program_id: not_executable
+            },
+            Diagnostic::UnsupportedBuiltin {
+                program_id: vote_program
+            },
+            Diagnostic::UnsupportedLoader {
+                program_id: foreign_program,
+                loader: foreign_loader
+            },
+            Diagnostic::MissingProgramData {
+                program_id: upgradeable_program,
+                programdata_address
+            },
+            Diagnostic::MissingAccount { pubkey: recipient },
+        ]
+    );
+}
+
+#[test]
+fn test_mock_source_set_slot_hashes() {
+    let payer = Keypair::new();
+    let slot_hashes = SlotHashes::new(&[(42, Hash::new_unique()), (41, Hash::new_unique())]);
+    let channel = lookup_table_channel(&payer, &slot_hashes);
+
+    let account = channel
+        .account_loader()
+        .load_account(&sysvar::slot_hashes::id())
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner(), &sysvar::id());
+    let stored: SlotHashes = bincode::deserialize(account.data()).unwrap();
+    assert_eq!(stored, slot_hashes);
+
+    // Creating a lookup table reads SlotHashes from the sysvar cache, and
+    // fails unless its recent slot is in there.
+    let results = channel.simulate_transactions_raw(
+        &[
+            create_lookup_table_tx(&payer, 42),
+            create_lookup_table_tx(&payer, 43),
+        ],
+        &AnalysisConfig::default(),
+    );
+    assert!(results[0].success, "{}", results[0].result);
+    assert_eq!(
+        results[1].error,
+        Some(TransactionError::InstructionError(
+            0,
+            InstructionError::InvalidInstructionData
+        ))
+    );
+}
+
+#[test]
+fn test_mock_source_filter_viable() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    let (viable, failed) = channel.filter_viable(&[
+        transfer_tx(&payer, 10_000),
+        transfer_tx(&payer, 10_000_000_000),
+        transfer_tx(&payer, 20_000),
+    ]);
+    assert_eq!(viable, vec![0, 2]);
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].0, 1);
+    assert!(!failed[0].1.is_empty());
+}
+
+#[test]
+fn test_mock_source_lamports_per_signature() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer).with_config(RollUpChannelConfig {
+        lamports_per_signature: Some(10_000),
+        ..RollUpChannelConfig::default()
+    });
+
+    // The fee the SVM charges and the estimated base fee agree.
+    let ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+    assert_eq!(results[0].fees_only.as_ref().unwrap().fee_lamports, 10_000);
+
+    let estimate = channel
+        .estimate_batch_fees(&[transfer_tx(&payer, 10_000)], &FixedFeeModel)
+        .unwrap();
+    assert_eq!(estimate.per_tx[0].base_fee_lamports, 10_000);
+}
+
+#[test]
+fn test_mock_source_simulate_logs_rpc_format() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    let system = system_program::id();
+    assert_eq!(
+        channel.simulate_logs(&transfer_tx(&payer, 10_000)),
+        vec![
+            format!("Program {} invoke [1]", system),
+            format!("Program {} success", system),
+        ]
+    );
+
+    let logs = channel.simulate_logs(&transfer_tx(&payer, 10_000_000_000));
+    assert_eq!(
+        logs.first(),
+        Some(&format!("Program {} invoke [1]", system))
+    );
+    assert_eq!(
+        logs.last(),
+        Some(&format!(
+            "Program {} failed: custom program error: 0x1",
+            system
+        ))
+    );
+}
+
+#[test]
+fn test_mock_source_summarize() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    let tx = transfer_tx(&payer, 10_000);
+    let recipient = tx.message.account_keys[1];
+    let summary = channel.summarize(&tx);
+    assert_eq!(summary.status, Ok(()));
+    assert_eq!(summary.fee_lamports, 5_000);
+    assert_eq!(summary.compute_units, 150);
+    assert_eq!(summary.logs.len(), 2);
+
+    // The System Program is read-only, so only the two balances are listed.
+    assert_eq!(summary.account_changes.len(), 2);
+    let payer_change = &summary.account_changes[0];
+    assert_eq!(payer_change.pubkey, payer.pubkey());
+    assert_eq!(payer_change.lamports_before, 1_000_000_000);
+    assert_eq!(payer_change.lamports_change(), -15_000);
+    let recipient_change = &summary.account_changes[1];
+    assert_eq!(recipient_change.pubkey, recipient);
+    assert_eq!(recipient_change.lamports_before, 0);
+    assert_eq!(recipient_change.lamports_after, 10_000);
+    assert!(!recipient_change.data_changed);
+
+    let failed = channel.summarize(&transfer_tx(&payer, 10_000_000_000));
+    assert!(failed.status.is_err());
+    assert_eq!(failed.fee_lamports, 5_000);
+    assert!(failed.account_changes.is_empty());
+}
+
+#[test]
+fn test_mock_source_implied_priority_fee() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    let priced = Transaction::new_signed_with_payer(
+        &[
+            ComputeBudgetInstruction::set_compute_unit_price(2_000_000),
+            system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 10_000),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    let results = channel.simulate_transactions_raw(
+        &[priced, transfer_tx(&payer, 10_000)],
+        &AnalysisConfig::default(),
+    );
+    assert!(results[0].success);
+    // 2 lamports per consumed CU.
+    assert_eq!(
+        results[0].implied_priority_fee_lamports,
+        Some(results[0].cu * 2)
+    );
+    assert_eq!(results[1].implied_priority_fee_lamports, None);
+}
+
+#[test]
+fn test_mock_source_fixed_fee_per_cu() {
+    // No fee model and an unreachable RPC: only the fixed price can succeed.
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+
+    let config = AnalysisConfig {
+        calculate_priority_fee: true,
+        fixed_fee_per_cu: Some(2_000_000),
+        ..AnalysisConfig::default()
+    };
+    let results = channel.simulate_transactions_raw(&[transfer_tx(&payer, 10_000)], &config);
+    let details = results[0].prioritization_fee_details.as_ref().unwrap();
+    assert_eq!(details.error_message, None);
+    assert_eq!(details.fee_per_cu_micro_lamports, 2_000_000);
+    assert_eq!(details.total_fee_lamports, results[0].cu * 2);
+}
+
+#[test]
+fn test_mock_source_cpi_count() {
+    let payer = Keypair::new();
+    let channel = lookup_table_channel(&payer, &SlotHashes::new(&[(42, Hash::new_unique())]));
+
+    // Creating a table funds, allocates and assigns it through the System
+    // Program.
+    let results = channel.simulate_transactions_raw(
+        &[create_lookup_table_tx(&payer, 42)],
+        &AnalysisConfig::default(),
+    );
+    assert!(results[0].success, "{}", results[0].result);
+    assert_eq!(results[0].cpi_count, 3);
+    assert_eq!(results[0].max_cpi_depth, 2);
+    assert_eq!(
+        results[0].invoked_programs,
+        vec![address_lookup_table::program::id(), system_program::id()]
+    );
+}
+
+#[test]
+fn test_mock_source_inner_instruction_cu() {
+    let payer = Keypair::new();
+    let channel = lookup_table_channel(&payer, &SlotHashes::new(&[(42, Hash::new_unique())]));
+
+    let results = channel.simulate_transactions_raw(
+        &[create_lookup_table_tx(&payer, 42)],
+        &AnalysisConfig::default(),
+    );
+    assert!(results[0].success, "{}", results[0].result);
+    // The table is funded, allocated and assigned through the System Program,
+    // which charges 150 CUs per call.
+    let cpis = &results[0].inner_instructions;
+    assert_eq!(cpis.len(), 3);
+    for cpi in cpis {
+        assert_eq!(cpi.instruction_index, 0);
+        assert_eq!(cpi.stack_height, 2);
+        assert_eq!(cpi.program_id, system_program::id());
+        assert_eq!(cpi.consumed_cu, Some(150));
+    }
+    // The Address Lookup Table program charges 750 CUs on top of its CPIs.
+    assert_eq!(results[0].cu, 750 + 3 * 150);
+}
+
+#[test]
+fn test_mock_source_epoch_total_stake() {
+    let payer = Keypair::new();
+    let source = seeded_source(&payer);
+    source.insert(
+        bpf_loader::id(),
+        native_loader::create_loadable_account_for_test("solana_bpf_loader_program"),
+    );
+    let program_id = Pubkey::new_unique();
+    let config = RollUpChannelConfig {
+        epoch_total_stake: 42_000,
+        ..RollUpChannelConfig::default()
+    };
+    let channel =
+        mock_channel_with_loader(RollUpAccountLoader::with_source(source)).with_config(config);
+    channel.load_program_bytes(program_id, &epoch_stake_program_elf());
+
+    let tx = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bytes(program_id, &[], vec![])],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+    // The program exits with the stake it reads, which fails the instruction.
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(42_000)
+        ))
+    );
+}
+
+#[test]
+fn test_mock_source_load_program_bytes() {
+    let payer = Keypair::new();
+    let source = seeded_source(&payer);
+    source.insert(
+        bpf_loader::id(),
+        native_loader::create_loadable_account_for_test("solana_bpf_loader_program"),
+    );
+    let config = RollUpChannelConfig {
+        reuse_processor: true,
+        ..RollUpChannelConfig::default()
+    };
+    let channel =
+        mock_channel_with_loader(RollUpAccountLoader::with_source(source)).with_config(config);
+    let program_id = Pubkey::new_unique();
+    let tx = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bytes(program_id, &[], vec![])],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+
+    let results = channel.simulate_transactions_raw(&[tx.clone()], &AnalysisConfig::default());
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::ProgramAccountNotFound)
+    );
+
+    // The kept processor picks up the program; with no stake configured it
+    // exits with 0.
+    channel.load_program_bytes(program_id, &epoch_stake_program_elf());
+    let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+    assert!(results[0].success, "{}", results[0].result);
+    assert_eq!(results[0].invoked_programs, vec![program_id]);
+    assert!(results[0].cu > 0);
+}
+
+#[test]
+fn test_mock_source_oversized_account_policies() {
+    let payer = Keypair::new();
+    let big = Pubkey::new_unique();
+    let channel_with = |policy: OversizedAccountPolicy| {
+        let source = seeded_source(&payer);
+        source.insert(
+            big,
+            AccountSharedData::new(1_000_000, 1_024, &system_program::id()),
+        );
+        mock_channel_with_loader(
+            RollUpAccountLoader::with_source(source).with_max_account_data_bytes(512, policy),
+        )
+    };
+    let ix = system_instruction::transfer(&payer.pubkey(), &big, 10_000);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+
+    // The SVM would run the rejected account as missing and succeed, so the
+    // transaction fails with the size error instead.
+    let channel = channel_with(OversizedAccountPolicy::Error);
+    let results = channel.simulate_transactions_raw(&[tx.clone()], &AnalysisConfig::default());
+    let too_large = SolanaClientExtError::AccountDataTooLarge {
+        pubkey: big,
+        data_len: 1_024,
+        max_data_len: 512,
+    };
+    assert!(!results[0].success);
+    assert!(
+        results[0].result.contains(&too_large.to_string()),
+        "{}",
+        results[0].result
+    );
+    assert!(matches!(
+        channel.account_loader().load_account(&big),
+        Err(SolanaClientExtError::AccountDataTooLarge {
+            data_len: 1_024,
+            max_data_len: 512,
+            ..
+        })
+    ));
+
+    // A truncated account loads with its data cut to the limit.
+    let channel = channel_with(OversizedAccountPolicy::Truncate);
+    let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+    assert!(results[0].success, "{}", results[0].result);
+    let (_, account) = results[0]
+        .post_accounts
+        .iter()
+        .find(|(pubkey, _)| *pubkey == big)
+        .unwrap();
+    assert_eq!(account.data().len(), 512);
+    assert_eq!(account.lamports(), 1_010_000);
+}
+
+#[test]
+fn test_mock_source_fetch_errors_are_not_cached() {
+    let payer = Keypair::new();
+    let source = Arc::new(seeded_source(&payer));
+    let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source.clone()));
+    let loader = channel.account_loader();
+
+    source.set_unavailable(true);
+    let err = loader.prefetch(&[payer.pubkey()]).unwrap_err();
+    assert!(matches!(err, SolanaClientExtError::RpcError(_)), "{}", err);
+    assert!(loader.load_account(&payer.pubkey()).is_err());
+
+    let results = channel.simulate_transactions_raw(&[transfer_tx(&payer, 1_000)]);
+    assert!(!results[0].success);
+    assert!(
+        results[0].result.contains("couldn't be loaded"),
+        "{}",
+        results[0].result
+    );
+
+    // The failed fetches weren't cached as missing accounts.
+    source.set_unavailable(false);
+    loader.prefetch(&[payer.pubkey()]).unwrap();
+    assert!(loader.load_account(&payer.pubkey()).unwrap().is_some());
+    let results = channel.simulate_transactions_raw(&[transfer_tx(&payer, 1_000)]);
+    assert!(results[0].success, "{}", results[0].result);
+}
+
+#[test]
+fn test_mock_source_failover() {
+    let down = Arc::new(MockAccountSource::new());
+    down.set_unavailable(true);
+    let up = Arc::new(MockAccountSource::new());
+    let pubkey = Pubkey::new_unique();
+    up.insert(pubkey, AccountSharedData::new(42, 0, &system_program::id()));
+    let missing = Pubkey::new_unique();
+
+    let sources: Vec<Box<dyn AccountSource>> =
+        vec![Box::new(Arc::clone(&down)), Box::new(Arc::clone(&up))];
+    let source = FailoverAccountSource::from_sources(sources);
+    let account = source.get_account(&pubkey).unwrap().unwrap();
+    assert_eq!(account.lamports(), 42);
+    let accounts = source.get_multiple_accounts(&[pubkey, missing]).unwrap();
+    assert!(accounts[0].is_some());
+    assert!(accounts[1].is_none());
+
+    // Once every source fails, the last error is returned.
+    up.set_unavailable(true);
+    let err = source.get_account(&pubkey).unwrap_err();
+    assert!(err.to_string().contains("failed to fetch"), "{}", err);
+    assert!(source.get_multiple_accounts(&[pubkey]).is_err());
+
+    // A loader over the failover source serves accounts from the source that answers.
+    down.set_unavailable(false);
+    down.insert(pubkey, AccountSharedData::new(7, 0, &system_program::id()));
+    let loader = RollUpAccountLoader::with_source(source);
+    assert_eq!(loader.load_account(&pubkey).unwrap().unwrap().lamports(), 7);
+}
+
+#[test]
+fn test_mock_source_overrides_are_per_call() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+    let config = AnalysisConfig::default();
+    // More than the 1 SOL the payer is seeded with.
+    let tx = transfer_tx(&payer, 1_500_000_000);
+    assert!(!channel.simulate_transactions_raw(&[tx.clone()], &config)[0].success);
+
+    let overrides = HashMap::from([(
+        payer.pubkey(),
+        AccountSharedData::new(2_000_000_000, 0, &system_program::id()),
+    )]);
+    let results =
+        channel.simulate_transactions_raw_with_overrides(&[tx.clone()], &config, &overrides);
+    assert!(results[0].success, "{}", results[0].result);
+
+    // The override isn't cached, so later calls see the real balance again.
+    assert!(!channel.simulate_transactions_raw(&[tx], &config)[0].success);
+    let cached = channel
+        .account_loader()
+        .load_account(&payer.pubkey())
+        .unwrap()
+        .unwrap();
+    assert_eq!(cached.lamports(), 1_000_000_000);
+}
+
+#[test]
+fn test_mock_source_max_cpi_depth() {
+    let payer = Keypair::new();
+    let channel = lookup_table_channel(&payer, &SlotHashes::new(&[(42, Hash::new_unique())]));
+    let missing_program_id = Pubkey::new_unique();
+    let missing_program = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bytes(missing_program_id, &[], vec![])],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+
+    let results = channel.simulate_transactions_raw(
+        &[
+            transfer_tx(&payer, 10_000),
+            create_lookup_table_tx(&payer, 42),
+            missing_program,
+        ],
+        &AnalysisConfig::default(),
+    );
+    // A transfer runs the System Program at the top level only.
+    assert_eq!(results[0].max_cpi_depth, 1);
+    // Creating a table calls into the System Program one level down.
+    assert_eq!(results[1].max_cpi_depth, 2);
+    // Nothing runs if the program can't be loaded.
+    assert!(!results[2].success);
+    assert_eq!(results[2].max_cpi_depth, 0);
+}
+
+#[test]
+fn test_mock_source_lookup_table_management() {
+    let payer = Keypair::new();
+    let channel = lookup_table_channel(&payer, &SlotHashes::new(&[(42, Hash::new_unique())]));
+    channel.set_sysvar(&Clock {
+        slot: 43,
+        ..Clock::default()
+    });
+    let (_, table) =
+        address_lookup_table::instruction::create_lookup_table(payer.pubkey(), payer.pubkey(), 42);
+
+    let results = channel.simulate_transactions_raw(
+        &[create_lookup_table_tx(&payer, 42)],
+        &AnalysisConfig::default(),
+    );
+    assert!(results[0].success, "{}", results[0].result);
+    let (_, created) = results[0]
+        .post_accounts
+        .iter()
+        .find(|(pubkey, _)| *pubkey == table)
+        .unwrap();
+    assert_eq!(created.owner(), &address_lookup_table::program::id());
+
+    // Extending the created table runs against its simulated state.
+    channel
+        .account_loader()
+        .insert_account(table, created.clone());
+    let new_addresses = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+    let extend_ix = address_lookup_table::instruction::extend_lookup_table(
+        table,
+        payer.pubkey(),
+        Some(payer.pubkey()),
+        new_addresses.clone(),
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[extend_ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+    assert!(results[0].success, "{}", results[0].result);
+    assert!(results[0].cu > 0);
+    let (_, extended) = results[0]
+        .post_accounts
+        .iter()
+        .find(|(pubkey, _)| *pubkey == table)
+        .unwrap();
+    let extended = AddressLookupTable::deserialize(extended.data()).unwrap();
+    assert_eq!(extended.addresses.as_ref(), new_addresses.as_slice());
+}
+
+#[test]
+fn test_mock_source_on_load_hook() {
+    let payer = Keypair::new();
+    let source = seeded_source(&payer);
+    let fetched = Pubkey::new_unique();
+    source.insert(
+        fetched,
+        AccountSharedData::new(1_000_000, 64, &system_program::id()),
+    );
+    let loads = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&loads);
+    let loader = RollUpAccountLoader::with_source(source).with_on_load(move |pubkey, account| {
+        recorded
+            .lock()
+            .unwrap()
+            .push((*pubkey, account.data().len()))
+    });
+
+    // Called once per fetch, with the fetched account.
+    loader.load_account(&fetched).unwrap();
+    loader.load_account(&fetched).unwrap();
+    assert_eq!(*loads.lock().unwrap(), vec![(fetched, 64)]);
+
+    // Not for missing accounts, nor for accounts inserted by hand.
+    let inserted = Pubkey::new_unique();
+    loader.insert_account(inserted, AccountSharedData::default());
+    assert!(loader
+        .load_account(&Pubkey::new_unique())
+        .unwrap()
+        .is_none());
+    assert!(loader.load_account(&inserted).unwrap().is_some());
+    assert_eq!(loads.lock().unwrap().len(), 1);
+
+    // A refetch calls it again.
+    loader.invalidate(&fetched);
+    loader.load_account(&fetched).unwrap();
+    assert_eq!(loads.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_mock_source_cache_invalidation() {
+    let source = Arc::new(MockAccountSource::new());
+    let pubkey = Pubkey::new_unique();
+    let set_lamports = |lamports| {
+        source.insert(
+            pubkey,
+            AccountSharedData::new(lamports, 0, &system_program::id()),
+        )
+    };
+    let lamports = |loader: &RollUpAccountLoader<'static>| {
+        loader.load_account(&pubkey).unwrap().unwrap().lamports()
+    };
+
+    set_lamports(1);
+    let loader = RollUpAccountLoader::with_source(Arc::clone(&source));
+    assert_eq!(lamports(&loader), 1);
+    // Without a TTL, the cached copy is served until it's dropped.
+    set_lamports(2);
+    assert_eq!(lamports(&loader), 1);
+    loader.invalidate(&pubkey);
+    assert_eq!(lamports(&loader), 2);
+    set_lamports(3);
+    loader.clear_cache();
+    assert_eq!(lamports(&loader), 3);
+
+    // With a TTL, it's refetched once it expires.
+    let loader = RollUpAccountLoader::with_source(Arc::clone(&source))
+        .with_cache_ttl(Duration::from_millis(200));
+    assert_eq!(lamports(&loader), 3);
+    set_lamports(4);
+    assert_eq!(lamports(&loader), 3);
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(lamports(&loader), 4);
+}
+
+#[test]
+fn test_mock_source_load_program_from_file() {
+    let payer = Keypair::new();
+    let source = seeded_source(&payer);
+    source.insert(
+        bpf_loader::id(),
+        native_loader::create_loadable_account_for_test("solana_bpf_loader_program"),
+    );
+    let channel = mock_channel_with_loader(RollUpAccountLoader::with_source(source));
+    let program_id = Pubkey::new_unique();
+    let path = std::env::temp_dir().join(format!("{}.so", program_id));
+    std::fs::write(&path, epoch_stake_program_elf()).unwrap();
+
+    let missing = channel.load_program_from_file(program_id, path.with_extension("missing"));
+    assert_eq!(missing.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    channel.load_program_from_file(program_id, &path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // The program runs from the file's ELF; with no stake configured it exits with 0.
+    let tx = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bytes(program_id, &[], vec![])],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    let results = channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default());
+    assert!(results[0].success, "{}", results[0].result);
+    assert_eq!(results[0].invoked_programs, vec![program_id]);
+    let program = channel
+        .account_loader()
+        .load_account(&program_id)
+        .unwrap()
+        .unwrap();
+    assert!(program.executable());
+    assert_eq!(program.owner(), &bpf_loader::id());
+    assert_eq!(program.data(), epoch_stake_program_elf().as_slice());
+}
+
+#[test]
+fn test_mock_source_exceeded_compute_budget() {
+    let payer = Keypair::new();
+    let channel = mock_channel(&payer);
+    let with_limit = |limit, lamports| {
+        let ixs = [
+            ComputeBudgetInstruction::set_compute_unit_limit(limit),
+            system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), lamports),
+        ];
+        Transaction::new_signed_with_payer(&ixs, Some(&payer.pubkey()), &[&payer], Hash::default())
+    };
+
+    let results = channel.simulate_transactions_raw(
+        &[
+            with_limit(100, 10_000),
+            with_limit(100_000, 10_000_000_000),
+            with_limit(100_000, 10_000),
+        ],
+        &AnalysisConfig::default(),
+    );
+    // Even the `SetComputeUnitLimit` instruction costs 150 CUs.
+    assert!(results[0].exceeded_compute_budget);
+    assert_eq!(
+        results[0].error,
+        Some(TransactionError::InstructionError(
+            0,
+            InstructionError::ComputationalBudgetExceeded
+        ))
+    );
+    // An overdraft fails for another reason.
+    assert!(!results[1].success);
+    assert!(!results[1].exceeded_compute_budget);
+    assert!(results[2].success, "{}", results[2].result);
+    assert!(!results[2].exceeded_compute_budget);
+}
+
+#[test]
+fn test_mock_source_simulate_across_budgets() {
+    let payer = Keypair::new();
+    let channel = mock_channel_with_loader(
+        RollUpAccountLoader::with_source(seeded_source(&payer)).with_rpc_call_log(),
+    );
+    let budget = |compute_unit_limit| ComputeBudget {
+        compute_unit_limit,
+        ..ComputeBudget::default()
+    };
+
+    // Each run sets the limit with a `SetComputeUnitLimit` instruction, which
+    // costs 150 CUs on top of the transfer's 150.
+    let results = channel.simulate_across_budgets(
+        &transfer_tx(&payer, 10_000),
+        &[budget(200), budget(300), ComputeBudget::default()],
+    );
+    assert_eq!(results.len(), 3);
+    assert!(results[0].exceeded_compute_budget);
+    for result in &results[1..] {
+        assert!(result.success, "{}", result.result);
+        assert_eq!(result.cu, 300);
+    }
+
+    // The runs share the account cache, so the payer is fetched once.
+    let payer_fetches = channel
+        .rpc_call_log()
+        .iter()
+        .filter(|call| call.args.contains(&payer.pubkey()))
+        .count();
+    assert_eq!(payer_fetches, 1);
+}
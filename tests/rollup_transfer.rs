@@ -1,12 +1,50 @@
-use solana_client_ext::state::rollup_channel::RollUpChannel;
 use solana_client_ext::AnalysisConfig;
 use solana_client_ext::*;
+use std::sync::Arc;
+
+mod common;
+use common::{FixedFeeModel, MockRpc};
 
 use solana_sdk::{
-    hash::Hash, message::Message, pubkey::Pubkey, signature::Keypair, signer::Signer,
-    system_instruction, transaction::Transaction,
+    bpf_loader_upgradeable,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction,
+    transaction::Transaction,
 };
 
+/// Token-2022, which is deployed with the upgradeable loader.
+const TOKEN_2022_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Creates `mint` as a Token-2022 mint with `payer` as its mint authority.
+fn create_token_2022_mint(payer: &Pubkey, mint: &Pubkey) -> Vec<Instruction> {
+    const MINT_LEN: usize = 82;
+    // `InitializeMint2` with 0 decimals and no freeze authority.
+    let mut data = vec![20, 0];
+    data.extend_from_slice(payer.as_ref());
+    data.push(0);
+    vec![
+        system_instruction::create_account(
+            payer,
+            mint,
+            Rent::default().minimum_balance(MINT_LEN),
+            MINT_LEN as u64,
+            &TOKEN_2022_PROGRAM_ID,
+        ),
+        Instruction::new_with_bytes(
+            TOKEN_2022_PROGRAM_ID,
+            &data,
+            vec![AccountMeta::new(*mint, false)],
+        ),
+    ]
+}
+
 fn create_transfer_tx(
     from: &Keypair,
     to: &Pubkey,
@@ -154,12 +192,13 @@ fn test_prioritization_fee_simulation() {
     let tx = create_transfer_tx(&payer, &recipient, lamports_for_rent_exemption, recent_blockhash);
 
     let accounts_for_channel = tx.message.account_keys.clone();
-    let mut channel = RollUpChannel::new(accounts_for_channel, &rpc_client);
+    let channel = RollUpChannel::new(accounts_for_channel, &rpc_client);
 
     let config_with_fee = AnalysisConfig {
         estimate_compute_units: true,
         calculate_priority_fee: true,
         tag: Some("test_fee_calc".to_string()),
+        ..AnalysisConfig::default()
     };
 
     println!("Processing tx with fee calculation, tag: {:?}", config_with_fee.tag);
@@ -209,7 +248,7 @@ fn test_prioritization_fee_simulation() {
                      assert!(fee_details.error_message.is_none(), "Expected no error in fee details for successful simulation with CUs ({} CUs), but got: {:?}", raw_sim_cu, fee_details.error_message);
                      
                      // Calculate expected total fee based on details
-                     let expected_total_fee = (fee_details.fee_per_cu_micro_lamports as u128 * raw_sim_cu as u128) / 1_000_000;
+                     let expected_total_fee = (fee_details.fee_per_cu_micro_lamports as u128 * raw_sim_cu as u128).div_ceil(1_000_000);
                      assert_eq!(fee_details.total_fee_lamports, expected_total_fee as u64, 
                                 "Total fee lamports ({}) does not match expected calculated fee ({}) based on fee_per_cu_micro_lamports ({}) and raw_sim_cu ({}).", 
                                 fee_details.total_fee_lamports, expected_total_fee, fee_details.fee_per_cu_micro_lamports, raw_sim_cu);
@@ -259,7 +298,7 @@ fn test_rollup_channel_tagging() {
         .expect("Failed to get latest blockhash");
 
     let accounts_for_channel = vec![payer.pubkey(), recipient1, recipient2];
-    let mut channel = RollUpChannel::new(accounts_for_channel, &rpc_client);
+    let channel = RollUpChannel::new(accounts_for_channel, &rpc_client);
 
     let tx1 = create_transfer_tx(&payer, &recipient1, 1000, recent_blockhash);
     let tx2 = create_transfer_tx(&payer, &recipient2, 2000, recent_blockhash);
@@ -269,21 +308,25 @@ fn test_rollup_channel_tagging() {
         estimate_compute_units: true,
         calculate_priority_fee: false,
         tag: Some("run1_cu_only".to_string()),
+        ..AnalysisConfig::default()
     };
     let config_cu_only_tag2 = AnalysisConfig {
         estimate_compute_units: true,
         calculate_priority_fee: false,
         tag: Some("run2_cu_only".to_string()),
+        ..AnalysisConfig::default()
     };
     let config_cu_only_tag_multi = AnalysisConfig {
         estimate_compute_units: true,
         calculate_priority_fee: false,
         tag: Some("run_multi_cu_only".to_string()),
+        ..AnalysisConfig::default()
     };
     let config_cu_only_no_tag = AnalysisConfig {
         estimate_compute_units: true,
         calculate_priority_fee: false,
         tag: None,
+        ..AnalysisConfig::default()
     };
 
     println!("Processing tx1 with tag: {:?}", config_cu_only_tag1.tag);
@@ -398,3 +441,426 @@ fn test_rollup_channel_tagging() {
         "Tag run1_cu_only should still have 2 results after a non-tagged call"
     );
 }
+
+#[test]
+fn test_partially_signed_multisig_estimation() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let Some(payer) = common::devnet_payer() else {
+        return;
+    };
+    let cosigner = Keypair::new();
+
+    // The cosigner is a second required signer, but only the payer signs.
+    let transfer_ix = system_instruction::transfer(&cosigner.pubkey(), &Pubkey::new_unique(), 0);
+    let msg = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+    assert_eq!(msg.header.num_required_signatures, 2);
+
+    let blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let mut tx = Transaction::new_unsigned(msg);
+    tx.partial_sign(&[&payer], blockhash);
+    assert!(!tx.is_signed(), "Transaction should only be partially signed");
+
+    let cus = rpc_client
+        .estimate_compute_units_unsigned_tx(&tx, &[&payer])
+        .expect("Local estimation should not require all signatures");
+    assert_eq!(cus.len(), 1);
+    assert!(cus[0] > 0, "Expected a non-zero CU estimate");
+    println!("Partially signed multisig CU estimate: {}", cus[0]);
+
+    // RPC estimation signs the message, so the missing cosigner is a build error.
+    let err = rpc_client
+        .estimate_compute_units_msg(&tx.message, &[&payer])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::TransactionBuildError(_))
+    ));
+}
+
+#[test]
+fn test_shared_channel_across_threads() {
+    use solana_sdk::account::ReadableAccount;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RollUpChannel<'static>>();
+
+    let payer = Keypair::new();
+    let rpc_client = Arc::new(MockRpc::new(&payer).into_client());
+    let channel = Arc::new(RollUpChannel::new_shared(vec![], rpc_client));
+    let payer = Arc::new(payer);
+
+    // Every thread simulates its own transfer against the shared account cache.
+    let handles: Vec<_> = [10_000, 20_000, 30_000, 40_000]
+        .into_iter()
+        .map(|lamports| {
+            let (channel, payer) = (Arc::clone(&channel), Arc::clone(&payer));
+            std::thread::spawn(move || {
+                let recipient = Pubkey::new_unique();
+                let ix = system_instruction::transfer(&payer.pubkey(), &recipient, lamports);
+                let tx = Transaction::new_signed_with_payer(
+                    &[ix],
+                    Some(&payer.pubkey()),
+                    &[&payer],
+                    Hash::default(),
+                );
+                let result = channel
+                    .simulate_transactions_raw(&[tx.clone()], &AnalysisConfig::default())
+                    .pop()
+                    .unwrap();
+                (tx, lamports, result)
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        let (tx, lamports, result) = handle.join().unwrap();
+        assert!(result.success, "{}", result.result);
+        assert_eq!(result.cu, 150);
+        let balance = |pubkey: &Pubkey| {
+            result
+                .post_accounts
+                .iter()
+                .find(|(key, _)| key == pubkey)
+                .map(|(_, account)| account.lamports())
+        };
+        // Simulations don't commit, so each one starts from the seeded balance.
+        assert_eq!(
+            balance(&payer.pubkey()),
+            Some(1_000_000_000 - lamports - 5_000)
+        );
+        assert_eq!(balance(&tx.message.account_keys[1]), Some(lamports));
+    }
+}
+
+#[test]
+fn test_check_accounts_exist() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &recipient, 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&payer.pubkey())));
+
+    let existence = rpc_client.check_accounts_exist(&tx).unwrap();
+    assert_eq!(existence.len(), 3);
+    assert!(!existence[&recipient]);
+    assert!(existence[&solana_sdk::system_program::id()]);
+}
+
+#[test]
+fn test_check_cu_within() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let Some(payer) = common::devnet_payer() else {
+        return;
+    };
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&payer.pubkey())));
+
+    let cu = rpc_client.check_cu_within(&tx, &[&payer], 1, 10_000).unwrap();
+    rpc_client.assert_cu_within(&tx, &[&payer], cu, cu);
+
+    let err = rpc_client.check_cu_within(&tx, &[&payer], cu + 1, cu + 100).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains(&format!("consumed {} CUs", cu)), "{}", message);
+    assert!(message.contains(&format!("expected {}..={}", cu + 1, cu + 100)));
+}
+
+#[test]
+fn test_build_versioned_with_lookup() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let Some(payer) = common::devnet_payer() else {
+        return;
+    };
+    let recipient = Pubkey::new_unique();
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &recipient, 1_000);
+    let message = Message::new(&[transfer_ix], Some(&payer.pubkey()));
+    let lookup_table = solana_sdk::message::AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: vec![recipient],
+    };
+
+    let tx = rpc_client
+        .build_versioned_with_lookup(&message, &[lookup_table], &FixedFeeModel, &[&payer])
+        .unwrap();
+    assert!(tx.verify_with_results().iter().all(|verified| *verified));
+    // The recipient is referenced through the lookup table, not as a static key.
+    assert_eq!(tx.message.address_table_lookups().map(<[_]>::len), Some(1));
+    assert!(!tx.message.static_account_keys().contains(&recipient));
+    // Compute unit limit and price come first, followed by the transfer.
+    assert_eq!(tx.message.instructions().len(), 3);
+}
+
+#[test]
+fn test_plan_compute_budget() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let Some(payer) = common::devnet_payer() else {
+        return;
+    };
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&payer.pubkey())));
+    let original_message = tx.message.clone();
+
+    let plan = rpc_client.plan_compute_budget(&tx, &[&payer]).unwrap();
+    assert!(!plan.is_empty() && plan.len() <= 2);
+    assert!(plan
+        .iter()
+        .all(|ix| ix.program_id == solana_sdk::compute_budget::id()));
+    // Planning leaves the transaction untouched.
+    assert_eq!(tx.message, original_message);
+}
+
+#[test]
+fn test_estimate_compute_units_hybrid() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let Some(payer) = common::devnet_payer() else {
+        return;
+    };
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&payer.pubkey())));
+
+    // System Program transfers run locally, so RPC is only used to load accounts.
+    let (cu, source) = rpc_client
+        .estimate_compute_units_hybrid(&tx, &[&payer])
+        .unwrap();
+    assert_eq!(source, CuEstimateSource::Local);
+    assert_eq!(cu, 150);
+
+    for (cu_source, expected_source) in [
+        (CuSource::LocalOnly, Some(CuEstimateSource::Local)),
+        (CuSource::RpcOnly, Some(CuEstimateSource::Rpc)),
+        (CuSource::MaxOfBoth, None),
+    ] {
+        let config = HybridEstimateConfig { cu_source };
+        let (cu, source) = rpc_client
+            .estimate_compute_units_hybrid_with_config(&tx, &[&payer], &config)
+            .unwrap();
+        assert_eq!(cu, 150, "{:?}", cu_source);
+        if let Some(expected_source) = expected_source {
+            assert_eq!(source, expected_source);
+        }
+    }
+}
+
+#[test]
+fn test_estimate_full_cost() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let Some(payer) = common::devnet_payer() else {
+        return;
+    };
+    let new_account = Keypair::new();
+    let rent = solana_sdk::rent::Rent::default().minimum_balance(0);
+    let create_ix = system_instruction::create_account(
+        &payer.pubkey(),
+        &new_account.pubkey(),
+        rent,
+        0,
+        &solana_sdk::system_program::id(),
+    );
+    let tx = Transaction::new_unsigned(Message::new(&[create_ix], Some(&payer.pubkey())));
+
+    let cost = rpc_client.estimate_full_cost(&tx, &FixedFeeModel).unwrap();
+    // Two signatures, and the new account's write lock is free by default.
+    assert_eq!(cost.base_fee, 10_000);
+    assert_eq!(cost.rent_for_new_accounts, rent);
+    assert_eq!(cost.total, cost.base_fee + cost.priority_fee + cost.rent_for_new_accounts);
+}
+
+#[test]
+fn test_clone_account_from() {
+    use solana_sdk::account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_sdk::bpf_loader_upgradeable::UpgradeableLoaderState;
+
+    let payer = Keypair::new();
+    // Stands in for mainnet: it has a mint and an upgradeable program that the
+    // loader's own cluster lacks.
+    let mainnet = MockRpc::new(&payer);
+    let token_program = solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGLPMaAUtrFkMxCB2CgUfLNm");
+    let mint = Pubkey::new_unique();
+    mainnet
+        .accounts
+        .insert(mint, AccountSharedData::new(1_461_600, 82, &token_program));
+    let program_id = Pubkey::new_unique();
+    let programdata_address = Pubkey::new_unique();
+    let mut program = AccountSharedData::new_data(
+        1_141_440,
+        &UpgradeableLoaderState::Program {
+            programdata_address,
+        },
+        &bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    program.set_executable(true);
+    mainnet.accounts.insert(program_id, program);
+    let programdata = AccountSharedData::new(2_000_000, 128, &bpf_loader_upgradeable::id());
+    mainnet
+        .accounts
+        .insert(programdata_address, programdata.clone());
+    let mainnet = mainnet.into_client();
+
+    let devnet = MockRpc::new(&payer);
+    let devnet_requests = devnet.requests.clone();
+    let devnet = devnet.into_client();
+    let loader = RollUpAccountLoader::new(&devnet);
+
+    let cloned = loader.clone_from(&mainnet, &mint).unwrap();
+    assert_eq!(cloned.owner(), &token_program);
+    assert_eq!(loader.load_account(&mint).unwrap(), Some(cloned));
+    // Cloning an upgradeable program brings its ProgramData along.
+    let cloned = loader.clone_from(&mainnet, &program_id).unwrap();
+    assert!(cloned.executable());
+    assert_eq!(
+        loader.load_account(&programdata_address).unwrap(),
+        Some(programdata)
+    );
+    // Everything was served from the cache rather than devnet.
+    assert!(devnet_requests.lock().unwrap().is_empty());
+
+    assert!(loader.clone_from(&mainnet, &Pubkey::new_unique()).is_err());
+}
+
+#[test]
+fn test_optimize_compute_units_with_margin() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let Some(payer) = common::devnet_payer() else {
+        return;
+    };
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let mut tx = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&payer.pubkey())));
+    let margin = CuMarginConfig {
+        recommended_multiplier: 2.0,
+        p95_multiplier: 3.0,
+    };
+
+    let cu = rpc_client
+        .optimize_compute_units_unsigned_tx_with_margin(&mut tx, &[&payer], &margin)
+        .unwrap();
+    // The estimate covers the transfer and the limit instruction itself.
+    assert!(cu > 150);
+    assert_eq!(parse_compute_budget(&tx).unit_limit, Some(cu * 2));
+
+    // Optimizing again replaces the limit instead of adding another one.
+    let again = rpc_client
+        .optimize_compute_units_unsigned_tx_with_margin(&mut tx, &[&payer], &margin)
+        .unwrap();
+    assert_eq!(again, cu);
+    assert_eq!(tx.message.instructions.len(), 2);
+}
+
+#[test]
+fn test_loader_endpoint_failover() {
+    // Nothing listens here, so every request fails over to devnet.
+    let unreachable = solana_client::rpc_client::RpcClient::new("http://127.0.0.1:1");
+    let devnet = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let loader = RollUpAccountLoader::with_endpoints(vec![&unreachable, &devnet]);
+
+    let system_program = loader
+        .load_account(&solana_sdk::system_program::id())
+        .unwrap();
+    assert!(system_program.is_some());
+    // Accounts missing from a reachable endpoint are reported as missing.
+    assert_eq!(loader.load_account(&Pubkey::new_unique()).unwrap(), None);
+}
+
+#[test]
+fn test_compare_local_vs_rpc() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let Some(payer) = common::devnet_payer() else {
+        return;
+    };
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&payer.pubkey())));
+
+    let comparison = rpc_client.compare_local_vs_rpc(&tx, &[&payer]).unwrap();
+    assert_eq!(comparison.local, 150);
+    // The System Program is a builtin, so both paths agree.
+    assert_eq!(comparison.rpc, Ok(150));
+    assert_eq!(comparison.difference, 0);
+}
+
+#[test]
+fn test_pack_into_transactions() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let Some(payer) = common::devnet_payer() else {
+        return;
+    };
+    // Far too many recipients for one packet, though well within the CU cap.
+    let instructions: Vec<_> = (0..60)
+        .map(|_| system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000))
+        .collect();
+    let blockhash = rpc_client.get_latest_blockhash().unwrap();
+
+    let transactions = rpc_client
+        .pack_into_transactions(&instructions, &payer.pubkey(), &[&payer], blockhash)
+        .unwrap();
+    assert!(transactions.len() > 1);
+    let mut packed = 0;
+    for tx in &transactions {
+        assert!(!transaction_metrics(tx).exceeds_packet_limit());
+        assert!(tx.verify().is_ok());
+        assert_eq!(tx.message.recent_blockhash, blockhash);
+        assert!(parse_compute_budget(tx).unit_limit.is_some());
+        packed += tx.message.instructions.len() - 1;
+    }
+    assert_eq!(packed, instructions.len());
+}
+
+#[test]
+fn test_optimize_with_fee_cap() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let Some(payer) = common::devnet_payer() else {
+        return;
+    };
+    let transfer_ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    let tx = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&payer.pubkey())));
+
+    // One lamport above the signature fee leaves almost no room for a priority fee.
+    let mut capped = tx.clone();
+    let fee = rpc_client
+        .optimize_with_fee_cap(&mut capped, &[&payer], 5_001)
+        .unwrap();
+    assert_eq!(fee.base_fee_lamports, 5_000);
+    assert!(fee.total_lamports <= 5_001);
+    assert_eq!(compute_priority_fee_from_tx(&capped), fee.priority_fee_lamports);
+    let settings = parse_compute_budget(&capped);
+    assert_eq!(settings.unit_limit, Some(fee.cu as u32));
+    assert!(settings.unit_price.is_some());
+
+    let err = rpc_client
+        .optimize_with_fee_cap(&mut tx.clone(), &[&payer], 4_999)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SolanaClientExtError>(),
+        Some(SolanaClientExtError::FeeCapExceeded {
+            base_fee: 5_000,
+            max_fee: 4_999
+        })
+    ));
+}
+
+#[test]
+fn test_simulate_upgradeable_program() {
+    let rpc_client = solana_client::rpc_client::RpcClient::new("https://api.devnet.solana.com");
+    let Some(payer) = common::devnet_payer() else {
+        return;
+    };
+    let mint = Keypair::new();
+    let program = rpc_client.get_account(&TOKEN_2022_PROGRAM_ID).unwrap();
+    assert_eq!(program.owner, bpf_loader_upgradeable::id());
+
+    let tx = Transaction::new_signed_with_payer(
+        &create_token_2022_mint(&payer.pubkey(), &mint.pubkey()),
+        Some(&payer.pubkey()),
+        &[&payer, &mint],
+        rpc_client.get_latest_blockhash().unwrap(),
+    );
+    let channel = RollUpChannel::new(vec![], &rpc_client);
+    let result = &channel.simulate_transactions_raw(&[tx], &AnalysisConfig::default())[0];
+
+    assert!(result.success, "{}", result.result);
+    assert_eq!(
+        result.invoked_programs,
+        vec![solana_sdk::system_program::id(), TOKEN_2022_PROGRAM_ID]
+    );
+    // The mint was initialized by the program's BPF code, on top of the
+    // System Program's 150 CUs.
+    assert!(result.cu > 150);
+}
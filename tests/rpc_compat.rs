@@ -0,0 +1,51 @@
+use solana_client_ext::rpc_compat::to_rpc_simulate_response;
+use solana_client_ext::RawSimulationResult;
+use solana_sdk::{
+    account::AccountSharedData, instruction::InstructionError, pubkey::Pubkey,
+    transaction::TransactionError, transaction_context::TransactionReturnData,
+};
+
+#[test]
+fn test_rpc_simulate_response_envelope() {
+    let program_id = Pubkey::new_unique();
+    let touched = Pubkey::new_unique();
+    let untouched = Pubkey::new_unique();
+
+    let mut result = RawSimulationResult::base_success(150);
+    result.logs = vec![format!("Program {} invoke [1]", program_id)];
+    result.return_data = Some(TransactionReturnData {
+        program_id,
+        data: vec![42],
+    });
+    result.post_accounts = vec![(touched, AccountSharedData::new(1_000, 3, &program_id))];
+
+    let response = to_rpc_simulate_response(&result, 218, Some(&[touched, untouched]));
+
+    assert_eq!(response["context"]["slot"], 218);
+    let value = &response["value"];
+    assert!(value["err"].is_null());
+    assert_eq!(value["unitsConsumed"], 150);
+    assert_eq!(value["logs"][0], result.logs[0].as_str());
+    assert_eq!(value["returnData"]["programId"], program_id.to_string());
+    assert_eq!(value["returnData"]["data"][0], "Kg==");
+    assert_eq!(value["accounts"][0]["lamports"], 1_000);
+    assert_eq!(value["accounts"][0]["data"][0], "AAAA");
+    assert!(value["accounts"][1].is_null());
+}
+
+#[test]
+fn test_rpc_simulate_response_error() {
+    let mut result = RawSimulationResult::base_failure("Transaction 0 failed");
+    result.error = Some(TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(6000),
+    ));
+
+    let response = to_rpc_simulate_response(&result, 1, None);
+
+    assert_eq!(
+        response["value"]["err"],
+        serde_json::json!({ "InstructionError": [0, { "Custom": 6000 }] })
+    );
+    assert!(response["value"]["accounts"].is_null());
+}
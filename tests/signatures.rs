@@ -0,0 +1,32 @@
+use solana_client_ext::verify_signatures;
+use solana_sdk::{
+    hash::Hash, message::Message, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    system_instruction, transaction::Transaction,
+};
+
+#[test]
+fn test_verify_signatures() {
+    let payer = Keypair::new();
+    let new_account = Keypair::new();
+    let ix = system_instruction::create_account(
+        &payer.pubkey(),
+        &new_account.pubkey(),
+        1_000_000,
+        0,
+        &Pubkey::new_unique(),
+    );
+    let message = Message::new(&[ix], Some(&payer.pubkey()));
+
+    let signed = Transaction::new(&[&payer, &new_account], message.clone(), Hash::default());
+    assert_eq!(verify_signatures(&signed), Ok(()));
+
+    // Only the payer signs; the new account's signature stays empty.
+    let mut partially_signed = Transaction::new_unsigned(message);
+    partially_signed.partial_sign(&[&payer], Hash::default());
+    assert_eq!(verify_signatures(&partially_signed), Err(vec![1]));
+
+    // Tampering with the message invalidates every signature.
+    let mut tampered = signed.clone();
+    tampered.message.recent_blockhash = Hash::new_unique();
+    assert_eq!(verify_signatures(&tampered), Err(vec![0, 1]));
+}